@@ -1,3 +1,5 @@
+#![allow(clippy::redundant_static_lifetimes)]
+
 use heck::ToKebabCase;
 use std::{io::Write, fs::File, process::Command};
 
@@ -34,15 +36,17 @@ fn wl_codegen(protocol: &str) {
     println!("cargo:rerun-if-changed={spec}");
     println!("cargo:rerun-if-changed={proto}");
 
-    let code = match yutani_codegen::protocol(spec) {
+    let options = yutani_codegen::CodegenOptions::default().allow(&["dead_code"]);
+    let code = match yutani_codegen::Protocol::load(spec).and_then(|protocol| yutani_codegen::render(&protocol, &options)) {
         Ok(code) => code,
-        Err(error) => panic!("Failed to read protocol specification '{spec}': {error:?}")
+        Err(error) => panic!("Failed to generate code for protocol specification '{spec}': {error:?}")
     };
     let mut proto_file = match File::create(proto) {
         Ok(proto_file) => proto_file,
         Err(error) => panic!("Failed to create Rust source file '{proto}': {error:?}")
     };
-    if let Err(error) = writeln!(proto_file, "// Auto-Generated file. Do not edit.\n#![allow(dead_code)]\n\n{}", code) {
+    // The rendered code is headed by its own `// Auto-Generated` comment
+    if let Err(error) = write!(proto_file, "{}", code) {
         panic!("Failed to write Rust source file '{proto}': {error:?}")
     }
     if let Err(error) = Command::new("rustfmt").arg(proto).status() {
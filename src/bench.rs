@@ -0,0 +1,182 @@
+//! Writing criterion benchmarks of the event senders of a protocol, to measure how long events take to encode.
+//!
+//! Like [`scaffold`](crate::scaffold), the benchmarks are source text to be copied into a bench harness, such as
+//! `benches/protocol.rs`, and edited from there.
+
+use std::fmt::Write;
+
+use quote::quote;
+
+use crate::{
+    entry_name, enum_path, events_trait_name, ident, scaffold::{implementation, rust}, trait_name, validate, Arg, ArrayType,
+    CodegenOptions, DataType, Diagnostic, Error, Event, Interface, Protocol, Result, Severity
+};
+
+/// The number of entries of each array argument.
+const ARRAY_LEN: usize = 64;
+/// The events written to a stream before it is replaced, as its transmit buffer only empties once it is sent.
+const BATCH: u64 = 1024;
+
+/// Write a Rust source file with a criterion benchmark for each event of a protocol, timing its `_to_stream` sender
+/// writing the event to the transmit buffer of a stream.
+///
+/// The generated code of the protocol is imported from `module`, such as `my_crate::protocol`, which must be a path
+/// from outside of the crate for a bench target in `benches/`. Each interface is implemented for a unit struct with
+/// every request stubbed as in a [skeleton](crate::scaffold), and the state of the event loop is the type alias
+/// `State`, `()` until edited to meet the [bounds](CodegenOptions::generic_bounds) of `options`.
+///
+/// Arguments have representative values, such as 64 character strings and arrays of 64 entries, with arrays of enum
+/// entries holding the first entry of the enum.
+/// Events that cannot be sent without a client are left out with a comment saying why, which are destructors, events
+/// with file descriptors or untyped `new_id` arguments, and events with fixed point numbers unless they are exposed as
+/// `f64` with [`CodegenOptions::fixed_as_f64`].
+pub fn benches(protocol: &Protocol, module: &str, options: &CodegenOptions) -> Result<String> {
+    let mut errors: Vec<_> = validate(protocol, options).into_iter().filter(|d| d.severity == Severity::Error).collect();
+    if syn::parse_str::<syn::Path>(module).is_err() {
+        errors.push(Diagnostic::error(&protocol.name, format!("module `{}` is not a valid path", module)));
+    }
+    if !errors.is_empty() {
+        return Err(Error::Validation(errors))
+    }
+
+    let mut source = String::new();
+    writeln!(source, "// Criterion benchmarks of the events of `{}`, generated by yutani-codegen.", protocol.name).unwrap();
+    writeln!(source, "use std::{{hint::black_box, os::unix::net::UnixListener, time::{{Duration, Instant}}}};\n").unwrap();
+    writeln!(source, "use criterion::{{criterion_group, criterion_main, Criterion}};").unwrap();
+    writeln!(source, "use yutani::{{lease::Resident, server::Client, wire::Stream, Id}};\n").unwrap();
+    writeln!(source, "use {}::*;\n", module).unwrap();
+    writeln!(source, "/// The state of the event loop, which must meet the bounds of the interface traits.").unwrap();
+    writeln!(source, "type State = ();\n").unwrap();
+    writeln!(source, "/// Time `iters` calls of `send`, each writing an event to a stream.").unwrap();
+    writeln!(source, "///").unwrap();
+    writeln!(source, "/// The transmit buffer of a stream only empties once it is sent, so a new stream is connected every {} events,", BATCH).unwrap();
+    writeln!(source, "/// outside of the measurement.").unwrap();
+    source += &format!(r#"fn encode(iters: u64, mut send: impl FnMut(&mut Stream)) -> Duration {{
+    let path = std::env::temp_dir().join(format!("yutani-bench-{{}}", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path).unwrap();
+    let mut elapsed = Duration::ZERO;
+    let mut remaining = iters;
+    while remaining > 0 {{
+        let mut stream = Stream::connect(&path).unwrap();
+        let _peer = listener.accept().unwrap();
+        let batch = remaining.min({BATCH});
+        let start = Instant::now();
+        for _ in 0..batch {{
+            send(&mut stream);
+        }}
+        elapsed += start.elapsed();
+        remaining -= batch;
+    }}
+    let _ = std::fs::remove_file(&path);
+    elapsed
+}}
+"#);
+
+    let mut benches = Vec::new();
+    for interface in protocol.interfaces.iter().filter(|i| !options.core_interfaces.contains_key(&i.name) && !i.events.is_empty()) {
+        let ty = format!("Bench{}", trait_name(protocol, interface, options));
+        writeln!(source, "\npub struct {};\n", ty).unwrap();
+        implementation(&mut source, protocol, interface, &ty, options);
+        for event in &interface.events {
+            let name = format!("{}.{}", interface.name, event.name);
+            if let Some(reason) = unsupported(protocol, interface, event, options) {
+                writeln!(source, "\n// `{}` is not benchmarked, as {}", name, reason).unwrap();
+                continue
+            }
+            let function = ident::name(&format!("{}_{}", ident::snake_case(&interface.name), ident::snake_case(&event.name)));
+            writeln!(source, "\n/// Encode the `{}` event.", name).unwrap();
+            writeln!(source, "pub fn {}(c: &mut Criterion) {{", function).unwrap();
+            writeln!(source, "    let mut object: Resident<{}, State, Client<State>> = {}.into_object(Id::new(3));", ty, ty).unwrap();
+            writeln!(source, "    let lease = object.lease().unwrap();").unwrap();
+            for arg in &event.args {
+                if let Some(value) = value(protocol, interface, arg, options) {
+                    writeln!(source, "    let {} = {};", variable(arg), value).unwrap();
+                }
+            }
+            writeln!(source, "    c.bench_function({:?}, |b| b.iter_custom(|iters| encode(iters, |stream| {{", name).unwrap();
+            write!(source, "        <{} as {}<State>>::{}(&lease, stream", ty, ident::name(&events_trait_name(protocol, interface, options)), ident::name(&format!("{}_to_stream", ident::snake_case(&event.name)))).unwrap();
+            for arg in &event.args {
+                write!(source, ", black_box({})", arg_expr(arg)).unwrap();
+            }
+            writeln!(source, ").unwrap()").unwrap();
+            writeln!(source, "    }})));").unwrap();
+            writeln!(source, "}}").unwrap();
+            benches.push(function);
+        }
+    }
+    writeln!(source, "\ncriterion_group!(benches{});", benches.iter().map(|f| format!(", {}", f)).collect::<String>()).unwrap();
+    writeln!(source, "criterion_main!(benches);").unwrap();
+    Ok(source)
+}
+
+/// Why an event cannot be benchmarked, if it cannot.
+fn unsupported(protocol: &Protocol, interface: &Interface, event: &Event, options: &CodegenOptions) -> Option<&'static str> {
+    if event.destructor {
+        return Some("it is a destructor, which removes the object from a client")
+    }
+    event.args.iter().find_map(|arg| match (arg.ty, arg.array_type) {
+        (DataType::Fd, _) => Some("file descriptors are queued for the client to send, rather than encoded"),
+        (DataType::NewId, _) if arg.interface.is_none() => Some("a `NewId` can only be read from a client"),
+        (DataType::Fixed, _) | (DataType::Array, Some(ArrayType::Fixed)) if !options.fixed_as_f64 => {
+            Some("a `Fixed` can only be read from a client, unless fixed point numbers are exposed as `f64`")
+        },
+        (DataType::Array, Some(ArrayType::Int | ArrayType::Uint)) if arg.enumeration.is_some() && first_entry(protocol, interface, arg, options).is_none() => {
+            Some("its array of enum entries is of an enum of another protocol")
+        },
+        _ => None
+    })
+}
+
+/// The value of a string or array argument, which is bound to a variable before the measurement.
+fn value(protocol: &Protocol, interface: &Interface, arg: &Arg, options: &CodegenOptions) -> Option<String> {
+    match (arg.ty, arg.array_type) {
+        (DataType::String, _) => Some(r#""wayland!".repeat(8)"#.to_owned()),
+        (DataType::Array, None) => Some(format!("[0xa5u8; {}]", ARRAY_LEN)),
+        (DataType::Array, Some(_)) if arg.enumeration.is_some() => {
+            first_entry(protocol, interface, arg, options).map(|entry| format!("[{}; {}]", entry, ARRAY_LEN))
+        },
+        (DataType::Array, Some(ArrayType::Int)) => Some(format!("(0..{}).collect::<Vec<i32>>()", ARRAY_LEN)),
+        (DataType::Array, Some(ArrayType::Uint)) => Some(format!("(0..{}).collect::<Vec<u32>>()", ARRAY_LEN)),
+        (DataType::Array, Some(ArrayType::Fixed)) => Some(format!("(0..{}).map(f64::from).collect::<Vec<f64>>()", ARRAY_LEN)),
+        _ => None
+    }
+}
+
+/// The expression of an argument passed to the sender, naming the variable bound by [`value`] if there is one.
+fn arg_expr(arg: &Arg) -> String {
+    let name = variable(arg);
+    match arg.ty {
+        DataType::Int => "-1234".into(),
+        DataType::Uint => "1234".into(),
+        DataType::Fixed => "12.5".into(),
+        DataType::String if arg.nullable => format!("Some(&*{})", name),
+        DataType::String | DataType::Array => format!("&{}", name),
+        DataType::Object if arg.nullable => "Some(Id::new(4))".into(),
+        DataType::Object | DataType::NewId => "Id::new(4)".into(),
+        DataType::Fd => unreachable!("events with file descriptors are not benchmarked")
+    }
+}
+
+/// The variable of a string or array argument, named after it unless that is the name of another variable in scope.
+fn variable(arg: &Arg) -> String {
+    let name = ident::snake_case(&arg.name);
+    if matches!(name.as_str(), "c" | "b" | "iters" | "stream" | "object" | "lease") {
+        format!("{}_arg", name)
+    } else {
+        ident::name(&name)
+    }
+}
+
+/// The path to the first entry of the enum of an array argument, relative to the generated code.
+fn first_entry(protocol: &Protocol, interface: &Interface, arg: &Arg, options: &CodegenOptions) -> Option<String> {
+    let (_, enumeration) = protocol.find_enum(interface, arg.enumeration.as_ref()?)?;
+    let (path, _) = enum_path(protocol, interface, arg, options)?;
+    let entry = enumeration.entries.iter().find(|entry| !enumeration.is_alias(entry))?;
+    let entry = if enumeration.is_rust_enum(options) {
+        ident::pascal(&entry_name(enumeration, entry), options)
+    } else {
+        ident::ident(&entry_name(enumeration, entry))
+    };
+    Some(rust(quote!{#path::#entry}))
+}
@@ -0,0 +1,374 @@
+use std::{env, fs::{self, File}, io, path::PathBuf, process::ExitCode};
+
+use yutani_codegen::*;
+
+const USAGE: &str = "usage: wl-codegen [--format toml|json|yaml|xml] [--copyright doc|comment|omit] [--allow <lint>]... [--lint <lint>=allow|warn|deny]...
+                  [--locked <lockfile>] <spec>
+       wl-codegen seeds [--format toml|json|yaml|xml] <spec> -o <dir>
+       wl-codegen import [--format toml|json|yaml|xml] <spec> [-o <file>]
+       wl-codegen fmt [--check] <spec>
+       wl-codegen scaffold [--format toml|json|yaml|xml] <spec> --interface <name> --type <name> [--module <path>] [-o <file>]
+       wl-codegen bench [--format toml|json|yaml|xml] <spec> --module <path> [-o <file>]
+       wl-codegen graph [--format toml|json|yaml|xml] <spec> [-o <file>]
+       wl-codegen schema [-o <file>]
+       wl-codegen new <name> [-o <file>]
+       wl-codegen lock [--format toml|json|yaml|xml] <spec> [-o <file>]
+       wl-codegen verify [--rustfmt] [--ignore-formatting] [--format human|json] [--copyright doc|comment|omit]
+                         [--allow <lint>]... [--locked <lockfile>] (<spec> <file>)...
+
+Generate the code for a protocol specification and write it to standard output.
+`seeds` instead writes a fuzzing seed corpus with a valid message for each request and event to <dir>, each followed
+by a marker with the index of each fd argument.
+`import` instead converts the spec, such as a Wayland XML specification, to TOML and writes it to <file> or standard
+output.
+`fmt` instead rewrites a TOML spec in the canonical layout, or with `--check` fails if it is not in that layout.
+`scaffold` instead writes a skeleton implementation of an interface for a new type to <file> or standard output,
+importing the generated code from <path>, which defaults to `crate::<module name>`.
+`bench` instead writes criterion benchmarks of encoding each event to <file> or standard output, importing the
+generated code from <path>, such as `my_crate::protocol`.
+`graph` instead writes a Graphviz graph of which messages create which interfaces to <file> or standard output.
+`schema` instead writes a JSON Schema of TOML specs, for editors, to <file> or standard output.
+`new` instead writes a skeleton TOML spec for a new protocol named <name> to <file> or standard output.
+`lock` instead writes a lockfile of the opcodes and signatures of every message of the spec to <file> or standard
+output. With `--locked`, the spec fails to generate or verify if a message in <lockfile> was moved, removed or changed.
+`verify` instead checks that each <file> is the code generated for its <spec> with the same options, without writing
+anything, printing a diff of each stale file, or with `--format json` a JSON object listing them. The code
+generated now is formatted by `rustfmt` with `--rustfmt`, and only tokens are compared with `--ignore-formatting`.
+It exits with 0 if every file is up to date, 1 if any is stale and 2 if any could not be checked.
+A spec of `-` is read from standard input, as TOML unless `--format` is given.
+The copyright is placed in the crate docs unless `--copyright` is given.
+Each `--allow` allows a lint, such as `dead_code`, for the generated code.
+Each `--lint` sets the level of a lint of the spec, such as `naming::snake_case`, or of a group of lints, such as
+`naming`.";
+
+#[derive(PartialEq, Eq)]
+enum Command {
+    Generate,
+    Seeds,
+    Import,
+    Fmt,
+    Scaffold,
+    Bench,
+    Graph,
+    Schema,
+    New,
+    Lock,
+    Verify
+}
+
+fn main() -> ExitCode {
+    let mut format = None;
+    let mut options = CodegenOptions::default();
+    let mut spec = None;
+    let mut args = env::args().skip(1).peekable();
+    let command = match args.next_if(|arg| matches!(arg.as_str(), "seeds" | "import" | "fmt" | "scaffold" | "bench" | "graph" | "schema" | "new" | "lock" | "verify")).as_deref() {
+        Some("seeds") => Command::Seeds,
+        Some("import") => Command::Import,
+        Some("fmt") => Command::Fmt,
+        Some("scaffold") => Command::Scaffold,
+        Some("bench") => Command::Bench,
+        Some("graph") => Command::Graph,
+        Some("schema") => Command::Schema,
+        Some("new") => Command::New,
+        Some("lock") => Command::Lock,
+        Some("verify") => Command::Verify,
+        _ => Command::Generate
+    };
+    let mut output = None;
+    let mut check = false;
+    let mut interface = None;
+    let mut ty = None;
+    let mut module = None;
+    let mut verify = build::VerifyOptions::default();
+    let mut json = false;
+    let mut pairs = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return ExitCode::SUCCESS
+            },
+            // Each spec of `verify` is read in the format of its extension, so its format is of the output
+            "--format" if command == Command::Verify => json = match args.next().as_deref() {
+                Some("human") => false,
+                Some("json") => true,
+                Some(format) => return usage(&format!("unsupported output format `{format}`")),
+                None => return usage("expected a format after `--format`")
+            },
+            "--format" => format = match args.next().as_deref() {
+                Some("toml") => Some(Format::Toml),
+                #[cfg(feature = "json")]
+                Some("json") => Some(Format::Json),
+                #[cfg(feature = "yaml")]
+                Some("yaml") => Some(Format::Yaml),
+                Some("xml") => Some(Format::Xml),
+                Some(format) => return usage(&format!("unsupported format `{format}`")),
+                None => return usage("expected a format after `--format`")
+            },
+            "--copyright" => options.copyright = match args.next().as_deref() {
+                Some("doc") => CopyrightStyle::Doc,
+                Some("comment") => CopyrightStyle::Comment,
+                Some("omit") => CopyrightStyle::Omit,
+                Some(style) => return usage(&format!("unsupported copyright style `{style}`")),
+                None => return usage("expected a style after `--copyright`")
+            },
+            "--check" if command == Command::Fmt => check = true,
+            "--interface" if command == Command::Scaffold => match args.next() {
+                Some(name) => interface = Some(name),
+                None => return usage("expected an interface after `--interface`")
+            },
+            "--type" if command == Command::Scaffold => match args.next() {
+                Some(name) => ty = Some(name),
+                None => return usage("expected a type after `--type`")
+            },
+            "--module" if matches!(command, Command::Scaffold | Command::Bench) => match args.next() {
+                Some(path) => module = Some(path),
+                None => return usage("expected a path after `--module`")
+            },
+            "--rustfmt" if command == Command::Verify => verify.rustfmt = true,
+            "--ignore-formatting" if command == Command::Verify => verify.ignore_formatting = true,
+            "--locked" if matches!(command, Command::Generate | Command::Verify) => match args.next() {
+                Some(path) => match Lockfile::load(&path) {
+                    Ok(lockfile) => options.lockfile = Some(lockfile),
+                    Err(error) => return fail(&path, &error)
+                },
+                None => return usage("expected a lockfile after `--locked`")
+            },
+            "-o" if matches!(command, Command::Seeds | Command::Import | Command::Scaffold | Command::Bench | Command::Graph | Command::Schema | Command::New | Command::Lock) => match args.next() {
+                Some(path) => output = Some(PathBuf::from(path)),
+                None => return usage("expected a path after `-o`")
+            },
+            "--allow" => match args.next() {
+                Some(lint) => options.allow.push(lint),
+                None => return usage("expected a lint after `--allow`")
+            },
+            "--lint" => match args.next().as_deref().map(|lint| lint.split_once('=')) {
+                Some(Some((lint, level))) => {
+                    let level = match level {
+                        "allow" => LintLevel::Allow,
+                        "warn" => LintLevel::Warn,
+                        "deny" => LintLevel::Deny,
+                        level => return usage(&format!("unsupported lint level `{level}`"))
+                    };
+                    options.lints.insert(lint.to_owned(), level);
+                },
+                _ => return usage("expected a lint and level after `--lint`, such as `naming=warn`")
+            },
+            // Each spec of `verify` is followed by the file generated from it
+            _ if command == Command::Verify => pairs.push(arg),
+            _ if spec.is_none() && command != Command::Schema => spec = Some(arg),
+            _ => return usage(&format!("unexpected argument `{arg}`"))
+        }
+    }
+    if command == Command::Schema {
+        return write_output(output.as_ref(), &schema::json())
+    }
+    // The argument of `new` is the name of the protocol to write a spec for
+    if command == Command::New {
+        let Some(name) = spec else {
+            return usage("expected a protocol name")
+        };
+        if let Some(output) = output.as_ref().filter(|output| output.exists()) {
+            eprintln!("error: {}: already exists", output.display());
+            return ExitCode::FAILURE
+        }
+        return write_output(output.as_ref(), &new_spec(&name))
+    }
+    if command == Command::Verify {
+        if pairs.is_empty() || pairs.len() % 2 != 0 {
+            return usage("expected a spec and a generated file for each protocol")
+        }
+        return verify_all(&pairs, &options, &verify, json)
+    }
+    let Some(spec) = spec else {
+        return usage("expected a spec")
+    };
+    if command == Command::Fmt {
+        return fmt(&spec, check)
+    }
+    let protocol = match (spec.as_str(), format) {
+        ("-", format) => Protocol::from_reader(io::stdin().lock(), format.unwrap_or_default()),
+        (path, Some(format)) => File::open(path).map_err(Error::from).and_then(|file| Protocol::from_reader(file, format)),
+        (path, None) => Protocol::load(path)
+    };
+    let protocol = match protocol {
+        Ok(protocol) => protocol,
+        Err(error) => return fail(&spec, &error)
+    };
+    match command {
+        Command::Generate => (),
+        Command::Seeds => return match output {
+            Some(output) => write_seeds(&protocol, &output),
+            None => usage("expected an output directory")
+        },
+        Command::Import => return import(&protocol, output.as_ref()),
+        Command::Scaffold => {
+            let (Some(interface), Some(ty)) = (interface, ty) else {
+                return usage("expected an interface and a type")
+            };
+            let module = module.unwrap_or_else(|| format!("crate::{}", module_path(&protocol, &options)));
+            return match scaffold(&protocol, &interface, &ty, &module, &options) {
+                Ok(source) => write_output(output.as_ref(), &source),
+                Err(error) => fail(&spec, &error)
+            }
+        },
+        Command::Bench => {
+            let Some(module) = module else {
+                return usage("expected the path of the generated code, such as `--module my_crate::protocol`")
+            };
+            return match benches(&protocol, &module, &options) {
+                Ok(source) => write_output(output.as_ref(), &source),
+                Err(error) => fail(&spec, &error)
+            }
+        },
+        Command::Graph => return write_output(output.as_ref(), &graph::to_dot(&protocol)),
+        Command::Lock => return write_output(output.as_ref(), &Lockfile::new([&protocol]).to_string()),
+        Command::Fmt => unreachable!("specs are formatted before being parsed"),
+        Command::Schema => unreachable!("the schema is written without a spec"),
+        Command::New => unreachable!("new specs are written without parsing a spec"),
+        Command::Verify => unreachable!("generated files are verified by `verify_all`")
+    }
+    for diagnostic in validate(&protocol, &options).iter().filter(|d| d.severity == Severity::Warning) {
+        eprintln!("{diagnostic}");
+    }
+    match render(&protocol, &options) {
+        Ok(code) => {
+            print!("{code}");
+            ExitCode::SUCCESS
+        },
+        Err(error) => fail(&spec, &error)
+    }
+}
+
+fn write_seeds(protocol: &Protocol, output: &PathBuf) -> ExitCode {
+    let written = fs::create_dir_all(output).and_then(|_| {
+        fuzz::seeds(protocol).into_iter().try_for_each(|(name, seed)| fs::write(output.join(name), seed))
+    });
+    match written {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {}: {error}", output.display());
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Verify each generated file against its spec, exiting with 1 if any is stale or 2 if any could not be checked.
+fn verify_all(pairs: &[String], options: &CodegenOptions, verify: &build::VerifyOptions, json: bool) -> ExitCode {
+    let mut stale = Vec::new();
+    let mut errors = Vec::new();
+    for pair in pairs.chunks(2) {
+        let (spec, file) = (&pair[0], &pair[1]);
+        match build::verify(spec, file, options, verify) {
+            Ok(()) => (),
+            Err(Error::Stale(mismatches)) => {
+                let diff: String = mismatches.iter().map(|mismatch| match mismatch {
+                    Mismatch::Contents { diff } => diff.clone(),
+                    mismatch => format!("{mismatch}\n")
+                }).collect();
+                stale.push((spec, file, diff));
+            },
+            Err(Error::Validation(diagnostics)) => {
+                errors.push((spec, file, diagnostics.iter().map(ToString::to_string).collect::<Vec<_>>().join("\n")));
+            },
+            Err(error) => errors.push((spec, file, format!("{error:?}")))
+        }
+    }
+    if json {
+        let entry = |(spec, file, text): &(&String, &String, String), key| format!(
+            "{{\"spec\": {}, \"file\": {}, \"{key}\": {}}}", json_string(spec), json_string(file), json_string(text)
+        );
+        let stale: Vec<_> = stale.iter().map(|stale| entry(stale, "diff")).collect();
+        let errors: Vec<_> = errors.iter().map(|error| entry(error, "error")).collect();
+        println!("{{\"stale\": [{}], \"errors\": [{}]}}", stale.join(", "), errors.join(", "));
+    } else {
+        for (spec, file, diff) in &stale {
+            eprintln!("error: {file}: stale, regenerate it from {spec}");
+            print!("{diff}");
+        }
+        for (_, file, error) in &errors {
+            eprintln!("error: {file}: {error}");
+        }
+    }
+    match (stale.is_empty(), errors.is_empty()) {
+        (_, false) => ExitCode::from(2),
+        (false, true) => ExitCode::FAILURE,
+        (true, true) => ExitCode::SUCCESS
+    }
+}
+
+/// A string as a JSON string literal.
+fn json_string(string: &str) -> String {
+    let mut json = String::from('"');
+    for c in string.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", u32::from(c))),
+            c => json.push(c)
+        }
+    }
+    json.push('"');
+    json
+}
+
+fn import(protocol: &Protocol, output: Option<&PathBuf>) -> ExitCode {
+    write_output(output, &protocol.to_toml())
+}
+
+/// Write to a file, or to standard output if there is no file.
+fn write_output(output: Option<&PathBuf>, contents: &str) -> ExitCode {
+    match output {
+        Some(output) => if let Err(error) = fs::write(output, contents) {
+            eprintln!("error: {}: {error}", output.display());
+            return ExitCode::FAILURE
+        },
+        None => print!("{contents}")
+    }
+    ExitCode::SUCCESS
+}
+
+fn fmt(spec: &str, check: bool) -> ExitCode {
+    let original = if spec == "-" {
+        io::read_to_string(io::stdin().lock())
+    } else {
+        fs::read_to_string(spec)
+    };
+    let formatted = match original.map_err(Error::from).and_then(|original| Ok((format_spec(&original)?, original))) {
+        Ok((formatted, original)) if check => return if formatted == original {
+            ExitCode::SUCCESS
+        } else {
+            eprintln!("{spec}: not in the canonical layout, run `wl-codegen fmt {spec}`");
+            ExitCode::FAILURE
+        },
+        Ok((formatted, _)) => formatted,
+        Err(error) => return fail(spec, &error)
+    };
+    if spec == "-" {
+        print!("{formatted}");
+    } else if let Err(error) = fs::write(spec, formatted) {
+        eprintln!("error: {spec}: {error}");
+        return ExitCode::FAILURE
+    }
+    ExitCode::SUCCESS
+}
+
+fn usage(message: &str) -> ExitCode {
+    eprintln!("error: {message}\n\n{USAGE}");
+    ExitCode::FAILURE
+}
+
+fn fail(spec: &str, error: &Error) -> ExitCode {
+    match error {
+        Error::Validation(diagnostics) => for diagnostic in diagnostics {
+            eprintln!("{diagnostic}");
+        },
+        Error::Comments(lines) => for line in lines {
+            eprintln!("error: {spec}:{line}: comments would be dropped by formatting, remove them to format the specification");
+        },
+        error => eprintln!("error: {spec}: {error:?}")
+    }
+    ExitCode::FAILURE
+}
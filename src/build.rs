@@ -0,0 +1,281 @@
+//! Generating code from a build script into `OUT_DIR`, so that generated code is not written into the source tree,
+//! to be included with [`include_protocol!`](crate::include_protocol).
+
+use std::{collections::BTreeMap, env, fs, io::{self, Write}, path::{Path, PathBuf}, process::{Command, Stdio}};
+
+use proc_macro2::{Delimiter, Spacing, TokenStream, TokenTree};
+
+use crate::{diff, marker, module_path, render, validate, validate_protocols, CodegenOptions, Diagnostic, Error, Mismatch, Protocol, Result, Severity};
+
+/// The most warnings of a specification printed by [`cargo_warnings`], after which they are counted.
+pub const WARNING_LIMIT: usize = 20;
+
+/// Generate the code for the protocol specified at `spec` into `OUT_DIR`, returning the name to include it by with
+/// [`include_protocol!`](crate::include_protocol), which is its [`module_path`](module_path()) with `/` between
+/// modules, such as `wayland` or `acme/wayland` for a protocol in the `acme` [namespace](Protocol::namespace).
+///
+/// The code is [wrapped](CodegenOptions::wrap_module) in its module, as a file included with `include!` cannot
+/// start with inner attributes. Cargo is told to rerun the build script when the specification changes, and to show
+/// the warnings of [`validate`] as [`cargo_warnings`].
+pub fn protocol<P: AsRef<Path>>(spec: P, options: &CodegenOptions) -> Result<String> {
+    let spec = spec.as_ref();
+    println!("cargo:rerun-if-changed={}", spec.display());
+    let out_dir = out_dir()?;
+    let protocol = Protocol::load(spec)?;
+    print_warnings(spec, &protocol, options);
+    write_protocol(&out_dir, &protocol, options)
+}
+
+/// Generate the code for every protocol specified at `spec` into `OUT_DIR`, such as a TOML file of several protocols
+/// in `[[protocol]]` tables, see [`Protocol::load_all`], returning the name to include them all by with
+/// [`include_protocol!`](crate::include_protocol).
+///
+/// Each protocol is generated as by [`build::protocol`](protocol), along with a module file named after the file of
+/// the specification, such as `extensions/mod` for `extensions.toml`, that includes each protocol in the module of
+/// its [namespace](Protocol::namespace). The protocols are checked together with
+/// [`validate_protocols`], failing if they collide.
+pub fn protocols<P: AsRef<Path>>(spec: P, options: &CodegenOptions) -> Result<String> {
+    let spec = spec.as_ref();
+    println!("cargo:rerun-if-changed={}", spec.display());
+    let out_dir = out_dir()?;
+    let protocols = Protocol::load_all(spec)?;
+    let errors: Vec<_> = validate_protocols(&protocols, options).into_iter().filter(|d| d.severity == Severity::Error).collect();
+    if !errors.is_empty() {
+        return Err(Error::Validation(errors))
+    }
+    let mut namespaces: BTreeMap<Option<&str>, Vec<String>> = BTreeMap::new();
+    for protocol in &protocols {
+        print_warnings(spec, protocol, options);
+        let name = write_protocol(&out_dir, protocol, options)?;
+        let include = format!("::core::include!(::core::concat!(::core::env!(\"OUT_DIR\"), \"/{}.rs\"));", name);
+        namespaces.entry(protocol.namespace.as_deref()).or_default().push(include);
+    }
+    let mut modules = String::from("// Auto-Generated file. Do not edit.\n");
+    for (namespace, includes) in namespaces {
+        match namespace {
+            Some(namespace) => modules.push_str(&format!("pub mod {} {{\n    {}\n}}\n", namespace, includes.join("\n    "))),
+            None => modules.push_str(&format!("{}\n", includes.join("\n")))
+        }
+    }
+    let stem = spec.file_stem().unwrap_or(spec.as_os_str()).to_string_lossy();
+    let name = format!("{}/mod", stem);
+    fs::create_dir_all(out_dir.join(&*stem))?;
+    fs::write(out_dir.join(format!("{}.rs", name)), modules)?;
+    Ok(name)
+}
+
+/// The `cargo:warning` lines showing the warnings among the diagnostics of a protocol in the output of Cargo, such as
+/// `cargo:warning=protocol/wayland.toml:120: wl_surface.attach: ...`, located by [`Protocol::line_of`].
+///
+/// Nobody reads the output of a build script that succeeds, so warnings only reach the user this way. At most
+/// [`WARNING_LIMIT`] warnings are shown, followed by a line counting the rest.
+pub fn cargo_warnings(spec: &Path, protocol: &Protocol, diagnostics: &[Diagnostic]) -> Vec<String> {
+    let warnings: Vec<_> = diagnostics.iter().filter(|d| d.severity == Severity::Warning).collect();
+    let mut lines: Vec<_> = warnings.iter().take(WARNING_LIMIT).map(|warning| {
+        let location = match protocol.line_of(&warning.path) {
+            Some(line) => format!("{}:{}", spec.display(), line),
+            None => spec.display().to_string()
+        };
+        let lint = warning.lint.map(|lint| format!(" [{}]", lint)).unwrap_or_default();
+        // Each line of output is a separate instruction to Cargo
+        let message = warning.message.replace('\n', " ");
+        format!("cargo:warning={}: {}: {}{}", location, warning.path, message, lint)
+    }).collect();
+    if warnings.len() > WARNING_LIMIT {
+        lines.push(format!(
+            "cargo:warning={}: {} more warnings not shown, see `yutani_codegen::validate`",
+            spec.display(),
+            warnings.len() - WARNING_LIMIT
+        ));
+    }
+    lines
+}
+
+/// Print the [`cargo_warnings`] of a protocol, unless they are [denied](CodegenOptions::deny_warnings) and so fail
+/// generation with all of them.
+fn print_warnings(spec: &Path, protocol: &Protocol, options: &CodegenOptions) {
+    if options.deny_warnings {
+        return
+    }
+    for line in cargo_warnings(spec, protocol, &validate(protocol, options)) {
+        println!("{}", line);
+    }
+}
+
+fn out_dir() -> Result<PathBuf> {
+    env::var_os("OUT_DIR").map(PathBuf::from).ok_or_else(|| Error::Io(io::Error::new(
+        io::ErrorKind::NotFound,
+        "`OUT_DIR` is not set, as it is only set for build scripts"
+    )))
+}
+
+/// Write the code for a protocol, wrapped in its module, to its [`module_path`](module_path()) in `out_dir`.
+fn write_protocol(out_dir: &Path, protocol: &Protocol, options: &CodegenOptions) -> Result<String> {
+    let options = CodegenOptions { wrap_module: true, ..options.clone() };
+    let name = module_path(protocol, &options).replace("::", "/");
+    let path = out_dir.join(format!("{}.rs", name));
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, render(protocol, &options)?)?;
+    Ok(name)
+}
+
+/// How [`verify`] compares checked in code with the code generated now.
+#[derive(Clone, Debug, Default)]
+pub struct VerifyOptions {
+    /// Format the code generated now with `rustfmt`, which must be on the `PATH`, before comparing, for code that was
+    /// formatted when it was checked in. Stale code is formatted with `rustfmt` on both sides of its diff too, rather
+    /// than written a statement or item per line.
+    pub rustfmt: bool,
+    /// Compare the tokens of the code rather than its text, so that code differing only in whitespace or comments is
+    /// up to date. With [`rustfmt`](Self::rustfmt), the checked in code is formatted too, so that code formatted by
+    /// another version of `rustfmt` is up to date.
+    pub ignore_formatting: bool
+}
+
+/// Check that the code checked in at `generated` is the code that [`render`] generates now for the protocol specified
+/// at `spec`, without writing anything, failing with a [`Mismatch::Contents`] of [`Error::Stale`] holding a unified
+/// diff from the checked in code to the code generated now if not. Both are normalized before they are diffed, see
+/// [`VerifyOptions::rustfmt`], so that the diff only shows how the code changed rather than how it is laid out.
+///
+/// The version and options hash of the [`GENERATED_WITH`](crate::generated_with) markers are ignored, so upgrading
+/// this crate only makes code stale if it changes the code generated.
+pub fn verify<P: AsRef<Path>, Q: AsRef<Path>>(spec: P, generated: Q, options: &CodegenOptions, verify: &VerifyOptions) -> Result<()> {
+    let generated = generated.as_ref();
+    let protocol = Protocol::load(spec)?;
+    let mut checked_in = fs::read_to_string(generated)?;
+    let mut current = render(&protocol, options)?;
+    if verify.rustfmt {
+        current = rustfmt(&current)?;
+        if verify.ignore_formatting {
+            checked_in = rustfmt(&checked_in)?;
+        }
+    }
+    let (checked_in, current) = (marker::without_markers(&checked_in), marker::without_markers(&current));
+    let up_to_date = if verify.ignore_formatting {
+        // Code that is not valid Rust, such as a conflicted merge, is compared as text
+        match (checked_in.parse::<TokenStream>(), current.parse::<TokenStream>()) {
+            (Ok(checked_in), Ok(current)) => same_tokens(checked_in, current),
+            _ => checked_in == current
+        }
+    } else {
+        checked_in == current
+    };
+    if up_to_date {
+        return Ok(())
+    }
+    let name = generated.display().to_string();
+    let (new_name, name) = (format!("{} (generated now)", name), name);
+    // Code only differing in layout has no differences once normalized, so is diffed as it is
+    let diff = Some(diff::unified(&normalize(&checked_in, verify), &normalize(&current, verify), &name, &new_name))
+        .filter(|diff| !diff.is_empty())
+        .unwrap_or_else(|| diff::unified(&checked_in, &current, &name, &new_name));
+    Err(Error::Stale(vec![Mismatch::Contents { diff }]))
+}
+
+/// Whether two token streams have the same tokens, regardless of the spacing between them.
+fn same_tokens(a: TokenStream, b: TokenStream) -> bool {
+    let (mut a, mut b) = (a.into_iter(), b.into_iter());
+    loop {
+        match (a.next(), b.next()) {
+            (None, None) => return true,
+            (Some(TokenTree::Group(a)), Some(TokenTree::Group(b))) if a.delimiter() == b.delimiter() => if !same_tokens(a.stream(), b.stream()) {
+                return false
+            },
+            (Some(TokenTree::Ident(a)), Some(TokenTree::Ident(b))) if a == b => (),
+            (Some(TokenTree::Punct(a)), Some(TokenTree::Punct(b))) if a.as_char() == b.as_char() => (),
+            (Some(TokenTree::Literal(a)), Some(TokenTree::Literal(b))) if a.to_string() == b.to_string() => (),
+            _ => return false
+        }
+    }
+}
+
+/// Code normalized to be diffed, so that a diff shows the changes to the code rather than to its layout: formatted by
+/// `rustfmt` with [`VerifyOptions::rustfmt`], or otherwise written a statement or item per line by [`pretty`].
+///
+/// Code that cannot be normalized, such as a conflicted merge, is diffed as it is.
+fn normalize(code: &str, verify: &VerifyOptions) -> String {
+    if verify.rustfmt {
+        rustfmt(code).unwrap_or_else(|_| code.to_owned())
+    } else {
+        code.parse().map_or_else(|_| code.to_owned(), |tokens| {
+            let mut code = String::new();
+            pretty(&mut code, tokens, 0);
+            code
+        })
+    }
+}
+
+/// Write tokens with each statement, item and attribute on a line of its own, indented by the braces around it.
+fn pretty(code: &mut String, tokens: TokenStream, depth: usize) {
+    let end_line = |code: &mut String, line: &mut String| if !line.is_empty() {
+        code.push_str(&"    ".repeat(depth));
+        code.push_str(line);
+        code.push('\n');
+        line.clear();
+    };
+    let mut line = String::new();
+    // Whether the last token was punctuation joined to the token after it, such as the first `:` of `::`
+    let mut joint = false;
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        let joined = std::mem::replace(&mut joint, matches!(&token, TokenTree::Punct(p) if p.spacing() == Spacing::Joint));
+        match token {
+            TokenTree::Group(group) if group.delimiter() == Delimiter::Brace => {
+                line.push_str(if line.is_empty() { "{" } else { " {" });
+                end_line(code, &mut line);
+                pretty(code, group.stream(), depth + 1);
+                line.push('}');
+                // A block followed by `;` or `,` ends the line after it
+                if !matches!(tokens.peek(), Some(TokenTree::Punct(p)) if matches!(p.as_char(), ';' | ',')) {
+                    end_line(code, &mut line);
+                }
+            },
+            TokenTree::Punct(p) if p.as_char() == ';' || (p.as_char() == ',' && line == "}") => {
+                line.push(p.as_char());
+                end_line(code, &mut line);
+            },
+            TokenTree::Punct(p) if p.as_char() == '#' => {
+                let bang = tokens.next_if(|t| matches!(t, TokenTree::Punct(p) if p.as_char() == '!')).map_or("", |_| "!");
+                let attribute = tokens.next().map(|t| t.to_string()).unwrap_or_default();
+                end_line(code, &mut line);
+                line.push_str(&format!("#{}{}", bang, attribute));
+                end_line(code, &mut line);
+            },
+            token => {
+                if !line.is_empty() && !joined {
+                    line.push(' ');
+                }
+                line.push_str(&token.to_string());
+            }
+        }
+    }
+    end_line(code, &mut line);
+}
+
+/// Format code with `rustfmt`.
+fn rustfmt(code: &str) -> Result<String> {
+    let mut child = Command::new("rustfmt").args(["--edition", "2021"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    child.stdin.take().expect("piped stdin").write_all(code.as_bytes())?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(Error::Io(io::Error::other(format!("`rustfmt` failed with {}", output.status))))
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// Include the code generated into `OUT_DIR` by [`build::protocol`](protocol), by the name it returned, which
+/// defines the module of the protocol.
+///
+/// `include_protocol!("wayland")` expands to `include!(concat!(env!("OUT_DIR"), "/wayland.rs"))`. A protocol in a
+/// namespace is included by its path, such as `include_protocol!("acme/wayland")` in a module named `acme`.
+#[macro_export]
+macro_rules! include_protocol {
+    ($name:literal) => {
+        ::core::include!(::core::concat!(::core::env!("OUT_DIR"), "/", $name, ".rs"));
+    };
+}
@@ -0,0 +1,162 @@
+//! Unified diffs of generated code, for showing how a checked in file is stale.
+
+/// The lines of context around each change.
+const CONTEXT: usize = 3;
+/// The most cells of the table of common subsequences before changed lines are diffed as one block, which is only hit
+/// when thousands of lines change at once.
+const MAX_CELLS: usize = 4_000_000;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Edit<'a> {
+    Equal(&'a str),
+    Delete(&'a str),
+    Insert(&'a str)
+}
+
+/// A unified diff from `old` to `new`, with the files named in the header, or an empty string if they have the same
+/// lines.
+pub(crate) fn unified(old: &str, new: &str, old_name: &str, new_name: &str) -> String {
+    let edits = edits(&old.lines().collect::<Vec<_>>(), &new.lines().collect::<Vec<_>>());
+    let changes: Vec<_> = edits.iter().enumerate().filter(|(_, edit)| !matches!(edit, Edit::Equal(_))).map(|(i, _)| i).collect();
+    if changes.is_empty() {
+        return String::new()
+    }
+    let mut diff = format!("--- {}\n+++ {}\n", old_name, new_name);
+    // Changes separated by less than twice the context share a hunk
+    let mut groups = vec![(changes[0], changes[0])];
+    for &change in &changes[1..] {
+        match groups.last_mut() {
+            Some((_, last)) if change - *last <= 2 * CONTEXT => *last = change,
+            _ => groups.push((change, change))
+        }
+    }
+    let (mut old_line, mut new_line, mut position) = (0, 0, 0);
+    for (first, last) in groups {
+        let (start, end) = (first.saturating_sub(CONTEXT), (last + 1 + CONTEXT).min(edits.len()));
+        for edit in &edits[position..start] {
+            old_line += usize::from(!matches!(edit, Edit::Insert(_)));
+            new_line += usize::from(!matches!(edit, Edit::Delete(_)));
+        }
+        let hunk = &edits[start..end];
+        let old_count = hunk.iter().filter(|edit| !matches!(edit, Edit::Insert(_))).count();
+        let new_count = hunk.iter().filter(|edit| !matches!(edit, Edit::Delete(_))).count();
+        // An empty range starts at the line before it
+        diff.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            old_line + usize::from(old_count > 0),
+            old_count,
+            new_line + usize::from(new_count > 0),
+            new_count
+        ));
+        for edit in hunk {
+            let (prefix, line) = match edit {
+                Edit::Equal(line) => (' ', line),
+                Edit::Delete(line) => ('-', line),
+                Edit::Insert(line) => ('+', line)
+            };
+            diff.push(prefix);
+            diff.push_str(line);
+            diff.push('\n');
+        }
+        old_line += old_count;
+        new_line += new_count;
+        position = end;
+    }
+    diff
+}
+
+/// The edits turning `old` into `new`, keeping the longest common subsequence of lines.
+fn edits<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Edit<'a>> {
+    let prefix = old.iter().zip(new).take_while(|(old, new)| old == new).count();
+    let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev()).take_while(|(old, new)| old == new).count();
+    let (old_changed, new_changed) = (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+    let mut edits: Vec<_> = old[..prefix].iter().map(|line| Edit::Equal(line)).collect();
+    let (rows, columns) = (old_changed.len() + 1, new_changed.len() + 1);
+    if rows * columns > MAX_CELLS {
+        edits.extend(old_changed.iter().map(|line| Edit::Delete(line)));
+        edits.extend(new_changed.iter().map(|line| Edit::Insert(line)));
+    } else {
+        // The length of the longest common subsequence of the lines from each position onwards
+        let mut lengths = vec![0u32; rows * columns];
+        for i in (0..old_changed.len()).rev() {
+            for j in (0..new_changed.len()).rev() {
+                lengths[i * columns + j] = if old_changed[i] == new_changed[j] {
+                    lengths[(i + 1) * columns + j + 1] + 1
+                } else {
+                    lengths[(i + 1) * columns + j].max(lengths[i * columns + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < old_changed.len() || j < new_changed.len() {
+            if i < old_changed.len() && j < new_changed.len() && old_changed[i] == new_changed[j] {
+                edits.push(Edit::Equal(old_changed[i]));
+                i += 1;
+                j += 1;
+            } else if j == new_changed.len() || (i < old_changed.len() && lengths[(i + 1) * columns + j] >= lengths[i * columns + j + 1]) {
+                edits.push(Edit::Delete(old_changed[i]));
+                i += 1;
+            } else {
+                edits.push(Edit::Insert(new_changed[j]));
+                j += 1;
+            }
+        }
+    }
+    edits.extend(old[old.len() - suffix..].iter().map(|line| Edit::Equal(line)));
+    edits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn equal() {
+        assert_eq!(unified("a\nb\n", "a\nb\n", "old", "new"), "");
+        assert_eq!(unified("", "", "old", "new"), "");
+    }
+
+    #[test]
+    fn insert() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n";
+        let new = "1\n2\n3\n4\ninserted\n5\n6\n7\n8\n";
+        assert_eq!(unified(old, new, "old", "new"), "--- old\n+++ new\n@@ -2,6 +2,7 @@\n 2\n 3\n 4\n+inserted\n 5\n 6\n 7\n");
+        // An empty range starts at the line before it
+        assert_eq!(unified("", "a\n", "old", "new"), "--- old\n+++ new\n@@ -0,0 +1,1 @@\n+a\n");
+    }
+
+    #[test]
+    fn delete() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n";
+        let new = "2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n";
+        assert_eq!(
+            unified(old, new, "old", "new"),
+            "--- old\n+++ new\n@@ -1,4 +1,3 @@\n-1\n 2\n 3\n 4\n@@ -9,4 +8,3 @@\n 9\n 10\n 11\n-12\n"
+        );
+    }
+
+    #[test]
+    fn changed_lines_share_hunks() {
+        let old = "a\nb\nc\nd\ne\n";
+        let new = "a\nB\nc\nD\ne\n";
+        assert_eq!(unified(old, new, "old", "new"), "--- old\n+++ new\n@@ -1,5 +1,5 @@\n a\n-b\n+B\n c\n-d\n+D\n e\n");
+    }
+
+    #[test]
+    fn too_many_changes() {
+        // Past the limit, the changed lines are deleted and inserted as one block, even where some are common
+        let lines = (MAX_CELLS as f64).sqrt() as usize + 1;
+        let old: Vec<_> = (0..lines).map(|i| i.to_string()).collect();
+        let new: Vec<_> = (0..lines).map(|i| if i % 2 == 1 { i.to_string() } else { format!("changed {}", i) }).collect();
+        let old: Vec<_> = ["first"].into_iter().chain(old.iter().map(String::as_str)).chain(["last"]).collect();
+        let new: Vec<_> = ["first"].into_iter().chain(new.iter().map(String::as_str)).chain(["last"]).collect();
+        let edits = edits(&old, &new);
+        assert_eq!(edits.len(), 2 + 2 * lines);
+        assert!(edits[0] == Edit::Equal("first") && edits[edits.len() - 1] == Edit::Equal("last"));
+        assert!(edits[1..=lines].iter().all(|edit| matches!(edit, Edit::Delete(_))));
+        assert!(edits[lines + 1..=2 * lines].iter().all(|edit| matches!(edit, Edit::Insert(_))));
+        // Under the limit, the common lines are kept
+        let edits = super::edits(&old[..100], &new[..100]);
+        assert_eq!(edits.iter().filter(|edit| matches!(edit, Edit::Equal(_))).count(), 1 + 49);
+    }
+}
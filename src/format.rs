@@ -0,0 +1,413 @@
+//! Writing specifications as TOML in a canonical layout, so that hand-edited specifications can be kept consistent.
+//!
+//! Keys are written in a fixed order for each table, with the header of the protocol first and items in the order they
+//! are specified. Messages and enums are written as arrays of tables and arguments as inline tables, while multi-line
+//! descriptions are written as multi-line strings, including those written as arrays of paragraphs.
+
+use std::{collections::BTreeMap, fmt::Write};
+
+use crate::{Arg, Constant, ConstValue, Enum, Entry, EntryValue, Error, Event, Interface, Protocol, Request, Result};
+
+/// Parse a TOML specification and write it in the canonical layout.
+///
+/// The layout only changes how the specification is written, so the protocol parsed from the result is the same as
+/// the protocol parsed from `spec`, or formatting fails with [`Error::FormatChanged`].
+///
+/// Comments are not part of the protocol and cannot be kept in the canonical layout, so a specification with comments
+/// is not rewritten, failing with [`Error::Comments`] rather than dropping them.
+pub fn format_spec(spec: &str) -> Result<String> {
+    let mut protocol = Protocol::from_str(spec)?;
+    let comments = comment_lines(spec);
+    if !comments.is_empty() {
+        return Err(Error::Comments(comments))
+    }
+    let formatted = protocol.to_toml();
+    let mut reparsed = Protocol::from_str(&formatted)?;
+    // Items move between lines when formatted
+    protocol.forget_source();
+    reparsed.forget_source();
+    if format!("{:?}", protocol) != format!("{:?}", reparsed) {
+        return Err(Error::FormatChanged)
+    }
+    Ok(formatted)
+}
+
+/// The lines of a TOML document with comments, counting from 1, skipping any `#` within a string.
+fn comment_lines(spec: &str) -> Vec<usize> {
+    let mut lines = Vec::new();
+    let mut rest = spec;
+    let mut line = 1;
+    while let Some(c) = rest.chars().next() {
+        if let Some(quote) = ["\"\"\"", "'''", "\"", "'"].into_iter().find(|quote| rest.starts_with(quote)) {
+            // Only basic strings have escapes, and single-line strings end at the end of the line if not before
+            let body = &rest[quote.len()..];
+            let mut end = body.len();
+            let mut escaped = false;
+            for (i, c) in body.char_indices() {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' && quote.starts_with('"') {
+                    escaped = true;
+                } else if body[i..].starts_with(quote) || (quote.len() == 1 && c == '\n') {
+                    end = i;
+                    break
+                }
+            }
+            line += body[..end].matches('\n').count();
+            rest = body.get(end + quote.len()..).unwrap_or("");
+        } else if c == '#' {
+            lines.push(line);
+            rest = rest.find('\n').map_or("", |end| &rest[end..]);
+        } else {
+            line += usize::from(c == '\n');
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    lines
+}
+
+/// A skeleton TOML specification for a new protocol named `name`, such as `acme_frobnicator`, to start writing a
+/// protocol from.
+///
+/// The skeleton has the protocol header and one interface of the same name with an enum, a destructor request, a
+/// request and an event. Optional keys, such as `since`, `allow-null`, `enum` and `interface`, are shown commented out
+/// where they apply. Arguments are written as arrays of tables so that they can be commented, rather than in the
+/// canonical layout, which [`format_spec`] only rewrites the skeleton into once the comments are removed.
+///
+/// The skeleton is valid as is, and generates code that compiles.
+pub fn new_spec(name: &str) -> String {
+    format!(r#"name = {name}
+summary = "TODO: summarise the protocol in one line"
+description = """
+TODO: describe what the protocol is for.
+"""
+# copyright = "Copyright © <year> <author>"
+# Unstable protocols are named with a `_vN` suffix, such as `acme_frobnicator_v1`
+# stability = "unstable"
+
+[[interface]]
+name = {name}
+version = 1
+summary = "TODO: summarise the interface in one line"
+description = """
+TODO: describe the interface.
+"""
+
+[[interface.enum]]
+name = "error"
+summary = "errors of the interface"
+# since = 2
+# bitfield = true
+[[interface.enum.entry]]
+name = "invalid_value"
+value = 0
+summary = "the value is not valid"
+# since = 2
+
+[[interface.request]]
+name = "destroy"
+destructor = true
+summary = "destroy the object"
+
+[[interface.request]]
+name = "set_value"
+summary = "set the value"
+# since = 2
+[[interface.request.arg]]
+name = "value"
+type = "uint"
+summary = "the new value"
+[[interface.request.arg]]
+name = "label"
+type = "string"
+summary = "a label for the value"
+# Only `string` and `object` arguments can allow null
+# allow-null = true
+
+[[interface.event]]
+name = "value"
+summary = "the current value"
+# since = 2
+[[interface.event.arg]]
+name = "value"
+type = "uint"
+summary = "the value"
+[[interface.event.arg]]
+name = "error"
+type = "uint"
+summary = "why the value was rejected, if it was"
+# enum = "error"
+[[interface.event.arg]]
+name = "source"
+type = "object"
+summary = "the object that set the value"
+# interface = {name}
+"#, name = basic_string(name))
+}
+
+/// Write a protocol as TOML in the canonical layout.
+pub(crate) fn toml(protocol: &Protocol) -> String {
+    let mut toml = Toml::default();
+    toml.string("name", &protocol.name);
+    toml.optional_string("rust_module", &protocol.rust_module);
+    toml.optional_string("namespace", &protocol.namespace);
+    toml.optional_string("stability", &protocol.stability_name);
+    toml.optional_string("summary", &protocol.summary);
+    toml.optional_text("description", &protocol.description);
+    toml.optional_text("copyright", &protocol.copyright);
+    if !protocol.externals.is_empty() {
+        let externals: Vec<_> = protocol.externals.iter().map(|external| basic_string(external)).collect();
+        toml.key("externals", format!("[{}]", externals.join(", ")));
+    }
+    toml.extensions(&protocol.extensions);
+    for constant in &protocol.constants {
+        toml.constant(constant);
+    }
+    for interface in &protocol.interfaces {
+        toml.interface(interface);
+    }
+    toml.0
+}
+
+#[derive(Default)]
+struct Toml(String);
+impl Toml {
+    fn header(&mut self, header: &str) {
+        if !self.0.is_empty() {
+            self.0.push('\n');
+        }
+        writeln!(self.0, "[[{}]]", header).unwrap();
+    }
+    fn key(&mut self, key: &str, value: impl std::fmt::Display) {
+        writeln!(self.0, "{} = {}", key, value).unwrap();
+    }
+    fn string(&mut self, key: &str, value: &str) {
+        self.key(key, basic_string(value));
+    }
+    fn optional_string(&mut self, key: &str, value: &Option<String>) {
+        if let Some(value) = value {
+            self.string(key, value);
+        }
+    }
+    /// A string that is written as a multi-line string if it spans multiple lines.
+    fn optional_text(&mut self, key: &str, value: &Option<String>) {
+        match value {
+            Some(value) if value.contains('\n') => self.key(key, multi_line_string(value)),
+            Some(value) => self.string(key, value),
+            None => ()
+        }
+    }
+    fn optional_integer(&mut self, key: &str, value: Option<impl std::fmt::Display>) {
+        if let Some(value) = value {
+            self.key(key, value);
+        }
+    }
+    fn flag(&mut self, key: &str, value: bool) {
+        if value {
+            self.key(key, true);
+        }
+    }
+    /// Keys that are not part of the format, written after the other keys of the table with their values inline.
+    fn extensions(&mut self, extensions: &BTreeMap<String, toml::Value>) {
+        for (key, value) in extensions {
+            self.key(&key_name(key), inline_value(value));
+        }
+    }
+
+    fn constant(&mut self, constant: &Constant) {
+        self.header("const");
+        self.string("name", &constant.name);
+        self.string("type", &constant.type_name);
+        match &constant.value {
+            ConstValue::Integer(value) => self.key("value", value),
+            ConstValue::String(value) => self.string("value", value)
+        }
+        self.optional_string("summary", &constant.summary);
+        self.optional_text("description", &constant.description);
+        self.extensions(&constant.extensions);
+    }
+    fn interface(&mut self, interface: &Interface) {
+        self.header("interface");
+        self.string("name", &interface.name);
+        self.key("version", interface.version);
+        self.optional_string("extends", &interface.extends);
+        self.optional_string("stability", &interface.stability_name);
+        self.flag("deprecated", interface.deprecated);
+        self.optional_string("replaced_by", &interface.replaced_by);
+        self.optional_string("deprecation_note", &interface.deprecation_note);
+        self.optional_string("capability", &interface.capability);
+        self.optional_string("summary", &interface.summary);
+        self.optional_text("description", &interface.description);
+        self.extensions(&interface.extensions);
+        // Inherited items are written by the interface they are inherited from
+        for enumeration in interface.enums.iter().filter(|e| !e.inherited) {
+            self.enumeration(enumeration);
+        }
+        for request in interface.requests.iter().filter(|r| !r.inherited) {
+            self.request(request);
+        }
+        for event in interface.events.iter().filter(|e| !e.inherited) {
+            self.event(event);
+        }
+    }
+    fn enumeration(&mut self, enumeration: &Enum) {
+        self.header("interface.enum");
+        self.string("name", &enumeration.name);
+        self.optional_integer("since", enumeration.since);
+        self.flag("bitfield", enumeration.bitfield);
+        self.optional_string("default_entry", &enumeration.default_entry);
+        self.optional_string("summary", &enumeration.summary);
+        self.optional_text("description", &enumeration.description);
+        self.extensions(&enumeration.extensions);
+        for entry in &enumeration.entries {
+            self.entry(entry);
+        }
+    }
+    fn entry(&mut self, entry: &Entry) {
+        self.header("interface.enum.entry");
+        self.string("name", &entry.name);
+        match &entry.literal {
+            Some(EntryValue::Integer(value)) => self.key("value", value),
+            Some(EntryValue::Expression(expression)) => self.string("value", expression),
+            None => ()
+        }
+        self.optional_string("alias_of", &entry.alias_of);
+        self.optional_integer("since", entry.since);
+        self.flag("combination", entry.combination);
+        self.optional_string("summary", &entry.summary);
+        self.optional_text("description", &entry.description);
+        self.extensions(&entry.extensions);
+    }
+    fn request(&mut self, request: &Request) {
+        self.header("interface.request");
+        self.string("name", &request.name);
+        self.optional_integer("since", request.since);
+        self.flag("destructor", request.destructor);
+        self.flag("unimplemented", request.unimplemented);
+        self.optional_string("unimplemented_error", &request.unimplemented_error);
+        self.optional_string("capability", &request.capability);
+        self.optional_string("summary", &request.summary);
+        self.optional_text("description", &request.description);
+        self.extensions(&request.extensions);
+        self.args(&request.args);
+    }
+    fn event(&mut self, event: &Event) {
+        self.header("interface.event");
+        self.string("name", &event.name);
+        self.optional_integer("since", event.since);
+        self.flag("destructor", event.destructor);
+        self.optional_string("summary", &event.summary);
+        self.optional_text("description", &event.description);
+        self.extensions(&event.extensions);
+        self.args(&event.args);
+    }
+    fn args(&mut self, args: &[Arg]) {
+        if args.is_empty() {
+            return
+        }
+        self.0 += "arg = [\n";
+        for arg in args {
+            write!(self.0, "    {{ name = {}, type = {}", basic_string(&arg.name), basic_string(&arg.type_name)).unwrap();
+            let keys = [
+                ("array_type", &arg.array_type_name),
+                ("interface", &arg.interface),
+                ("enum", &arg.enumeration)
+            ];
+            for (key, value) in keys {
+                if let Some(value) = value {
+                    write!(self.0, ", {} = {}", key, basic_string(value)).unwrap();
+                }
+            }
+            if arg.nullable {
+                self.0 += ", allow-null = true";
+            }
+            if let Some(summary) = &arg.summary {
+                write!(self.0, ", summary = {}", basic_string(summary)).unwrap();
+            }
+            for (key, value) in &arg.extensions {
+                write!(self.0, ", {} = {}", key_name(key), inline_value(value)).unwrap();
+            }
+            self.0 += " },\n";
+        }
+        self.0 += "]\n";
+    }
+}
+
+/// A key, which is quoted unless it is a bare key.
+pub(crate) fn key_name(key: &str) -> String {
+    if !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        key.to_owned()
+    } else {
+        basic_string(key)
+    }
+}
+
+/// A value written on one line, with tables as inline tables.
+fn inline_value(value: &toml::Value) -> String {
+    use toml::Value;
+    match value {
+        Value::String(string) => basic_string(string),
+        Value::Integer(integer) => integer.to_string(),
+        Value::Float(float) if float.is_nan() => "nan".to_owned(),
+        Value::Float(float) if float.is_infinite() => if *float > 0.0 { "inf" } else { "-inf" }.to_owned(),
+        // Debug formatting keeps the fractional part of whole numbers, so they are read back as floats
+        Value::Float(float) => format!("{:?}", float),
+        Value::Boolean(boolean) => boolean.to_string(),
+        Value::Datetime(datetime) => datetime.to_string(),
+        Value::Array(array) => {
+            let values: Vec<_> = array.iter().map(inline_value).collect();
+            format!("[{}]", values.join(", "))
+        },
+        Value::Table(table) if table.is_empty() => "{}".to_owned(),
+        Value::Table(table) => {
+            let keys: Vec<_> = table.iter().map(|(key, value)| format!("{} = {}", key_name(key), inline_value(value))).collect();
+            format!("{{ {} }}", keys.join(", "))
+        }
+    }
+}
+
+/// Escape a character that cannot appear as is in a TOML basic string.
+fn escape(string: &mut String, c: char) -> bool {
+    match c {
+        '\\' => *string += "\\\\",
+        '"' => *string += "\\\"",
+        '\n' => *string += "\\n",
+        '\t' => *string += "\\t",
+        '\r' => *string += "\\r",
+        c if c.is_control() => write!(string, "\\u{:04X}", c as u32).unwrap(),
+        _ => return false
+    }
+    true
+}
+
+pub(crate) fn basic_string(value: &str) -> String {
+    let mut string = String::with_capacity(value.len() + 2);
+    string.push('"');
+    for c in value.chars() {
+        if !escape(&mut string, c) {
+            string.push(c);
+        }
+    }
+    string.push('"');
+    string
+}
+
+/// A multi-line basic string, starting on the line after the opening quotes and ending with the closing quotes so
+/// that no newline is added.
+fn multi_line_string(value: &str) -> String {
+    let mut string = String::with_capacity(value.len() + 7);
+    string += "\"\"\"\n";
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' | '\t' => string.push(c),
+            // Quotes are only escaped where they would otherwise close the string
+            '"' if !matches!(chars.peek(), None | Some('"')) => string.push(c),
+            c => if !escape(&mut string, c) {
+                string.push(c)
+            }
+        }
+    }
+    string += "\"\"\"";
+    string
+}
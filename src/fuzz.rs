@@ -0,0 +1,133 @@
+//! Seed inputs for fuzzing the wire parsing of a protocol.
+//!
+//! Random bytes rarely make it past the message header, so [`seeds`] synthesizes a valid message for every request
+//! and event of a protocol to start a corpus from.
+
+use crate::{min_message_size, Arg, DataType, Interface, Protocol};
+
+/// The object id messages are addressed to.
+const OBJECT: u32 = 1;
+
+/// A valid wire message for each request and event of a protocol, named `interface-request-opcode-name` or
+/// `interface-event-opcode-name`.
+///
+/// Arguments are placeholders: zero for numbers and ids, empty strings and arrays, and the value of the first entry
+/// for enums. Messages are addressed to object 1 and use the native byte order, as on the wire. Being the smallest
+/// each argument can be, the layout is that of the senders with [`min_message_size`].
+///
+/// File descriptors are carried outside of the message data, so the message is followed by a 4 byte marker for each
+/// `fd` argument with the index of its descriptor, counting from 0. A harness removes the markers and passes a
+/// dup'd descriptor for each alongside the message.
+pub fn seeds(protocol: &Protocol) -> Vec<(String, Vec<u8>)> {
+    let mut seeds = Vec::new();
+    for interface in &protocol.interfaces {
+        let requests = interface.requests_with_opcodes().map(|(opcode, r)| ("request", opcode, &r.name, &r.args));
+        let events = interface.events_with_opcodes().map(|(opcode, e)| ("event", opcode, &e.name, &e.args));
+        for (kind, opcode, name, args) in requests.chain(events) {
+            seeds.push((format!("{}-{}-{}-{}", interface.name, kind, opcode, name), message(protocol, interface, opcode, args)));
+        }
+    }
+    seeds
+}
+
+fn message(protocol: &Protocol, interface: &Interface, opcode: u16, args: &[Arg]) -> Vec<u8> {
+    let size = min_message_size(args);
+    let mut message = Vec::with_capacity(size);
+    message.extend(OBJECT.to_ne_bytes());
+    message.extend(((size as u32) << 16 | u32::from(opcode)).to_ne_bytes());
+    let mut fds = 0u32;
+    let mut markers = Vec::new();
+    for arg in args {
+        let start = message.len();
+        match arg.ty {
+            DataType::Int | DataType::Uint => {
+                let value = arg.enumeration.as_ref()
+                    .and_then(|name| protocol.find_enum(interface, name))
+                    .and_then(|(_, enumeration)| enumeration.entries.iter().find(|e| e.alias_of.is_none()))
+                    .map_or(0, |entry| entry.value as u32);
+                message.extend(value.to_ne_bytes());
+            },
+            // Null for a nullable string, and the length of the nul terminator for an empty string
+            DataType::String => message.extend(u32::from(!arg.nullable).to_ne_bytes()),
+            DataType::Fixed | DataType::Object | DataType::Array => message.extend(0u32.to_ne_bytes()),
+            DataType::Fd => {
+                markers.extend(fds.to_ne_bytes());
+                fds += 1;
+            },
+            DataType::NewId => {
+                // An empty interface name, the padded nul terminator and version 1
+                if arg.interface.is_none() {
+                    message.extend(1u32.to_ne_bytes());
+                    message.extend(0u32.to_ne_bytes());
+                    message.extend(1u32.to_ne_bytes());
+                }
+                message.extend(0u32.to_ne_bytes());
+            }
+        }
+        // The nul terminator of an empty string and its padding are what remains of the smallest size of the argument
+        message.resize(start + arg.min_size(), 0);
+    }
+    debug_assert_eq!(message.len(), size);
+    message.extend(markers);
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The seed of a request with `args`, such as `{ name = "x", type = "uint" }`, of an interface with an enum `mode`.
+    fn seed(args: &str) -> Vec<u8> {
+        let protocol = Protocol::from_str(&format!(r#"
+            name = "acme"
+            [[interface]]
+            name = "acme_thing"
+            version = 1
+            [[interface.enum]]
+            name = "mode"
+            entry = [{{ name = "on", value = 3 }}, {{ name = "off", value = 0 }}]
+            [[interface.request]]
+            name = "go"
+            arg = [{}]
+        "#, args)).unwrap();
+        let seeds = seeds(&protocol);
+        assert_eq!(seeds[0].0, "acme_thing-request-0-go");
+        seeds.into_iter().next().unwrap().1
+    }
+
+    /// A message to object 1 with opcode 0 and the words of `body`, followed by `markers`.
+    fn words(body: &[u32], markers: &[u32]) -> Vec<u8> {
+        let size = 8 + 4 * body.len() as u32;
+        [&[OBJECT, size << 16][..], body, markers].concat().into_iter().flat_map(u32::to_ne_bytes).collect()
+    }
+
+    #[test]
+    fn strings_and_arrays_are_padded() {
+        // An empty string is its length of 1, then the nul terminator padded to 4 bytes
+        assert_eq!(seed(r#"{ name = "s", type = "string" }, { name = "a", type = "array" }"#), words(&[1, 0, 0], &[]));
+    }
+
+    #[test]
+    fn new_id_without_interface() {
+        // The interface name, the version and the id
+        assert_eq!(seed(r#"{ name = "id", type = "new_id" }"#), words(&[1, 0, 1, 0], &[]));
+        assert_eq!(seed(r#"{ name = "id", type = "new_id", interface = "acme_thing" }"#), words(&[0], &[]));
+    }
+
+    #[test]
+    fn nullable_arguments_are_null() {
+        let args = r#"{ name = "o", type = "object", allow-null = true }, { name = "s", type = "string", allow-null = true }"#;
+        assert_eq!(seed(args), words(&[0, 0], &[]));
+    }
+
+    #[test]
+    fn enums_are_their_first_entry() {
+        assert_eq!(seed(r#"{ name = "m", type = "uint", enum = "mode" }, { name = "i", type = "int", enum = "mode" }"#), words(&[3, 3], &[]));
+    }
+
+    #[test]
+    fn fds_are_marked_after_the_message() {
+        let args = r#"{ name = "a", type = "fd" }, { name = "x", type = "uint" }, { name = "b", type = "fd" }"#;
+        assert_eq!(seed(args), words(&[0], &[0, 1]));
+    }
+}
@@ -0,0 +1,113 @@
+//! Drawing the object lifecycle of a protocol as a Graphviz graph, for reviewing which messages create which objects.
+
+use std::{collections::BTreeSet, fmt::Write};
+
+use crate::{DataType, Interface, Protocol};
+
+/// A Graphviz digraph of the lifecycle of the objects of a protocol, which can be drawn with `dot -Tsvg`.
+///
+/// Each interface is a node, and each `new_id` argument with an interface is an edge from the interface of its
+/// message to the interface it creates, labelled with the name of the message. Edges from events are dotted.
+///
+/// Interfaces with a destructor request or event are rounded. Interfaces that no message of the protocol creates,
+/// which are bound as globals through the registry, such as `wl_compositor`, are drawn with a double border.
+/// Interfaces from other protocols are drawn as dashed nodes.
+pub fn to_dot(protocol: &Protocol) -> String {
+    let mut dot = String::new();
+    let mut external = BTreeSet::new();
+    let _ = writeln!(dot, "digraph {} {{", quoted(&protocol.name));
+    let _ = writeln!(dot, "    node [shape=box];");
+    for interface in &protocol.interfaces {
+        let mut style = Vec::new();
+        if is_destructible(interface) {
+            style.push("style=rounded");
+        }
+        if !is_created(protocol, &interface.name) {
+            style.push("peripheries=2");
+        }
+        if style.is_empty() {
+            let _ = writeln!(dot, "    {};", quoted(&interface.name));
+        } else {
+            let _ = writeln!(dot, "    {} [{}];", quoted(&interface.name), style.join(", "));
+        }
+    }
+    for interface in &protocol.interfaces {
+        let requests = interface.requests.iter().map(|r| (&r.name, &r.args, false));
+        let events = interface.events.iter().map(|e| (&e.name, &e.args, true));
+        for (name, args, event) in requests.chain(events) {
+            for created in args.iter().filter(|a| a.ty == DataType::NewId).filter_map(|a| a.interface.as_ref()) {
+                if protocol.interface(created).is_none() {
+                    external.insert(created);
+                }
+                let style = if event { ", style=dotted" } else { "" };
+                let _ = writeln!(dot, "    {} -> {} [label={}{}];", quoted(&interface.name), quoted(created), quoted(name), style);
+            }
+        }
+    }
+    for interface in external {
+        let _ = writeln!(dot, "    {} [style=dashed];", quoted(interface));
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// Whether the interface has a request or event that destroys its objects.
+fn is_destructible(interface: &Interface) -> bool {
+    interface.requests.iter().any(|r| r.destructor) || interface.events.iter().any(|e| e.destructor)
+}
+
+/// Whether a `new_id` argument of a message of the protocol creates objects of the interface named `name`.
+fn is_created(protocol: &Protocol, name: &str) -> bool {
+    protocol.interfaces.iter().any(|interface| {
+        interface.requests.iter().flat_map(|r| &r.args)
+            .chain(interface.events.iter().flat_map(|e| &e.args))
+            .any(|a| a.ty == DataType::NewId && a.interface.as_deref() == Some(name))
+    })
+}
+
+/// A name as a quoted Graphviz ID.
+fn quoted(name: &str) -> String {
+    format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot() {
+        let protocol = Protocol::from_str(r#"
+            name = 'acme "graph" \ v1'
+
+            [[interface]]
+            name = "acme_manager"
+            version = 1
+            [[interface.request]]
+            name = "get_thing"
+            arg = [{ name = "id", type = "new_id", interface = "acme_thing" }]
+            [[interface.request]]
+            name = "get_output"
+            arg = [{ name = "id", type = "new_id", interface = "wl_output" }]
+
+            [[interface]]
+            name = "acme_thing"
+            version = 1
+            [[interface.request]]
+            name = "destroy"
+            destructor = true
+            [[interface.event]]
+            name = "child"
+            arg = [{ name = "id", type = "new_id", interface = "acme_thing" }]
+        "#).unwrap();
+        assert_eq!(to_dot(&protocol), r#"digraph "acme \"graph\" \\ v1" {
+    node [shape=box];
+    "acme_manager" [peripheries=2];
+    "acme_thing" [style=rounded];
+    "acme_manager" -> "acme_thing" [label="get_thing"];
+    "acme_manager" -> "wl_output" [label="get_output"];
+    "acme_thing" -> "acme_thing" [label="child", style=dotted];
+    "wl_output" [style=dashed];
+}
+"#);
+    }
+}
@@ -0,0 +1,121 @@
+//! Conversion of protocol names to Rust identifiers.
+//!
+//! Names are first transliterated: ASCII letters, digits and underscores are kept, accented Latin letters lose their
+//! accents (`é` becomes `e`, `ß` becomes `ss` and `æ` becomes `ae`) and any other character becomes an underscore.
+//! The result is then converted to the case of the generated item, which also drops leading and repeated underscores.
+//!
+//! The keywords `self`, `Self`, `super`, `crate` and `_` cannot be raw identifiers, so names that become one of them
+//! once converted are given a trailing underscore instead, such as `self_`, which [`validate`](crate::validate) warns
+//! about. Names that are empty once converted or start with a digit cannot be salvaged, and are reported as errors.
+
+use heck::{ToPascalCase, ToShoutySnakeCase, ToSnakeCase};
+use proc_macro2::{Ident, Span};
+
+use crate::{AcronymCase, CodegenOptions};
+
+fn transliterate(name: &str) -> String {
+    let mut transliterated = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            transliterated.push(c);
+            continue
+        }
+        let lower = match c.to_lowercase().next().unwrap_or(c) {
+            'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => "a",
+            'æ' => "ae",
+            'ç' => "c",
+            'è' | 'é' | 'ê' | 'ë' => "e",
+            'ì' | 'í' | 'î' | 'ï' => "i",
+            'ð' => "d",
+            'ñ' => "n",
+            'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' => "o",
+            'ù' | 'ú' | 'û' | 'ü' => "u",
+            'ý' | 'ÿ' => "y",
+            'þ' => "th",
+            'ß' => "ss",
+            _ => "_"
+        };
+        if c.is_uppercase() {
+            transliterated.push_str(&lower.to_ascii_uppercase());
+        } else {
+            transliterated.push_str(lower);
+        }
+    }
+    transliterated
+}
+
+pub(crate) fn snake_case(name: &str) -> String {
+    transliterate(name).to_snake_case()
+}
+/// PascalCase, with the words that are [acronyms](CodegenOptions::acronyms) cased by
+/// [`acronym_case`](CodegenOptions::acronym_case).
+pub(crate) fn pascal_case(name: &str, options: &CodegenOptions) -> String {
+    let name = transliterate(name);
+    if options.acronyms.is_empty() {
+        return name.to_pascal_case()
+    }
+    // The words are split as `heck` splits them, so that names without acronyms are unchanged
+    name.to_snake_case().split('_').filter(|word| !word.is_empty()).map(|word| {
+        match options.acronyms.iter().find(|acronym| acronym.eq_ignore_ascii_case(word)) {
+            Some(acronym) => match options.acronym_case {
+                AcronymCase::Upper => word.to_ascii_uppercase(),
+                AcronymCase::Lower => word.to_pascal_case(),
+                AcronymCase::Preserve => acronym.clone()
+            },
+            None => word.to_pascal_case()
+        }
+    }).collect()
+}
+pub(crate) fn shouty_snake_case(name: &str) -> String {
+    transliterate(name).to_shouty_snake_case()
+}
+
+/// The identifier used for a converted name that is a keyword that cannot be a raw identifier, such as `self_` for
+/// `self`.
+pub(crate) fn keyword_fallback(name: &str) -> Option<String> {
+    matches!(name, "self" | "Self" | "super" | "crate" | "_").then(|| format!("{}_", name))
+}
+
+/// A converted name as its identifier is written, with the [fallback](keyword_fallback) of a keyword, for doc links to
+/// generated items.
+pub(crate) fn name(name: &str) -> String {
+    keyword_fallback(name).unwrap_or_else(|| name.to_owned())
+}
+
+/// Why a converted name cannot be used as an identifier, if it cannot. Keywords have a
+/// [fallback](keyword_fallback) instead.
+pub(crate) fn problem(name: &str) -> Option<&'static str> {
+    if name.is_empty() {
+        Some("has no characters that can be used in an identifier")
+    } else if name.starts_with(|c: char| c.is_ascii_digit()) {
+        Some("must not start with a digit")
+    } else if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        Some("has characters that cannot be used in an identifier")
+    } else {
+        None
+    }
+}
+
+/// A raw identifier for a converted name, or the [fallback](keyword_fallback) of a keyword that cannot be raw.
+///
+/// Other names that cannot be identifiers are prefixed with an underscore rather than panicking, which is only
+/// reachable when generating code for a protocol that failed validation.
+pub(crate) fn ident(name: &str) -> Ident {
+    if let Some(fallback) = keyword_fallback(name) {
+        return Ident::new(&fallback, Span::call_site())
+    }
+    match problem(name) {
+        None => Ident::new_raw(name, Span::call_site()),
+        Some(_) => {
+            let name: String = name.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+            Ident::new(&format!("_{}", name), Span::call_site())
+        }
+    }
+}
+
+pub(crate) fn snake(name: &str) -> Ident {
+    ident(&snake_case(name))
+}
+pub(crate) fn pascal(name: &str, options: &CodegenOptions) -> Ident {
+    ident(&pascal_case(name, options))
+}
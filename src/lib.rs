@@ -1,23 +1,79 @@
+mod bench;
+pub mod build;
+mod diff;
+pub mod fuzz;
+pub mod graph;
+mod format;
+mod ident;
+mod lint;
+mod lock;
+mod marker;
+mod options;
 mod proto;
+mod scaffold;
+pub mod schema;
+pub mod transform;
+mod validate;
+mod xml;
 use std::{path::Path, io, string::FromUtf8Error};
 
-use heck::{ToPascalCase, ToSnakeCase, ToShoutySnakeCase};
-use proc_macro2::{TokenStream, Ident, Span};
+use proc_macro2::{Group, TokenStream, TokenTree, Ident, Literal, Span};
+pub use format::{format_spec, new_spec};
+pub use lint::*;
+pub use lock::*;
+pub use marker::*;
+pub use options::*;
 pub use proto::*;
+pub use scaffold::scaffold;
+pub use bench::benches;
 use quote::quote;
+pub use validate::*;
+pub use xml::XmlError;
 
 pub type Result<T> = core::result::Result<T, Error>;
 #[derive(Debug)]
 pub enum Error {
     Toml(toml::de::Error),
+    Xml(XmlError),
+    #[cfg(feature = "json")]
+    Json(serde_json::Error),
+    #[cfg(feature = "yaml")]
+    Yaml(serde_yaml::Error),
     Io(io::Error),
-    Utf8(FromUtf8Error)
+    Utf8(FromUtf8Error),
+    Validation(Vec<Diagnostic>),
+    /// Generated code does not match what would be generated now, see [`verify_generated`].
+    Stale(Vec<Mismatch>),
+    /// A specification of several protocols in `[[protocol]]` tables was parsed as a single protocol, rather than with
+    /// [`Protocol::load_all`].
+    MultipleProtocols,
+    /// Formatting a specification with [`format_spec`] would change the protocol it describes.
+    FormatChanged,
+    /// A specification has comments on these lines, counting from 1, which [`format_spec`] would drop.
+    Comments(Vec<usize>)
 }
 impl From<toml::de::Error> for Error {
     fn from(error: toml::de::Error) -> Self {
         Self::Toml(error)
     }
 }
+impl From<XmlError> for Error {
+    fn from(error: XmlError) -> Self {
+        Self::Xml(error)
+    }
+}
+#[cfg(feature = "json")]
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Json(error)
+    }
+}
+#[cfg(feature = "yaml")]
+impl From<serde_yaml::Error> for Error {
+    fn from(error: serde_yaml::Error) -> Self {
+        Self::Yaml(error)
+    }
+}
 impl From<io::Error> for Error {
     fn from(error: io::Error) -> Self {
         Self::Io(error)
@@ -30,303 +86,2347 @@ impl From<FromUtf8Error> for Error {
 }
 
 pub fn protocol<P: AsRef<Path>>(path: P) -> Result<TokenStream> {
-    let protocol = proto::Protocol::load(path)?;
-    let header = format!("# {}", protocol.name);
-    let summary = protocol.summary.map(|summary| quote! {#![doc = #summary]});
-    let description = protocol.description.map(|description| quote! {#![doc = #description]});
-    let copyright = protocol.copyright.map(|copyright| quote! {
-        #![doc = "## Copyright"]
-        #![doc = #copyright]
-    });
-
-    let interfaces = protocol.interfaces.into_iter().map(|i| interface(i));
-
-    Ok(quote!{
-        #![doc = #header]
-        #summary
-        #![doc = ""]
-        #description
-        #copyright
-        
+    protocol_with_options(path, &CodegenOptions::default())
+}
+pub fn protocol_with_options<P: AsRef<Path>>(path: P, options: &CodegenOptions) -> Result<TokenStream> {
+    generate(&Protocol::load(path)?, options)
+}
+/// Generate the code for a protocol read from any source, such as standard input or a byte slice.
+pub fn protocol_from_reader<R: io::Read>(reader: R, format: Format, options: &CodegenOptions) -> Result<TokenStream> {
+    generate(&Protocol::from_reader(reader, format)?, options)
+}
+
+/// Generate the code for a protocol, failing if validation finds any errors, or any warnings with
+/// [`deny_warnings`](CodegenOptions::deny_warnings).
+pub fn generate(protocol: &Protocol, options: &CodegenOptions) -> Result<TokenStream> {
+    let errors: Vec<_> = validate(protocol, options).into_iter().filter(|d| d.severity == Severity::Error || options.deny_warnings).collect();
+    if !errors.is_empty() {
+        return Err(Error::Validation(errors))
+    }
+    let docs = doc_lines([
+        Some(format!("# {}", protocol.name)),
+        protocol.summary.clone(),
+        protocol.stability.warning("protocol"),
+        protocol.description.clone(),
+        protocol.copyright.as_ref()
+            .filter(|_| options.copyright == CopyrightStyle::Doc)
+            .map(|copyright| format!("## Copyright\n\n{}", copyright))
+    ]);
+
+    let generated_with = generated_with(options);
+    // Deprecated interfaces are still used by the generated code itself, such as in `INTERFACES`
+    let deprecated = protocol.interfaces.iter().any(|i| i.deprecated).then_some("deprecated");
+    let mut lints = options.allow.iter().map(String::as_str).chain(deprecated).peekable();
+    let allow = lints.peek().is_some().then(|| {
+        let lints = lints.map(|lint| lint.parse::<TokenStream>().expect("validated lint"));
+        quote!{allow(#(#lints),*)}
+    });
+    let max_message_size = MAX_MESSAGE_SIZE as u16;
+    let t = state_param(options);
+    let embedded_spec = options.embed_spec.then(|| {
+        let name = &protocol.name;
+        // The literal escapes the specification, so any content is a valid string
+        let spec = Literal::string(&protocol.to_toml());
+        quote!{
+            #[doc = "The name of the protocol."]
+            pub const PROTOCOL_NAME: &::core::primitive::str = #name;
+            #[doc = "The specification of the protocol as TOML."]
+            pub const PROTOCOL_SPEC: &::core::primitive::str = #spec;
+        }
+    });
+    // Used by the assertions that tie each opcode to the name of its message, see `opcode_assertion`
+    let name_checks = (!protocol.interfaces.is_empty()).then(|| quote!{
+        const fn names_match(a: &::core::primitive::str, b: &::core::primitive::str) -> ::core::primitive::bool {
+            let (a, b) = (a.as_bytes(), b.as_bytes());
+            if a.len() != b.len() {
+                return false
+            }
+            let mut i = 0;
+            while i < a.len() {
+                if a[i] != b[i] {
+                    return false
+                }
+                i += 1;
+            }
+            true
+        }
+        const fn names_unique(names: &[&::core::primitive::str]) -> ::core::primitive::bool {
+            let mut i = 0;
+            while i < names.len() {
+                let mut j = i + 1;
+                while j < names.len() {
+                    if names_match(names[i], names[j]) {
+                        return false
+                    }
+                    j += 1;
+                }
+                i += 1;
+            }
+            true
+        }
+    });
+    // Used by the dispatch functions and debug printers of every interface to read arguments, see `Arg::getter`, so
+    // that each argument is read by a short call rather than repeating the errors of reading it
+    let read_args = (!protocol.interfaces.is_empty()).then(|| quote!{
+        #[allow(dead_code)]
+        trait ReadArgs {
+            fn string_required(&mut self, description: &'static ::core::primitive::str) -> ::core::result::Result<::std::string::String, ::yutani::wire::WlError<'static>>;
+            fn object_required(&mut self, description: &'static ::core::primitive::str) -> ::core::result::Result<::yutani::Id, ::yutani::wire::WlError<'static>>;
+            fn array<E>(
+                &mut self,
+                description: &'static ::core::primitive::str,
+                element: impl ::core::ops::FnMut(&mut Self) -> ::core::result::Result<E, ::yutani::wire::WlError<'static>>
+            ) -> ::core::result::Result<::std::vec::Vec<E>, ::yutani::wire::WlError<'static>>;
+        }
+        impl ReadArgs for ::yutani::wire::Stream {
+            fn string_required(&mut self, description: &'static ::core::primitive::str) -> ::core::result::Result<::std::string::String, ::yutani::wire::WlError<'static>> {
+                self.string()?.ok_or(::yutani::wire::WlError {
+                    description: ::std::borrow::Cow::Borrowed(description),
+                    ..::yutani::wire::WlError::NON_NULLABLE
+                })
+            }
+            fn object_required(&mut self, description: &'static ::core::primitive::str) -> ::core::result::Result<::yutani::Id, ::yutani::wire::WlError<'static>> {
+                self.object()?.ok_or(::yutani::wire::WlError {
+                    description: ::std::borrow::Cow::Borrowed(description),
+                    ..::yutani::wire::WlError::NON_NULLABLE
+                })
+            }
+            fn array<E>(
+                &mut self,
+                description: &'static ::core::primitive::str,
+                mut element: impl ::core::ops::FnMut(&mut Self) -> ::core::result::Result<E, ::yutani::wire::WlError<'static>>
+            ) -> ::core::result::Result<::std::vec::Vec<E>, ::yutani::wire::WlError<'static>> {
+                let len = self.u32()?;
+                if len % 4 != 0 {
+                    return ::core::result::Result::Err(::yutani::wire::WlError {
+                        description: ::std::borrow::Cow::Borrowed(description),
+                        ..::yutani::wire::WlError::CORRUPT
+                    })
+                }
+                // The length is untrusted, so the array grows as elements are successfully read
+                let mut array = ::std::vec::Vec::new();
+                for _ in 0..len / 4 {
+                    array.push(element(self)?);
+                }
+                ::core::result::Result::Ok(array)
+            }
+        }
+    });
+    // Used by the broadcast senders to write the arguments of an event once for many messages, see `Arg::payload`,
+    // writing them as the stream does so that each message is the same as if sent alone
+    let write_args = (!protocol.interfaces.is_empty()).then(|| quote!{
+        #[allow(dead_code)]
+        trait WriteArgs {
+            fn string(&mut self, string: ::core::option::Option<&::core::primitive::str>);
+            fn bytes(&mut self, bytes: &[::core::primitive::u8]);
+            fn padded(&mut self, bytes: &[::core::primitive::u8], len: ::core::primitive::usize);
+        }
+        impl WriteArgs for ::std::vec::Vec<::core::primitive::u32> {
+            fn string(&mut self, string: ::core::option::Option<&::core::primitive::str>) {
+                let ::core::option::Option::Some(string) = string else {
+                    return self.push(0)
+                };
+                // The length counts the terminating nul byte and the padding
+                let len = (string.len() + 4) & !3;
+                self.push(len as ::core::primitive::u32);
+                self.padded(string.as_bytes(), len);
+            }
+            fn bytes(&mut self, bytes: &[::core::primitive::u8]) {
+                // An empty array is not written at all, and the length counts the padding
+                if bytes.is_empty() {
+                    return
+                }
+                let len = (bytes.len() + 3) & !3;
+                self.push(len as ::core::primitive::u32);
+                self.padded(bytes, len);
+            }
+            fn padded(&mut self, bytes: &[::core::primitive::u8], len: ::core::primitive::usize) {
+                self.extend((0..len).step_by(4).map(|start| {
+                    let mut word = [0; 4];
+                    for (i, byte) in word.iter_mut().enumerate() {
+                        *byte = bytes.get(start + i).copied().unwrap_or(0);
+                    }
+                    ::core::primitive::u32::from_ne_bytes(word)
+                }));
+            }
+        }
+    });
+    let constants = protocol.constants.iter().map(constant);
+    let interfaces = protocol.interfaces.iter().map(|i| interface(protocol, i, options));
+    let descriptors = protocol.interfaces.iter().map(|i| {
+        let module = ident::snake(interface_name(protocol, i, options));
+        quote!{#module::DESCRIPTOR}
+    });
+
+    let items = quote!{
+        #[doc = "The version of `yutani-codegen` and the options that generated this code, see `yutani_codegen::verify_generated`."]
+        pub const GENERATED_WITH: &::core::primitive::str = #generated_with;
+
+        #[doc = "A value that does not correspond to any entry of an enum."]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct UnknownEnumValue {
+            #[doc = "The value that was not recognised."]
+            pub value: ::core::primitive::i64
+        }
+        impl ::core::fmt::Display for UnknownEnumValue {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                ::core::write!(f, "unknown enum value {}", self.value)
+            }
+        }
+        impl ::std::error::Error for UnknownEnumValue {}
+
+        #[doc = "A string that does not name any entry of an enum."]
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        pub struct ParseEnumError {
+            #[doc = "The string that was not recognised."]
+            pub value: ::std::string::String,
+            #[doc = "The name of the enum."]
+            pub enumeration: &'static ::core::primitive::str
+        }
+        impl ::core::fmt::Display for ParseEnumError {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                ::core::write!(f, "{:?} is not an entry of enum {}", self.value, self.enumeration)
+            }
+        }
+        impl ::std::error::Error for ParseEnumError {}
+
+        #[doc = "The largest size of a message in bytes, including its header."]
+        pub const MAX_MESSAGE_SIZE: ::core::primitive::u16 = #max_message_size;
+
+        #[doc = "A description of an interface, usable in `static` and `const` contexts."]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct InterfaceDescriptor {
+            #[doc = "The name of the interface."]
+            pub name: &'static ::core::primitive::str,
+            #[doc = "The highest version of the interface."]
+            pub version: ::core::primitive::u32,
+            #[doc = "Request names indexed by opcode."]
+            pub requests: &'static [&'static ::core::primitive::str],
+            #[doc = "Event names indexed by opcode."]
+            pub events: &'static [&'static ::core::primitive::str]
+        }
+        #[doc = "A function dispatching a request to an object implementing an interface."]
+        pub type DispatchFn<#t> = fn(::yutani::lease::Lease<dyn ::core::any::Any>, &mut ::yutani::wire::EventLoop<#t>, &mut ::yutani::server::Client<#t>, ::yutani::wire::Message) -> ::core::result::Result<(), ::yutani::wire::WlError<'static>>;
+        #[doc = "An interface with the entry point that dispatches requests to an implementation of it."]
+        pub struct Implementation<#t> {
+            #[doc = "The description of the interface."]
+            pub descriptor: &'static InterfaceDescriptor,
+            #[doc = "Dispatch a request to an object implementing the interface."]
+            pub dispatch: DispatchFn<#t>
+        }
+        impl<#t> ::core::clone::Clone for Implementation<#t> {
+            fn clone(&self) -> Self {
+                *self
+            }
+        }
+        impl<#t> ::core::marker::Copy for Implementation<#t> {}
+
+        #embedded_spec
+
+        #name_checks
+
+        #read_args
+        #write_args
+
+        #[doc = "The descriptors of every interface of the protocol."]
+        pub const INTERFACES: &[&InterfaceDescriptor] = &[#(&#descriptors),*];
+
+        #(#constants)*
+
         #(#interfaces)*
+    };
+    let allow = allow.into_iter();
+    Ok(if options.wrap_module {
+        let module = ident::ident(&module_name(protocol, options));
+        quote!{
+            #(#[doc = #docs])*
+            #(#[#allow])*
+            pub mod #module {
+                #items
+            }
+        }
+    } else {
+        quote!{
+            #(#![doc = #docs])*
+            #(#![#allow])*
+
+            #items
+        }
+    })
+}
+
+/// Generate the code for a protocol as source text, headed by a comment marking it as generated and recording the
+/// [`GENERATED_WITH`](generated_with) marker.
+/// 
+/// The copyright is written as comments when [`CopyrightStyle::Comment`] is set, as are
+/// [source markers](CodegenOptions::source_markers).
+pub fn render(protocol: &Protocol, options: &CodegenOptions) -> Result<String> {
+    let code = generate(protocol, options)?;
+    let mut rendered = format!("// Auto-Generated file. Do not edit.\n// Generated with {}.\n", generated_with(options));
+    if let (Some(copyright), CopyrightStyle::Comment) = (&protocol.copyright, options.copyright) {
+        rendered.push_str("//\n");
+        for line in copyright.trim_matches('\n').lines() {
+            let line = line.trim_end();
+            if line.is_empty() {
+                rendered.push_str("//\n");
+            } else {
+                rendered.push_str(&format!("// {}\n", line));
+            }
+        }
+    }
+    let code = code.to_string();
+    let code = if options.source_markers { source_marker_comments(&code) } else { code };
+    rendered.push_str(&format!("\n{}\n", code));
+    Ok(rendered)
+}
+
+pub fn constant(constant: &Constant) -> TokenStream {
+    let ident = ident::ident(&ident::shouty_snake_case(&constant.name));
+    let alias = doc_alias(&constant.name, &ident);
+    let summary = fallback_summary(&constant.summary, &constant.description, || format!("Constant `{}`", constant.name));
+    let docs = docs([summary, constant.description.clone()]);
+    let ty = constant.ty.ty();
+    let value = match (&constant.value, constant.ty) {
+        (ConstValue::Integer(value), ConstType::Int) => Literal::i32_suffixed(*value as i32),
+        (ConstValue::Integer(value), _) => Literal::u32_suffixed(*value as u32),
+        (ConstValue::String(value), _) => Literal::string(value)
+    };
+    quote!{
+        #docs
+        #alias
+        pub const #ident: #ty = #value;
+    }
+}
+
+/// The note of the `#[deprecated]` attribute of a deprecated interface, if any.
+fn deprecation_note(interface: &Interface) -> Option<String> {
+    interface.deprecation_note.clone()
+        .or_else(|| interface.replaced_by.as_ref().map(|replacement| format!("use `{}` instead", replacement)))
+}
+
+/// Documentation naming the replacement of a deprecated interface, linking to it if it is in the same protocol.
+fn replacement_doc(protocol: &Protocol, interface: &Interface, options: &CodegenOptions) -> Option<String> {
+    let replacement = interface.replaced_by.as_ref().filter(|_| interface.deprecated)?;
+    Some(match protocol.interfaces.iter().find(|i| &i.name == replacement) {
+        Some(replacement) => format!("Replaced by [`{}`]({}).", replacement.name, interface_link(protocol, replacement, options)),
+        None => format!("Replaced by `{}`.", replacement)
     })
 }
 
-pub fn interface(interface: Interface) -> TokenStream {
-    let trait_ident = Ident::new_raw(&interface.name.to_pascal_case(), Span::call_site());
-    let mod_ident = Ident::new_raw(&interface.name.to_snake_case(), Span::call_site());
+pub fn interface(protocol: &Protocol, interface: &Interface, options: &CodegenOptions) -> TokenStream {
+    let t = state_param(options);
+    let state = state_declaration(options);
+    let rust_name = interface_name(protocol, interface, options);
+    let trait_name = trait_name(protocol, interface, options);
+    let trait_ident = ident::ident(&trait_name);
+    let dispatch_name = dispatch_trait_name(protocol, interface, options);
+    let dispatch_trait = ident::ident(&dispatch_name);
+    let mod_ident = ident::snake(rust_name);
     let name = &interface.name;
     let version = interface.version;
-    let version_doc = format!("`Version {}`", interface.version);
-    let summary = interface.summary.as_ref().map(|summary| quote!{#[doc = #summary]});
-    let description = interface.description.as_ref().map(|description| quote! {#[doc = #description]});
-
-    let enums = interface.enums.iter().map(|e| enumeration(e));
-    let requests = interface.requests.iter().map(|r| request(r));
-    let events = interface.events.iter().enumerate().map(|(opcode, e)| event(&interface, e, opcode.try_into().unwrap()));
-
-    let dispatch_requests = interface.requests.iter().enumerate().map(|(opcode, r)| {
-        let opcode: u16 = opcode.try_into().unwrap();
-        let request_name = &r.name.to_snake_case();
-        let ident = Ident::new_raw(request_name, Span::call_site());
+    let stability = protocol.stability(interface);
+    let stability_attribute = options.unstable_attribute.as_ref()
+        .filter(|_| stability != Stability::Stable)
+        .map(|attribute| {
+            let attribute: TokenStream = attribute.parse().expect("validated unstable attribute");
+            quote!{#[#attribute]}
+        });
+    let deprecated_attribute = interface.deprecated.then(|| match deprecation_note(interface) {
+        Some(note) => quote!{#[deprecated(note = #note)]},
+        None => quote!{#[deprecated]}
+    });
+
+    let types = interface_types(protocol, interface, options);
+    let requests = interface.requests_with_opcodes().map(|(opcode, r)| request(protocol, interface, r, opcode, options));
+    let core_dispatch = options.core_interfaces.get(name).map(|path| -> TokenStream {
+        path.parse().expect("validated core dispatch path")
+    });
+    // The structs of a core interface would only be used by its trait, which is not generated
+    let core = core_dispatch.is_some();
+    let args_structs = interface.requests.iter()
+        .filter(|_| !core)
+        .filter_map(|r| args_struct(protocol, interface, r, options));
+    let event_structs = interface.events_with_opcodes()
+        .filter(|_| options.event_structs && !core)
+        .map(|(opcode, e)| event_struct(protocol, interface, e, opcode, options));
+    let erased = options.erased_senders.then(|| erased_senders(protocol, interface, options));
+    let concrete_dispatch = options.concrete_types.get(name).map(|ty| {
+        let ty: TokenStream = ty.parse().expect("validated concrete type");
+        let dispatch_ident = ident::ident(&format!("dispatch_{}", ident::snake_case(rust_name)));
+        let doc = format!("Dispatch a request to this type without the downcast of [`{0}::dispatch`], for objects known to be of this type.", dispatch_name);
+        quote!{
+            impl #ty {
+                #[doc = #doc]
+                pub fn #dispatch_ident<#state>(this: ::yutani::lease::Lease<Self>, event_loop: &mut ::yutani::wire::EventLoop<#t>, client: &mut ::yutani::server::Client<#t>, message: ::yutani::wire::Message) -> ::core::result::Result<(), ::yutani::wire::WlError<'static>>
+                where
+                    Self: #trait_ident<#t>
+                {
+                    <Self as #dispatch_trait<#t>>::dispatch_typed(this, event_loop, client, message)
+                }
+            }
+        }
+    });
+    let events: Vec<_> = interface.events_with_opcodes().map(|(opcode, e)| event(protocol, interface, e, opcode, options)).collect();
+    let debug = debug_printer(protocol, interface, options);
+    let trait_alias = doc_alias(name, &trait_ident);
+    let mod_alias = doc_alias(name, &mod_ident);
+    let mod_doc = format!("The descriptor, message tables and types of the `{}` interface.", name);
+
+    let request_count: u16 = interface.requests.len().try_into().unwrap();
+    let event_count: u16 = interface.events.len().try_into().unwrap();
+    let request_names = interface.requests.iter().map(|r| &r.name);
+    let event_names = interface.events.iter().map(|e| &e.name);
+    let request_sizes = interface.requests.iter().map(|r| message_size(&r.args));
+    let event_sizes = interface.events.iter().map(|e| message_size(&e.args));
+    let request_destructors = interface.requests.iter().map(|r| r.destructor);
+    let event_destructors = interface.events.iter().map(|e| e.destructor);
+    let request_tables_message = format!("the request tables of `{}` do not have one entry for each request", name);
+    let event_tables_message = format!("the event tables of `{}` do not have one entry for each event", name);
+    let docs = docs([
+        fallback_summary(&interface.summary, &interface.description, || format!("Interface `{}`", interface.name)),
+        Some(format!("`Version {}`", interface.version)),
+        stability.warning("interface"),
+        replacement_doc(protocol, interface, options),
+        interface.capability.as_ref().map(|capability| format!(
+            "Requests are only dispatched to clients with the `{}` capability, see [`check_capability`](Self::check_capability).",
+            capability
+        )),
+        interface.description.clone(),
+        opcode_table("Requests", interface.requests_with_opcodes().map(|(opcode, r)| (opcode, r.name.as_str(), r.since, r.destructor))),
+        opcode_table("Events", interface.events_with_opcodes().map(|(opcode, e)| (opcode, e.name.as_str(), e.since, e.destructor)))
+    ]);
+
+    let metrics_predicate = options.dispatch_metrics.as_ref().map(|predicate| -> TokenStream {
+        predicate.parse().expect("validated dispatch metrics predicate")
+    });
+    // Handlers of the table, and those between request hooks, are generic functions of their own, which cannot name
+    // `Self` of the impl they are in
+    let handler_fns = options.dispatch_strategy == DispatchStrategy::Table || options.intercept_requests;
+    let this = if handler_fns { quote!{I} } else { quote!{Self} };
+    let dispatch_requests: Vec<_> = interface.requests_with_opcodes().map(|(opcode, r)| {
+        let context = format!("{}.{}", name, r.name);
+        let request_name = &ident::snake_case(&r.name);
+        let ident = ident::ident(request_name);
         let stream = Ident::new("_stream", Span::call_site());
 
         let define_args = r.args.iter().map(|a| {
-            let ident = Ident::new_raw(&a.name.to_snake_case(), Span::call_site());
-            let getter = a.getter(&stream);
-            quote!{let #ident = #getter;}
+            let ident = ident::snake(&a.name);
+            let getter = a.getter(&stream, enum_path(protocol, interface, a, options).as_ref(), &context, options);
+            let check = options.validate_enums.then(|| enum_check(protocol, interface, r, a, options));
+            quote!{
+                let #ident = #getter;
+                #check
+            }
         });
-        let args = r.args.iter().map(|a| {
-            let ident = Ident::new_raw(&a.name.to_snake_case(), Span::call_site());
+        let args: Vec<_> = r.args.iter().map(|a| {
+            let ident = ident::snake(&a.name);
             quote!{#ident}
-        });
+        }).collect();
+        let args = match args_struct_name(r, options) {
+            Some(args_struct) => {
+                let args_struct = ident::ident(&args_struct);
+                vec![quote!{#mod_ident::#args_struct { #(#args),* }}]
+            },
+            None => args
+        };
         let args_debug_idents = r.args.iter().map(|a| {
-            let ident = Ident::new_raw(&a.name.to_snake_case(), Span::call_site());
+            let ident = ident::snake(&a.name);
             quote!{#ident}
         });
-        let args_debug_templates = r.args.iter().enumerate().map(|(i, _)| {
-            if i == 0 {
-                quote!{"{:?}"}
-            } else {
-                quote!{", {:?}"}
+        let debug_template = debug_template("", name, request_name, r.args.len());
+        let assertion = opcode_assertion(quote!{#mod_ident::REQUEST_NAMES}, quote!{names_match}, opcode, &r.name);
+        let capability_check = r.capability(interface).map(|capability| quote!{
+            <#this as #trait_ident<#t>>::check_capability(_client, #capability)?;
+        });
+        let destroy_hook = r.destructor.then(|| quote!{
+            let mut _this = _this;
+            <#this as #trait_ident<#t>>::on_destroy(&mut _this, _event_loop, _client);
+        });
+        let count = metrics_predicate.as_ref().map(|predicate| {
+            let index = Literal::usize_unsuffixed(opcode.into());
+            quote!{
+                #[cfg(#predicate)]
+                #mod_ident::request_counts()[#index].fetch_add(1, ::core::sync::atomic::Ordering::Relaxed);
             }
         });
-        quote!{
-            #opcode => {
-                let #stream = _client.stream();
-                #(#define_args)*
-                #[cfg(debug_assertions)]
-                {
-                    ::std::println!(::std::concat!(#name, "@{}.", #request_name, "(", #(#args_debug_templates,)* ")"), _this.id(), #(#args_debug_idents,)*);
-                }
-                Self::#ident(_this, _event_loop, _client #(, #args)*)
+        let body = quote!{
+            #count
+            #assertion
+            #capability_check
+            let #stream = _client.stream();
+            #(#define_args)*
+            #[cfg(debug_assertions)]
+            {
+                ::std::println!(#debug_template, _this.id(), #(#args_debug_idents,)*);
             }
+            #destroy_hook
+            <#this as #trait_ident<#t>>::#ident(_this, _event_loop, _client #(, #args)*)
+        };
+        (opcode, ident::ident(&format!("handle_{}", request_name)), body)
+    }).collect();
+
+    // Only interfaces with privileged requests have the hook, as it is never called otherwise
+    let check_capability = interface.requests.iter().any(|r| r.capability(interface).is_some()).then(|| quote!{
+        #[doc = "Check that a client has a capability needed for a request to be dispatched, before any of its arguments are read."]
+        #[doc = ""]
+        #[doc = "The request is rejected with the error if the check fails, which should be a protocol error. By default every client has every capability."]
+        fn check_capability(_client: &mut ::yutani::server::Client<#t>, _capability: &'static ::core::primitive::str) -> ::core::result::Result<(), ::yutani::wire::WlError<'static>> {
+            ::core::result::Result::Ok(())
         }
     });
 
-    quote!{
-        #summary
+    let request_hooks = (options.intercept_requests && !interface.requests.is_empty()).then(|| quote!{
+        #[doc = "Observe a request before it is handled, before its arguments are read. By default nothing is done."]
         #[doc = ""]
-        #[doc = #version_doc]
-        #[doc = ""]
-        #description
-        pub trait #trait_ident<T>: 'static + ::core::marker::Sized {
-            const INTERFACE: &'static ::core::primitive::str = #name;
-            const VERSION: ::core::primitive::u32 = #version;
-            #[doc(hidden)]
-            fn dispatch(_this: ::yutani::lease::Lease<dyn ::core::any::Any>, _event_loop: &mut ::yutani::wire::EventLoop<T>, _client: &mut ::yutani::server::Client<T>, _message: ::yutani::wire::Message) -> ::core::result::Result<(), ::yutani::wire::WlError<'static>> {
-                let _this: ::yutani::lease::Lease<Self> = _this.downcast().ok_or(::yutani::wire::WlError::INTERNAL)?;
-                match _message.opcode {
-                    #(#dispatch_requests,)*
-                    _ => ::core::result::Result::Err(::yutani::wire::WlError::INVALID_OPCODE)
+        #[doc = "Every request with a known opcode is passed to this hook, with the name of the interface and of the request, and then to its handler."]
+        fn before_request(_client: &mut ::yutani::server::Client<#t>, _interface: &'static ::core::primitive::str, _opcode: ::core::primitive::u16, _name: &'static ::core::primitive::str) {}
+        #[doc = "Observe the result of a request after it is handled, including errors reading its arguments. By default nothing is done."]
+        fn after_request(_client: &mut ::yutani::server::Client<#t>, _interface: &'static ::core::primitive::str, _opcode: ::core::primitive::u16, _name: &'static ::core::primitive::str, _result: &::core::result::Result<(), ::yutani::wire::WlError<'static>>) {}
+    });
+
+    let (events_in_trait, events_trait) = if options.split_traits {
+        let events_ident = ident::ident(&events_trait_name(protocol, interface, options));
+        let events_doc = format!("Event senders for [`{}`], implemented for all of its implementors.", trait_name);
+        let events_trait = quote!{
+            #[doc = #events_doc]
+            #stability_attribute
+            #deprecated_attribute
+            pub trait #events_ident<#state>: #trait_ident<#t> {
+                #(#events)*
+            }
+            impl<#state, I: #trait_ident<#t>> #events_ident<#t> for I {}
+        };
+        (None, Some(events_trait))
+    } else {
+        (Some(quote!{#(#events)*}), None)
+    };
+
+    let ext_trait = ext_trait(protocol, interface, options).map(|ext_trait| quote!{
+        #stability_attribute
+        #deprecated_attribute
+        #ext_trait
+    });
+
+    // Only needed when `handler_fns` is set, as the arms of the match otherwise handle each request inline
+    let handlers = dispatch_requests.iter().filter(|_| handler_fns).map(|(_, handler, body)| quote!{
+        fn #handler<#state, I: #trait_ident<#t>>(_this: ::yutani::lease::Lease<I>, _event_loop: &mut ::yutani::wire::EventLoop<#t>, _client: &mut ::yutani::server::Client<#t>, _message: ::yutani::wire::Message) -> ::core::result::Result<(), ::yutani::wire::WlError<'static>> {
+            #body
+        }
+    });
+    // The hooks only observe, so a handler always runs once the request is dispatched to it
+    let intercept = |opcode: TokenStream, name: TokenStream, handler: TokenStream| if options.intercept_requests {
+        quote!{{
+            <Self as #trait_ident<#t>>::before_request(_client, <Self as #trait_ident<#t>>::INTERFACE, #opcode, #name);
+            let result = #handler(_this, _event_loop, _client, _message);
+            <Self as #trait_ident<#t>>::after_request(_client, <Self as #trait_ident<#t>>::INTERFACE, #opcode, #name, &result);
+            result
+        }}
+    } else {
+        quote!{#handler(_this, _event_loop, _client, _message)}
+    };
+    // A match with only the wildcard arm is linted by Clippy, so interfaces without requests always use the fallback
+    let dispatch_body = if interface.requests.is_empty() {
+        quote!{<Self as #trait_ident<#t>>::unknown_request(_this, _event_loop, _client, _message)}
+    } else if options.dispatch_strategy == DispatchStrategy::Table {
+        let entries = dispatch_requests.iter().map(|(_, handler, _)| quote!{#handler::<#t, Self>});
+        let call = intercept(quote!{opcode}, quote!{#mod_ident::REQUEST_NAMES[usize::from(opcode)]}, quote!{handler});
+        quote!{
+            #(#handlers)*
+            type Handler<T, I> = fn(::yutani::lease::Lease<I>, &mut ::yutani::wire::EventLoop<T>, &mut ::yutani::server::Client<T>, ::yutani::wire::Message) -> ::core::result::Result<(), ::yutani::wire::WlError<'static>>;
+            // A constant expression, so the table is promoted to a static rather than built for each request
+            let handlers: &[Handler<#t, Self>] = &[#(#entries),*];
+            let opcode = _message.opcode;
+            match handlers.get(usize::from(opcode)) {
+                ::core::option::Option::Some(handler) => #call,
+                ::core::option::Option::None => <Self as #trait_ident<#t>>::unknown_request(_this, _event_loop, _client, _message)
+            }
+        }
+    } else {
+        let arms = dispatch_requests.iter().zip(&interface.requests).map(|((opcode, handler, body), r)| {
+            let request_name = &r.name;
+            let body = if handler_fns { intercept(quote!{#opcode}, quote!{#request_name}, quote!{#handler::<#t, Self>}) } else { body.clone() };
+            quote!{
+                #opcode => {
+                    #body
                 }
             }
-            #[doc = "Create a new object that can be tracked by `yutani`"]
-            fn into_object(self, id: ::yutani::Id) -> ::yutani::lease::Resident<Self, T, ::yutani::server::Client<T>> {
-                ::yutani::lease::Resident::new(id, Self::dispatch, Self::INTERFACE, Self::VERSION, self)
+        });
+        quote!{
+            #(#handlers)*
+            match _message.opcode {
+                #(#arms,)*
+                _ => <Self as #trait_ident<#t>>::unknown_request(_this, _event_loop, _client, _message)
+            }
+        }
+    };
+    let downcast_error = wl_error("INTERNAL", name, "object is not of the type whose implementation it was dispatched to");
+    let opcode_error = wl_error("INVALID_OPCODE", name, "request opcode is not known");
+    let version_error = wl_error("UNSUPPORTED_VERSION", name, "version is higher than the highest version of the interface");
+    let marker = source_marker(protocol, "interface", name, interface.line, options);
+    let dispatch_doc = format!(
+        "Dispatches requests to the implementors of [`{}`] and makes them objects, implemented for all of them.", trait_name
+    );
+    let interface_trait = quote!{
+        #marker
+        #docs
+        #trait_alias
+        #stability_attribute
+        #deprecated_attribute
+        pub trait #trait_ident<#state>: #dispatch_trait<#t> {
+            #[doc = "The name of the interface."]
+            const INTERFACE: &'static ::core::primitive::str = #mod_ident::DESCRIPTOR.name;
+            #[doc = "The highest version of the interface."]
+            const VERSION: ::core::primitive::u32 = #mod_ident::DESCRIPTOR.version;
+            #[doc = "Handle a request with an opcode not known to this version of the protocol."]
+            #[doc = ""]
+            #[doc = "The message is passed through with its arguments unread so that it may be logged, forwarded or drained."]
+            #[doc = "By default the request is rejected with an invalid opcode error."]
+            fn unknown_request(_this: ::yutani::lease::Lease<Self>, _event_loop: &mut ::yutani::wire::EventLoop<#t>, _client: &mut ::yutani::server::Client<#t>, _message: ::yutani::wire::Message) -> ::core::result::Result<(), ::yutani::wire::WlError<'static>> {
+                ::core::result::Result::Err(#opcode_error)
             }
+            #check_capability
+            #request_hooks
+            #[doc = "Release what the object holds when it is destroyed, such as buffers, or tell other parts of the server that it is gone. By default nothing is done."]
+            #[doc = ""]
+            #[doc = "This is called exactly once for an object destroyed by a destructor request, after the arguments of the request are read and before its handler is called, which still receives the object. A request that fails before then, such as with a malformed argument, does not call it."]
+            #[doc = ""]
+            #[doc = "Nothing else calls it. The runtime drops the objects of a client that disconnects without a hook to call it from, and destructor events are sent without the event loop, so teardown in those cases should call it directly."]
+            fn on_destroy(_this: &mut Self, _event_loop: &mut ::yutani::wire::EventLoop<#t>, _client: &mut ::yutani::server::Client<#t>) {}
+            #(#requests)*
+            #events_in_trait
+        }
+        // The plumbing is implemented for every implementor, so it cannot be overridden or implemented by hand
+        #[doc = #dispatch_doc]
+        #stability_attribute
+        #deprecated_attribute
+        pub trait #dispatch_trait<#state>: 'static + ::core::marker::Sized {
+            #[doc = "The description of the interface."]
+            const DESCRIPTOR: &'static InterfaceDescriptor;
+            #[doc = "The entry point that dispatches requests to this implementation."]
+            const IMPLEMENTATION: Implementation<#t>;
+            #[doc = "Dispatch a request to an object of this type, failing if the object is of another type."]
+            fn dispatch(this: ::yutani::lease::Lease<dyn ::core::any::Any>, event_loop: &mut ::yutani::wire::EventLoop<#t>, client: &mut ::yutani::server::Client<#t>, message: ::yutani::wire::Message) -> ::core::result::Result<(), ::yutani::wire::WlError<'static>>;
+            #[doc = "Dispatch a request to an object of this type."]
+            fn dispatch_typed(this: ::yutani::lease::Lease<Self>, event_loop: &mut ::yutani::wire::EventLoop<#t>, client: &mut ::yutani::server::Client<#t>, message: ::yutani::wire::Message) -> ::core::result::Result<(), ::yutani::wire::WlError<'static>>;
+            #[doc = "Create a new object that can be tracked by `yutani`"]
+            fn into_object(self, id: ::yutani::Id) -> ::yutani::lease::Resident<Self, #t, ::yutani::server::Client<#t>>;
             #[doc = "Create a new object that can be tracked by `yutani`, with a given version"]
-            fn into_versioned_object(self, id: ::yutani::Id, version: u32) -> ::core::result::Result<::yutani::lease::Resident<Self, T, ::yutani::server::Client<T>>, ::yutani::wire::WlError<'static>> {
-                if version > Self::VERSION {
-                    ::core::result::Result::Err(::yutani::wire::WlError::UNSUPPORTED_VERSION)
+            fn into_versioned_object(self, id: ::yutani::Id, version: u32) -> ::core::result::Result<::yutani::lease::Resident<Self, #t, ::yutani::server::Client<#t>>, ::yutani::wire::WlError<'static>>;
+        }
+        impl<#state, I: #trait_ident<#t>> #dispatch_trait<#t> for I {
+            const DESCRIPTOR: &'static InterfaceDescriptor = &#mod_ident::DESCRIPTOR;
+            const IMPLEMENTATION: Implementation<#t> = Implementation { descriptor: &#mod_ident::DESCRIPTOR, dispatch: <Self as #dispatch_trait<#t>>::dispatch };
+            fn dispatch(_this: ::yutani::lease::Lease<dyn ::core::any::Any>, _event_loop: &mut ::yutani::wire::EventLoop<#t>, _client: &mut ::yutani::server::Client<#t>, _message: ::yutani::wire::Message) -> ::core::result::Result<(), ::yutani::wire::WlError<'static>> {
+                let _this: ::yutani::lease::Lease<Self> = _this.downcast().ok_or(#downcast_error)?;
+                <Self as #dispatch_trait<#t>>::dispatch_typed(_this, _event_loop, _client, _message)
+            }
+            fn dispatch_typed(_this: ::yutani::lease::Lease<Self>, _event_loop: &mut ::yutani::wire::EventLoop<#t>, _client: &mut ::yutani::server::Client<#t>, _message: ::yutani::wire::Message) -> ::core::result::Result<(), ::yutani::wire::WlError<'static>> {
+                #dispatch_body
+            }
+            fn into_object(self, id: ::yutani::Id) -> ::yutani::lease::Resident<Self, #t, ::yutani::server::Client<#t>> {
+                ::yutani::lease::Resident::new(id, <Self as #dispatch_trait<#t>>::dispatch, <Self as #trait_ident<#t>>::INTERFACE, <Self as #trait_ident<#t>>::VERSION, self)
+            }
+            fn into_versioned_object(self, id: ::yutani::Id, version: u32) -> ::core::result::Result<::yutani::lease::Resident<Self, #t, ::yutani::server::Client<#t>>, ::yutani::wire::WlError<'static>> {
+                if version > <Self as #trait_ident<#t>>::VERSION {
+                    ::core::result::Result::Err(#version_error)
                 } else {
-                    ::core::result::Result::Ok(::yutani::lease::Resident::new(id, Self::dispatch, Self::INTERFACE, version, self))
+                    ::core::result::Result::Ok(::yutani::lease::Resident::new(id, <Self as #dispatch_trait<#t>>::dispatch, <Self as #trait_ident<#t>>::INTERFACE, version, self))
                 }
             }
-            #(#requests)*
-            #(#events)*
-        }
-        pub mod #mod_ident {
-            #(#enums)*
         }
-    }
-}
+        #events_trait
+        #ext_trait
+        #concrete_dispatch
+    };
+    let (interface_trait, module_docs, implementation) = match core_dispatch {
+        Some(dispatch) => {
+            let note = format!("`{}` is implemented by the runtime, which dispatches its requests with `{}`.", name, options.core_interfaces[name]);
+            let implementation = quote!{
+                #[doc = "The entry point of the runtime that dispatches requests to the interface."]
+                pub fn implementation<#state>() -> super::Implementation<#t> {
+                    super::Implementation { descriptor: &DESCRIPTOR, dispatch: #dispatch }
+                }
+            };
+            // There is no trait to mark, so the module is marked instead
+            (None, quote!{#marker #docs #[doc = ""] #[doc = #note]}, Some(implementation))
+        },
+        None => (Some(interface_trait), quote!{#[doc = #mod_doc]}, None)
+    };
 
-pub fn enumeration(enumeration: &Enum) -> TokenStream {
-    let ident = Ident::new_raw(&enumeration.name.to_pascal_case(), Span::call_site());
-    let since = enumeration.since.map(|since| {
-        let since = format!("`Since version {}`", since);
+    // Core interfaces have no trait to lease their objects as
+    let lease = (!core).then(|| {
+        let doc = format!(
+            "Lease the object with an id from a client as `I`, an implementation of [`{}`](super::{}), such as an object that a request refers to by its id.",
+            trait_name, ident::name(&trait_name)
+        );
+        let no_object = wl_error("NO_OBJECT", name, "no object has the id");
+        let leased = wl_error("INTERNAL", name, "the object is already leased, such as by the request being dispatched");
+        let wrong_interface = wl_error("NO_OBJECT", name, "the object is of another interface");
+        let downcast_error = wl_error("INTERNAL", name, "object is not of the type whose implementation it was leased as");
         quote!{
+            #[doc = #doc]
             #[doc = ""]
-            #[doc = #since]
+            #[doc = "Fails with the `NO_OBJECT` protocol error if the client has no object with the id or it is of another interface, and with the `INTERNAL` error if it is already leased or is another implementation."]
+            pub fn lease<#state, I: super::#trait_ident<#t>>(client: &mut ::yutani::server::Client<#t>, id: ::yutani::Id) -> ::core::result::Result<::yutani::lease::Lease<I>, ::yutani::wire::WlError<'static>> {
+                let lease = client.get_mut(id).ok_or(#no_object)?.lease().ok_or(#leased)?;
+                if lease.interface() != DESCRIPTOR.name {
+                    return ::core::result::Result::Err(#wrong_interface)
+                }
+                lease.downcast().ok_or(#downcast_error)
+            }
         }
     });
-    let summary = enumeration.summary.as_ref().map(|summary| quote!{#[doc = #summary]});
-    let description = enumeration.description.as_ref().map(|description| quote! {#[doc = #description]});
 
-    let entries = enumeration.entries.iter().map(|entry| {
-        let name = if entry.name.starts_with(char::is_numeric) {
-            format!("{}_{}", enumeration.name, entry.name).to_shouty_snake_case()
-        } else { entry.name.to_shouty_snake_case() };
-        let ident = Ident::new_raw(&name, Span::call_site());
-        let since = entry.since.map(|since| {
-            let since = format!("`Since version {}`", since);
-            quote!{
-                #[doc = ""]
-                #[doc = #since]
-            }
-        });
-        let summary = entry.summary.as_ref().map(|summary| quote!{#[doc = #summary]});
-        let description = entry.description.as_ref().map(|description| quote! {#[doc = #description]});
-        let value = entry.value;
+    // Core interfaces are dispatched by the runtime, so their requests are not counted
+    let metrics = metrics_predicate.filter(|_| !core).map(|predicate| {
+        let counters = interface.requests.iter().map(|_| quote!{::core::sync::atomic::AtomicU64::new(0)});
         quote!{
-            #summary
-            #since
-            #[doc = ""]
-            #description
-            pub const #ident: Self = Self(#value);
+            #[cfg(#predicate)]
+            static REQUEST_COUNTS: [::core::sync::atomic::AtomicU64; REQUEST_COUNT as usize] = [#(#counters),*];
+            #[doc = "The number of requests dispatched to objects of the interface, indexed by opcode, with names in [`REQUEST_NAMES`]."]
+            #[cfg(#predicate)]
+            pub fn request_counts() -> &'static [::core::sync::atomic::AtomicU64] {
+                &REQUEST_COUNTS
+            }
         }
     });
-    let entries_debug = enumeration.entries.iter().map(|entry| {
-        let name = if entry.name.starts_with(char::is_numeric) {
-            format!("{}_{}", enumeration.name, entry.name).to_shouty_snake_case()
-        } else { entry.name.to_shouty_snake_case() };
-        let value = entry.value;
-        quote!{#value => ::core::write!(f, "{}({})", #name, #value)}
-    });
 
     quote!{
-        #summary
-        #since
-        #[doc = ""]
-        #description
-        #[repr(transparent)]
-        pub struct #ident(u32);
-        impl #ident {
-            #(#entries)*
-        }
-        impl ::core::convert::From<::core::primitive::u32> for #ident {
-            fn from(value: ::core::primitive::u32) -> Self {
-                Self(value)
-            }
-        }
-        impl ::core::convert::Into<::core::primitive::u32> for #ident {
-            fn into(self) -> ::core::primitive::u32 {
-                self.0
-            }
-        }
-        impl ::core::fmt::Debug for #ident {
-            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
-                match self.0 {
-                    #(#entries_debug,)*
-                    value => ::core::write!(f, "UNKNOWN({})", value)
-                }
-            }
+        #interface_trait
+        #mod_alias
+        #stability_attribute
+        #deprecated_attribute
+        #module_docs
+        pub mod #mod_ident {
+            #[allow(unused_imports)]
+            use super::ReadArgs as _;
+            #[doc = "The description of the interface."]
+            pub const DESCRIPTOR: super::InterfaceDescriptor = super::InterfaceDescriptor {
+                name: #name,
+                version: #version,
+                requests: REQUEST_NAMES,
+                events: EVENT_NAMES
+            };
+            #[doc = "The number of requests, and one more than the highest request opcode."]
+            pub const REQUEST_COUNT: ::core::primitive::u16 = #request_count;
+            #[doc = "The number of events, and one more than the highest event opcode."]
+            pub const EVENT_COUNT: ::core::primitive::u16 = #event_count;
+            #[doc = "Request names indexed by opcode."]
+            pub const REQUEST_NAMES: &[&::core::primitive::str] = &[#(#request_names),*];
+            #[doc = "Event names indexed by opcode."]
+            pub const EVENT_NAMES: &[&::core::primitive::str] = &[#(#event_names),*];
+            #[doc = "The smallest size of each request in bytes, with empty strings and arrays, indexed by opcode."]
+            pub const REQUEST_MIN_SIZES: &[::core::primitive::u16] = &[#(#request_sizes),*];
+            #[doc = "The smallest size of each event in bytes, with empty strings and arrays, indexed by opcode."]
+            pub const EVENT_MIN_SIZES: &[::core::primitive::u16] = &[#(#event_sizes),*];
+            #[doc = "Whether each request destroys the object it is sent to, indexed by opcode."]
+            pub const REQUEST_DESTRUCTORS: &[::core::primitive::bool] = &[#(#request_destructors),*];
+            #[doc = "Whether each event destroys the object it is sent from, indexed by opcode."]
+            pub const EVENT_DESTRUCTORS: &[::core::primitive::bool] = &[#(#event_destructors),*];
+            const _: () = ::core::assert!(
+                REQUEST_NAMES.len() == REQUEST_COUNT as usize
+                    && REQUEST_MIN_SIZES.len() == REQUEST_COUNT as usize
+                    && REQUEST_DESTRUCTORS.len() == REQUEST_COUNT as usize
+                    && super::names_unique(REQUEST_NAMES),
+                #request_tables_message
+            );
+            const _: () = ::core::assert!(
+                EVENT_NAMES.len() == EVENT_COUNT as usize
+                    && EVENT_MIN_SIZES.len() == EVENT_COUNT as usize
+                    && EVENT_DESTRUCTORS.len() == EVENT_COUNT as usize
+                    && super::names_unique(EVENT_NAMES),
+                #event_tables_message
+            );
+            #implementation
+            #lease
+            #metrics
+            #types
+            #(#args_structs)*
+            #(#event_structs)*
+            #erased
+            #debug
         }
     }
 }
 
-pub fn request(request: &Request) -> TokenStream {
-    let ident = Ident::new_raw(&request.name.to_snake_case(), Span::call_site());
-    let since = request.since.map(|since| {
-        let since = format!("`Since version {}`", since);
-        quote!{
-            #[doc = ""]
-            #[doc = #since]
+/// Allow `clippy::too_many_arguments` for a generated function with `count` parameters, if that is over the limit
+/// of Clippy, as the parameters of handlers and senders are the arguments of their message.
+fn allow_many_params(count: usize) -> Option<TokenStream> {
+    (count > 7).then(|| quote!{#[allow(clippy::too_many_arguments)]})
+}
+
+/// A `WlError` of the kind of the constant `kind`, such as `INTERNAL`, described as `context: detail`, where the
+/// context is the interface or message the error occurred in, such as `wl_surface` or `wl_surface.attach`.
+pub(crate) fn wl_error(kind: &str, context: &str, detail: &str) -> TokenStream {
+    let kind = Ident::new(kind, Span::call_site());
+    let description = format!("{}: {}", context, detail);
+    quote!{
+        ::yutani::wire::WlError {
+            description: ::std::borrow::Cow::Borrowed(#description),
+            ..::yutani::wire::WlError::#kind
         }
+    }
+}
+
+/// A compile time assertion that `opcode` is the opcode of the message `name` in the table of names at `names`, so
+/// that the opcodes used on the wire cannot drift from the message tables without failing to compile.
+fn opcode_assertion(names: TokenStream, names_match: TokenStream, opcode: u16, name: &str) -> TokenStream {
+    let index = usize::from(opcode);
+    let message = format!("opcode {} is not the opcode of `{}`", opcode, name);
+    quote!{
+        const _: () = ::core::assert!(#names_match(#names[#index], #name), #message);
+    }
+}
+
+/// A markdown table documenting the opcode, name and version of each message.
+fn opcode_table<'a>(header: &str, messages: impl Iterator<Item = (u16, &'a str, Option<u32>, bool)>) -> Option<String> {
+    let rows: Vec<_> = messages.map(|(opcode, name, since, destructor)| {
+        let destructor = if destructor { " (destructor)" } else { "" };
+        format!("| {} | `{}`{} | {} |", opcode, name, destructor, since.unwrap_or(1))
+    }).collect();
+    if rows.is_empty() {
+        return None
+    }
+    Some(format!("## {}\n\n| Opcode | Name | Since |\n|-|-|-|\n{}", header, rows.join("\n")))
+}
+
+/// The number of array bytes shown by the generated debug printers before truncating.
+const DEBUG_ARRAY_LIMIT: usize = 16;
+
+/// Generate `debug_request` and `debug_event`, which decode a message into a human-readable line without requiring
+/// an implementation of the interface trait.
+pub fn debug_printer(protocol: &Protocol, interface: &Interface, options: &CodegenOptions) -> TokenStream {
+    let requests = interface.requests_with_opcodes().map(|(opcode, r)| (opcode, r.name.as_str(), r.args.as_slice()));
+    let events = interface.events_with_opcodes().map(|(opcode, e)| (opcode, e.name.as_str(), e.args.as_slice()));
+    let debug_requests = debug_match(debug_messages(protocol, interface, options, requests).collect(), wl_error(
+        "INVALID_OPCODE", &interface.name, "request opcode is not known"
+    ));
+    let debug_events = debug_match(debug_messages(protocol, interface, options, events).collect(), wl_error(
+        "INVALID_OPCODE", &interface.name, "event opcode is not known"
+    ));
+    let has_arrays = interface.requests.iter().flat_map(|r| &r.args)
+        .chain(interface.events.iter().flat_map(|e| &e.args))
+        .any(|a| a.ty == DataType::Array);
+    let array_limit = has_arrays.then(|| {
+        let array_limit = DEBUG_ARRAY_LIMIT;
+        quote!{const DEBUG_ARRAY_LIMIT: usize = #array_limit;}
     });
-    let summary = request.summary.as_ref().map(|summary| quote!{#[doc = #summary]});
-    let description = request.description.as_ref().map(|description| quote! {#[doc = #description]});
 
-    let args = request.args.iter().map(|a| {
-        let ident = Ident::new_raw(&a.name.to_snake_case(), Span::call_site());
-        let ty = a.ty();
-        quote!{
-            #ident: #ty
+    quote!{
+        #array_limit
+        #[doc = "Decode a request from the stream into a human-readable line, such as `wl_surface.attach(buffer: 12, x: 0, y: 0)`"]
+        pub fn debug_request(message: &::yutani::wire::Message, _stream: &mut ::yutani::wire::Stream) -> ::core::result::Result<::std::string::String, ::yutani::wire::WlError<'static>> {
+            #debug_requests
         }
-    });
-    let arg_summaries: Vec<_> = request.args.iter().filter_map(|a| {
-        a.summary.as_ref().map(|summary| {
-            let summary = format!("\n`{}`: {}", a.name, summary);
-            quote!{#[doc = #summary]}
-        })
-    }).collect();
-    let arg_summaries_header = if arg_summaries.is_empty() {
-        None
-    } else {
-        Some(quote!{
-            #[doc = ""]
-            #[doc = "## Arguments"]
-        })
-    };
+        #[doc = "Decode an event from the stream into a human-readable line, such as `wl_output.scale(factor: 2)`"]
+        pub fn debug_event(message: &::yutani::wire::Message, _stream: &mut ::yutani::wire::Stream) -> ::core::result::Result<::std::string::String, ::yutani::wire::WlError<'static>> {
+            #debug_events
+        }
+    }
+}
 
+/// Match the opcode of a message to decode it, failing with `error` for unknown opcodes, or always failing if there are
+/// no messages, as Clippy lints a match with only the wildcard arm.
+fn debug_match(arms: Vec<TokenStream>, error: TokenStream) -> TokenStream {
+    if arms.is_empty() {
+        return quote!{
+            let _ = message;
+            ::core::result::Result::Err(#error)
+        }
+    }
     quote!{
-        #summary
-        #since
-        #[doc = ""]
-        #description
-        #arg_summaries_header
-        #(#arg_summaries)*
-        fn #ident(this: ::yutani::lease::Lease<Self>, event_loop: &mut ::yutani::wire::EventLoop<T>, client: &mut ::yutani::server::Client<T> #(, #args)*) -> ::core::result::Result<(), ::yutani::wire::WlError<'static>>;
+        match message.opcode {
+            #(#arms,)*
+            _ => ::core::result::Result::Err(#error)
+        }
     }
 }
 
-pub fn event(interface: &Interface, event: &Event, opcode: u16) -> TokenStream {
-    let name = &interface.name;
-    let event_name = &event.name.to_snake_case();
-    let ident = Ident::new_raw(event_name, Span::call_site());
+fn debug_messages<'a>(protocol: &'a Protocol, interface: &'a Interface, options: &'a CodegenOptions, messages: impl Iterator<Item = (u16, &'a str, &'a [Arg])> + 'a) -> impl Iterator<Item = TokenStream> + 'a {
     let stream = Ident::new("_stream", Span::call_site());
-    let since = event.since.map(|since| {
-        let since = format!("`Since version {}`", since);
+    messages.map(move |(opcode, name, args)| {
+        let context = format!("{}.{}", interface.name, name);
+        let define_args = args.iter().map(|a| {
+            let ident = ident::snake(&a.name);
+            let getter = a.getter(&stream, module_enum_path(protocol, interface, a, options).as_ref(), &context, options);
+            quote!{let #ident = #getter;}
+        });
+        let (templates, args_debug): (Vec<_>, Vec<_>) = args.iter().map(|a| {
+            let (template, debug) = a.debug(module_enum_path(protocol, interface, a, options));
+            (format!("{}: {}", a.name, template), debug)
+        }).unzip();
+        let template = format!("{}.{}({})", interface.name, name, templates.join(", "));
+        let line = if args.is_empty() {
+            quote!{::std::string::String::from(#template)}
+        } else {
+            quote!{::std::format!(#template #(, #args_debug)*)}
+        };
         quote!{
-            #[doc = ""]
-            #[doc = #since]
+            #opcode => {
+                #(#define_args)*
+                ::core::result::Result::Ok(#line)
+            }
         }
-    });
-    let summary = event.summary.as_ref().map(|summary| quote!{#[doc = #summary]});
-    let description = event.description.as_ref().map(|description| quote! {#[doc = #description]});
+    })
+}
 
-    let args = event.args.iter().map(|a| {
-        let ident = Ident::new_raw(&a.name.to_snake_case(), Span::call_site());
-        let ty = a.send_ty();
+/// The items of an interface module that do not depend on which side of the connection is generated, which are the
+/// enums of the interface.
+/// 
+/// Only server code is generated, so these are part of the interface module. Code for both sides should generate
+/// these once and refer to them from each side, so that both use the same enum types.
+pub fn interface_types(protocol: &Protocol, interface: &Interface, options: &CodegenOptions) -> TokenStream {
+    let enums = interface.enums.iter().map(|e| {
+        let marker = source_marker(protocol, "enum", &format!("{}.{}", interface.name, e.name), e.line, options);
+        let enumeration = enumeration(protocol, e, options);
         quote!{
-            #ident: #ty
+            #marker
+            #enumeration
         }
     });
-    let args_senders = event.args.iter().map(|a| a.sender(&stream));
-    let arg_summaries: Vec<_> = event.args.iter().filter_map(|a| {
-        a.summary.as_ref().map(|summary| {
-            let summary = format!("\n`{}`: {}", a.name, summary);
-            quote!{#[doc = #summary]}
-        })
-    }).collect();
-    let arg_summaries_header = if arg_summaries.is_empty() {
-        None
+    quote!{#(#enums)*}
+}
+
+/// The name of the trait with the event senders of an interface, which is suffixed with `Events` when traits are split.
+fn events_trait_name(protocol: &Protocol, interface: &Interface, options: &CodegenOptions) -> String {
+    if options.split_traits {
+        format!("{}Events", ident::pascal_case(interface_name(protocol, interface, options), options))
     } else {
-        Some(quote!{
-            #[doc = ""]
-            #[doc = "## Arguments"]
-        })
-    };
+        trait_name(protocol, interface, options)
+    }
+}
 
-    let args_debug_idents = event.args.iter().map(|a| {
-        let ident = Ident::new_raw(&a.name.to_snake_case(), Span::call_site());
-        quote!{#ident}
-    });
-    let args_debug_templates = event.args.iter().enumerate().map(|(i, _)| {
-        if i == 0 {
-            quote!{"{:?}"}
-        } else {
-            quote!{", {:?}"}
-        }
-    });
+/// The name of the [extension trait](CodegenOptions::ext_traits) of an interface, such as `WlSurfaceExt`.
+fn ext_trait_name(protocol: &Protocol, interface: &Interface, options: &CodegenOptions) -> String {
+    format!("{}Ext", ident::pascal_case(interface_name(protocol, interface, options), options))
+}
 
-    quote!{
-        #summary
-        #since
-        #[doc = ""]
-        #description
-        #arg_summaries_header
-        #(#arg_summaries)*
-        fn #ident(_this: &mut ::yutani::lease::Lease<Self>, _client: &mut ::yutani::server::Client<T> #(, #args)*) -> ::core::result::Result<(), ::yutani::wire::WlError<'static>> {
-            #[cfg(debug_assertions)]
-            {
-                ::std::println!(::std::concat!(" -> ", #name, "@{}.", #event_name, "(", #(#args_debug_templates,)* ")"), _this.id(), #(#args_debug_idents,)*);
-            }
-            let #stream = _client.stream();
-            let _key = #stream.start_message(_this.id(), #opcode);
-            #(#args_senders;)*
-            #stream.commit(_key)
-        }
+/// The name of the companion trait of an interface trait that dispatches requests to its implementors and makes them
+/// objects, such as `WlSurfaceDispatch`.
+fn dispatch_trait_name(protocol: &Protocol, interface: &Interface, options: &CodegenOptions) -> String {
+    format!("{}Dispatch", ident::pascal_case(interface_name(protocol, interface, options), options))
+}
+
+/// The name of the trait implemented for an interface, which is suffixed with `Requests` when traits are split.
+fn trait_name(protocol: &Protocol, interface: &Interface, options: &CodegenOptions) -> String {
+    let name = ident::pascal_case(interface_name(protocol, interface, options), options);
+    if options.split_traits {
+        name + "Requests"
+    } else {
+        name
+    }
+}
+
+/// The item that links to an interface point to, which is its trait, or its module for a
+/// [core interface](CodegenOptions::core_interfaces) that has no trait.
+fn interface_link(protocol: &Protocol, interface: &Interface, options: &CodegenOptions) -> String {
+    if options.core_interfaces.contains_key(&interface.name) {
+        ident::name(&ident::snake_case(interface_name(protocol, interface, options)))
+    } else {
+        ident::name(&trait_name(protocol, interface, options))
+    }
+}
+
+/// The name of the module for the code generated for a protocol, such as the file name of the code, which is
+/// [`CodegenOptions::rust_module`], [`Protocol::rust_module`] or otherwise the protocol name in snake case.
+pub fn module_name(protocol: &Protocol, options: &CodegenOptions) -> String {
+    options.rust_module.as_ref().or(protocol.rust_module.as_ref()).cloned()
+        .unwrap_or_else(|| ident::snake_case(&protocol.name))
+}
+
+/// The path of the module for the code generated for a protocol, which is its [`module_name`] within its
+/// [namespace](Protocol::namespace), such as `acme::wayland`, relative to where the generated modules are placed.
+pub fn module_path(protocol: &Protocol, options: &CodegenOptions) -> String {
+    let module = module_name(protocol, options);
+    match &protocol.namespace {
+        Some(namespace) => format!("{}::{}", namespace, module),
+        None => module
+    }
+}
+
+/// The name used for the items generated for an interface, which may differ from the protocol name.
+fn interface_name<'a>(protocol: &Protocol, interface: &'a Interface, options: &CodegenOptions) -> &'a str {
+    if options.strip_unstable_prefix && protocol.stability(interface) == Stability::Unstable {
+        interface.name.strip_prefix('z').unwrap_or(&interface.name)
+    } else {
+        &interface.name
+    }
+}
+
+/// The path to the enum type of an argument of `interface`, relative to the protocol module, and whether it is
+/// backed by an `i32`.
+fn enum_path(protocol: &Protocol, interface: &Interface, arg: &Arg, options: &CodegenOptions) -> Option<(TokenStream, bool)> {
+    let Some((owner, enumeration)) = protocol.find_enum(interface, arg.enumeration.as_ref()?) else {
+        return external_enum_path(protocol, arg, options)
+    };
+    let ident = ident::pascal(&enumeration.name, options);
+    let module = ident::snake(interface_name(protocol, owner, options));
+    Some((quote!{#module::#ident}, protocol.is_signed_enum(enumeration)))
+}
+
+/// The path to the enum type of an argument of `interface`, relative to an interface module.
+fn module_enum_path(protocol: &Protocol, interface: &Interface, arg: &Arg, options: &CodegenOptions) -> Option<(TokenStream, bool)> {
+    if protocol.find_enum(interface, arg.enumeration.as_ref()?).is_none() {
+        return external_enum_path(protocol, arg, options)
+    }
+    enum_path(protocol, interface, arg, options).map(|(path, signed)| (quote!{super::#path}, signed))
+}
+
+/// The absolute path to the enum of an [external interface](Protocol::externals) that an argument refers to, such as
+/// `wl_output.transform`, in the [module of its protocol](CodegenOptions::external_modules).
+/// 
+/// Which arguments refer to the enum in the other protocol is not known, so it is taken to be backed by an `i32` if
+/// the argument is an `int`, as it is when generated from that protocol.
+fn external_enum_path(protocol: &Protocol, arg: &Arg, options: &CodegenOptions) -> Option<(TokenStream, bool)> {
+    let (owner, name) = arg.enumeration.as_ref()?.split_once('.')?;
+    if !protocol.is_external(owner) {
+        return None
+    }
+    let module: TokenStream = options.external_modules.get(owner)?.parse().expect("validated external module");
+    let owner = ident::snake(owner);
+    let ident = ident::pascal(name, options);
+    Some((quote!{#module::#owner::#ident}, arg.is_int()))
+}
+
+/// Reject a decoded argument whose value is not an entry of its enum, or has bits set outside of a bitfield.
+fn enum_check(protocol: &Protocol, interface: &Interface, request: &Request, arg: &Arg, options: &CodegenOptions) -> Option<TokenStream> {
+    let (path, signed) = enum_path(protocol, interface, arg, options)?;
+    let ident = ident::snake(&arg.name);
+    if !matches!(arg.ty, DataType::Int | DataType::Uint) {
+        return None
+    }
+    let raw = arg.enum_value(signed);
+    let message = format!(
+        "{}.{}: invalid value {{}} for argument `{}` of enum `{}`",
+        interface.name, request.name, arg.name, arg.enumeration.as_ref()?
+    );
+    Some(quote!{
+        if #path::try_from_raw(#raw).is_err() {
+            return ::core::result::Result::Err(::yutani::wire::WlError {
+                description: ::std::borrow::Cow::Owned(::std::format!(#message, #ident)),
+                ..::yutani::wire::WlError::CORRUPT
+            })
+        }
+    })
+}
+
+pub fn enumeration(protocol: &Protocol, enumeration: &Enum, options: &CodegenOptions) -> TokenStream {
+    let signed = protocol.is_signed_enum(enumeration);
+    let repr = if signed {
+        quote!{::core::primitive::i32}
+    } else {
+        quote!{::core::primitive::u32}
+    };
+    if enumeration.is_rust_enum(options) {
+        return rust_enumeration(enumeration, options, signed)
+    }
+    let ident = ident::pascal(&enumeration.name, options);
+    let summary = fallback_summary(&enumeration.summary, &enumeration.description, || format!("Enum `{}`", enumeration.name));
+    let docs = docs([summary, since_doc(enumeration.since), enumeration.description.clone()]);
+
+    let entries = enumeration.entries.iter().map(|entry| {
+        let ident = ident::ident(&entry_name(enumeration, entry));
+        let doc = entry_doc(enumeration, entry);
+        let alias = doc_alias(&entry.name, &ident);
+        let value = entry_value(entry.value, signed);
+        quote!{
+            #doc
+            #alias
+            pub const #ident: Self = Self(#value);
+        }
+    });
+    let (aliases, canonical): (Vec<_>, Vec<_>) = enumeration.entries.iter().partition(|entry| enumeration.is_alias(entry));
+    let canonical_idents = canonical.iter().map(|entry| ident::ident(&entry_name(enumeration, entry)));
+    let canonical_names = canonical.iter().map(|entry| entry_name(enumeration, entry));
+    let aliases = aliases.iter().map(|entry| {
+        let name = entry_name(enumeration, entry);
+        let ident = ident::ident(&name);
+        quote!{(#name, Self::#ident)}
+    });
+    let mut values: Vec<_> = canonical.iter().map(|entry| entry.value).collect();
+    values.sort_unstable();
+    // Consecutive values are matched as a range, as Clippy lints a pattern of each value
+    let contiguous = values.windows(2).all(|pair| pair[1] == pair[0] + 1);
+    let values = match values[..] {
+        [first, .., last] if contiguous => {
+            let (first, last) = (entry_value(first, signed), entry_value(last, signed));
+            quote!{#first..=#last}
+        },
+        _ => {
+            let values = values.iter().map(|&value| entry_value(value, signed));
+            quote!{#(#values)|*}
+        }
+    };
+    let mask = entry_value(enumeration.entries.iter().fold(0, |mask, entry| mask | entry.value), signed);
+    let bitfield = enumeration.bitfield.then(|| quote!{
+        #[doc = "Every flag of the bitfield."]
+        pub const MASK: Self = Self(#mask);
+        #[doc = "Clear any bits that do not belong to a flag."]
+        pub fn truncate(self) -> Self {
+            Self(self.0 & Self::MASK.0)
+        }
+    });
+    let is_known = if enumeration.bitfield {
+        quote!{self.0 & !Self::MASK.0 == 0}
+    } else if enumeration.entries.is_empty() {
+        quote!{false}
+    } else {
+        quote!{::core::matches!(self.0, #values)}
+    };
+    let entries_str = canonical.iter().map(|entry| {
+        let name = entry_name(enumeration, entry);
+        let value = entry_value(entry.value, signed);
+        quote!{#value => ::core::option::Option::Some(#name)}
+    }).collect::<Vec<_>>();
+    let flags: Vec<_> = enumeration.entries.iter().filter(|entry| entry.value != 0).map(|entry| {
+        let name = entry_name(enumeration, entry);
+        let value = entry_value(entry.value, signed);
+        quote!{(#value, #name)}
+    }).collect();
+    // Bitfields are written as their flags separated by `|`, followed by any bits that do not belong to a flag in
+    // hex, alike for `Display` and `Debug` so that logs and error messages read the same
+    let (display, debug) = if enumeration.bitfield {
+        let write_flags = quote!{
+            if let ::core::option::Option::Some(name) = self.as_str() {
+                return f.write_str(name);
+            }
+            let mut remaining = self.0;
+            if remaining == 0 {
+                return f.write_str("(empty)");
+            }
+            let mut first = true;
+            for (value, name) in [#(#flags),*] {
+                if self.0 & value == value && remaining & value != 0 {
+                    if !first {
+                        f.write_str(" | ")?;
+                    }
+                    f.write_str(name)?;
+                    remaining &= !value;
+                    first = false;
+                }
+            }
+            if first {
+                ::core::write!(f, "{:#x}", remaining)
+            } else if remaining != 0 {
+                ::core::write!(f, " | {:#x}", remaining)
+            } else {
+                ::core::result::Result::Ok(())
+            }
+        };
+        (write_flags.clone(), write_flags)
+    } else {
+        let display = quote!{
+            match self.as_str() {
+                ::core::option::Option::Some(name) => f.write_str(name),
+                ::core::option::Option::None => ::core::write!(f, "{}", self.0)
+            }
+        };
+        let debug = quote!{
+            match self.as_str() {
+                ::core::option::Option::Some(name) => ::core::write!(f, "{}({})", name, self.0),
+                ::core::option::Option::None => ::core::write!(f, "UNKNOWN({})", self.0)
+            }
+        };
+        (display, debug)
+    };
+    let default = enum_default(enumeration, &ident, |entry| {
+        let ident = ident::ident(&entry_name(enumeration, entry));
+        quote!{Self::#ident}
+    });
+    let from_str = enum_from_str(enumeration, &ident, |entry| {
+        let ident = ident::ident(&entry_name(enumeration, entry));
+        quote!{Self::#ident}
+    });
+    let serde = enum_serde(enumeration, &ident, options, signed, quote!{::core::result::Result::Ok(#ident::from(value))});
+    let alias = doc_alias(&enumeration.name, &ident);
+    let lenient_doc = format!(
+        "`From<{0}>` is also implemented for lenient conversions, which prevents implementing `TryFrom<{0}>`.",
+        if signed { "i32" } else { "u32" }
+    );
+    let is_known_doc = if enumeration.bitfield {
+        "Check that no bits are set other than those of the entries."
+    } else {
+        "Check that the value corresponds to an entry."
+    };
+
+    quote!{
+        #docs
+        #alias
+        #[derive(Clone, Copy, PartialEq, Eq, Hash)]
+        #[repr(transparent)]
+        pub struct #ident(#repr);
+        impl #ident {
+            #(#entries)*
+            #[doc = #is_known_doc]
+            pub fn is_known(self) -> ::core::primitive::bool {
+                #is_known
+            }
+            #bitfield
+            #[doc = "Every entry in protocol order, excluding aliases of an earlier entry with the same value."]
+            pub const ENTRIES: &'static [Self] = &[#(Self::#canonical_idents),*];
+            #[doc = "The names of `ENTRIES`."]
+            pub const ENTRY_NAMES: &'static [&'static ::core::primitive::str] = &[#(#canonical_names),*];
+            #[doc = "Entries sharing the value of an earlier entry, and their names."]
+            pub const ALIASES: &'static [(&'static ::core::primitive::str, Self)] = &[#(#aliases),*];
+            #[doc = "Iterate over `ENTRIES`."]
+            pub fn iter() -> impl ::core::iter::Iterator<Item = Self> {
+                Self::ENTRIES.iter().copied()
+            }
+            #[doc = "The name of the entry with exactly this value."]
+            pub fn as_str(&self) -> ::core::option::Option<&'static ::core::primitive::str> {
+                match self.0 {
+                    #(#entries_str,)*
+                    _ => ::core::option::Option::None
+                }
+            }
+            #[doc = "Convert from a raw value, failing if it is not known to the protocol."]
+            #[doc = ""]
+            #[doc = #lenient_doc]
+            pub fn try_from_raw(value: #repr) -> ::core::result::Result<Self, super::UnknownEnumValue> {
+                let value = Self(value);
+                if value.is_known() {
+                    ::core::result::Result::Ok(value)
+                } else {
+                    ::core::result::Result::Err(super::UnknownEnumValue { value: value.0.into() })
+                }
+            }
+            #[doc = "Convert from a raw value, which need not be known to the protocol, in `const` contexts."]
+            pub const fn from_raw(value: #repr) -> Self {
+                Self(value)
+            }
+            #[doc = "The raw value, in `const` contexts."]
+            pub const fn to_raw(self) -> #repr {
+                self.0
+            }
+        }
+        impl ::core::convert::From<#repr> for #ident {
+            fn from(value: #repr) -> Self {
+                Self(value)
+            }
+        }
+        impl ::core::convert::From<#ident> for #repr {
+            fn from(value: #ident) -> Self {
+                value.0
+            }
+        }
+        impl ::core::cmp::PartialEq<#repr> for #ident {
+            fn eq(&self, other: &#repr) -> ::core::primitive::bool {
+                self.0 == *other
+            }
+        }
+        impl ::core::cmp::PartialEq<#ident> for #repr {
+            fn eq(&self, other: &#ident) -> ::core::primitive::bool {
+                *self == other.0
+            }
+        }
+        impl ::core::fmt::Debug for #ident {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                #debug
+            }
+        }
+        impl ::core::fmt::Display for #ident {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                #display
+            }
+        }
+        #from_str
+        #default
+        #serde
+    }
+}
+
+/// Generate an enum as a Rust enum, which must not be a bitfield or contain entries sharing a value.
+fn rust_enumeration(enumeration: &Enum, options: &CodegenOptions, signed: bool) -> TokenStream {
+    let (repr, repr_hint) = if signed {
+        (quote!{::core::primitive::i32}, Ident::new("i32", Span::call_site()))
+    } else {
+        (quote!{::core::primitive::u32}, Ident::new("u32", Span::call_site()))
+    };
+    let ident = ident::pascal(&enumeration.name, options);
+    let summary = fallback_summary(&enumeration.summary, &enumeration.description, || format!("Enum `{}`", enumeration.name));
+    let docs = docs([summary, since_doc(enumeration.since), enumeration.description.clone()]);
+
+    let variants: Vec<_> = enumeration.entries.iter().map(|entry| {
+        ident::pascal(&entry_name(enumeration, entry), options)
+    }).collect();
+    let names: Vec<_> = enumeration.entries.iter().map(|entry| entry_name(enumeration, entry)).collect();
+    let values: Vec<_> = enumeration.entries.iter().map(|entry| entry_value(entry.value, signed)).collect();
+    let default_entry = enumeration.default_entry();
+    let entry_docs = enumeration.entries.iter().zip(&variants).map(|(entry, variant)| {
+        let doc = entry_doc(enumeration, entry);
+        let alias = doc_alias(&entry.name, variant);
+        let default = default_entry.is_some_and(|default| std::ptr::eq(default, entry)).then(|| quote!{#[default]});
+        quote!{
+            #doc
+            #alias
+            #default
+        }
+    });
+    let derive_default = default_entry.map(|_| quote!{, Default});
+    let alias = doc_alias(&enumeration.name, &ident);
+    let variant = |entry: &Entry| {
+        let variant = ident::pascal(&entry_name(enumeration, entry), options);
+        quote!{Self::#variant}
+    };
+    let from_str = enum_from_str(enumeration, &ident, variant);
+    let serde = enum_serde(enumeration, &ident, options, signed, quote!{#ident::try_from_raw(value).map_err(E::custom)});
+
+    quote!{
+        #docs
+        #alias
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash #derive_default)]
+        #[non_exhaustive]
+        #[repr(#repr_hint)]
+        pub enum #ident {
+            #(
+                #entry_docs
+                #variants = #values,
+            )*
+        }
+        impl #ident {
+            #[doc = "Every entry in protocol order."]
+            pub const ENTRIES: &'static [Self] = &[#(Self::#variants),*];
+            #[doc = "The names of `ENTRIES`."]
+            pub const ENTRY_NAMES: &'static [&'static ::core::primitive::str] = &[#(#names),*];
+            #[doc = "Entries sharing the value of an earlier entry, which Rust enums never have."]
+            pub const ALIASES: &'static [(&'static ::core::primitive::str, Self)] = &[];
+            #[doc = "Iterate over `ENTRIES`."]
+            pub fn iter() -> impl ::core::iter::Iterator<Item = Self> {
+                Self::ENTRIES.iter().copied()
+            }
+            #[doc = "Check that the value corresponds to an entry, which is always true of a Rust enum."]
+            pub fn is_known(self) -> ::core::primitive::bool {
+                true
+            }
+            #[doc = "Convert from a raw value, failing if it is not known to the protocol."]
+            pub fn try_from_raw(value: #repr) -> ::core::result::Result<Self, super::UnknownEnumValue> {
+                match value {
+                    #(#values => ::core::result::Result::Ok(Self::#variants),)*
+                    value => ::core::result::Result::Err(super::UnknownEnumValue { value: value.into() })
+                }
+            }
+            #[doc = "The raw value, in `const` contexts."]
+            pub const fn to_raw(self) -> #repr {
+                self as #repr
+            }
+            #[doc = "The name of the entry."]
+            pub fn as_str(&self) -> ::core::option::Option<&'static ::core::primitive::str> {
+                match self {
+                    #(Self::#variants => ::core::option::Option::Some(#names),)*
+                }
+            }
+        }
+        impl ::core::convert::TryFrom<#repr> for #ident {
+            type Error = super::UnknownEnumValue;
+            fn try_from(value: #repr) -> ::core::result::Result<Self, Self::Error> {
+                Self::try_from_raw(value)
+            }
+        }
+        impl ::core::convert::From<#ident> for #repr {
+            fn from(value: #ident) -> Self {
+                value as #repr
+            }
+        }
+        impl ::core::cmp::PartialEq<#repr> for #ident {
+            fn eq(&self, other: &#repr) -> ::core::primitive::bool {
+                self.to_raw() == *other
+            }
+        }
+        impl ::core::cmp::PartialEq<#ident> for #repr {
+            fn eq(&self, other: &#ident) -> ::core::primitive::bool {
+                *self == other.to_raw()
+            }
+        }
+        impl ::core::fmt::Display for #ident {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                match self.as_str() {
+                    ::core::option::Option::Some(name) => f.write_str(name),
+                    ::core::option::Option::None => ::core::write!(f, "{}", self.to_raw())
+                }
+            }
+        }
+        #from_str
+        #serde
+    }
+}
+
+/// Implement `Serialize` and `Deserialize` if enabled, with `from_raw` converting the backing integer named `value` to
+/// the enum or an error of type `E`.
+fn enum_serde(enumeration: &Enum, ident: &Ident, options: &CodegenOptions, signed: bool, from_raw: TokenStream) -> Option<TokenStream> {
+    let serde: syn::Path = syn::parse_str(options.serde.as_ref()?).ok()?;
+    let (repr, serialize_repr) = if signed {
+        (quote!{::core::primitive::i32}, quote!{serialize_i32})
+    } else {
+        (quote!{::core::primitive::u32}, quote!{serialize_u32})
+    };
+    let expecting = format!("an entry name or integer value of enum {}", enumeration.name);
+    let serialize = if enumeration.bitfield {
+        quote!{
+            if self.as_str().is_some() || (self.is_known() && self.to_raw() != 0) {
+                serializer.collect_str(self)
+            } else {
+                serializer.#serialize_repr(self.to_raw())
+            }
+        }
+    } else {
+        quote!{
+            match self.as_str() {
+                ::core::option::Option::Some(name) => serializer.serialize_str(name),
+                ::core::option::Option::None => serializer.#serialize_repr(self.to_raw())
+            }
+        }
+    };
+    Some(quote!{
+        impl #serde::Serialize for #ident {
+            fn serialize<S: #serde::Serializer>(&self, serializer: S) -> ::core::result::Result<S::Ok, S::Error> {
+                #serialize
+            }
+        }
+        impl<'de> #serde::Deserialize<'de> for #ident {
+            fn deserialize<D: #serde::Deserializer<'de>>(deserializer: D) -> ::core::result::Result<Self, D::Error> {
+                struct Visitor;
+                impl<'de> #serde::de::Visitor<'de> for Visitor {
+                    type Value = #ident;
+                    fn expecting(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                        f.write_str(#expecting)
+                    }
+                    fn visit_str<E: #serde::de::Error>(self, value: &::core::primitive::str) -> ::core::result::Result<#ident, E> {
+                        value.parse().map_err(E::custom)
+                    }
+                    fn visit_u64<E: #serde::de::Error>(self, value: ::core::primitive::u64) -> ::core::result::Result<#ident, E> {
+                        let value = #repr::try_from(value).map_err(E::custom)?;
+                        #from_raw
+                    }
+                    fn visit_i64<E: #serde::de::Error>(self, value: ::core::primitive::i64) -> ::core::result::Result<#ident, E> {
+                        let value = #repr::try_from(value).map_err(E::custom)?;
+                        #from_raw
+                    }
+                }
+                deserializer.deserialize_any(Visitor)
+            }
+        }
+    })
+}
+
+/// Implement `FromStr`, accepting either the protocol name or the constant name of an entry in any case, with
+/// `entry` producing the path to an entry. Bitfields additionally accept flags separated by `|`.
+fn enum_from_str(enumeration: &Enum, ident: &Ident, entry: impl Fn(&Entry) -> TokenStream) -> TokenStream {
+    let enum_name = &enumeration.name;
+    let entries = enumeration.entries.iter().map(|e| {
+        let name = &e.name;
+        let constant = entry_name(enumeration, e);
+        let entry = entry(e);
+        quote!{(#name, #constant, #entry)}
+    });
+    let parse = if enumeration.bitfield {
+        quote!{
+            let mut value = 0;
+            for flag in s.split('|') {
+                value |= parse(flag).ok_or_else(error)?.0;
+            }
+            ::core::result::Result::Ok(Self(value))
+        }
+    } else {
+        quote!{parse(s).ok_or_else(error)}
+    };
+    quote!{
+        impl ::core::str::FromStr for #ident {
+            type Err = super::ParseEnumError;
+            fn from_str(s: &::core::primitive::str) -> ::core::result::Result<Self, Self::Err> {
+                let entries: &[(&::core::primitive::str, &::core::primitive::str, Self)] = &[#(#entries),*];
+                let parse = |name: &::core::primitive::str| {
+                    let name = name.trim();
+                    entries.iter()
+                        .find(|(entry, constant, _)| entry.eq_ignore_ascii_case(name) || constant.eq_ignore_ascii_case(name))
+                        .map(|(_, _, value)| *value)
+                };
+                let error = || super::ParseEnumError { value: s.into(), enumeration: #enum_name };
+                #parse
+            }
+        }
+    }
+}
+
+/// Implement `Default` if the enum has a default entry, with `entry` producing the path to an entry.
+fn enum_default(enumeration: &Enum, ident: &Ident, entry: impl Fn(&Entry) -> TokenStream) -> Option<TokenStream> {
+    let default = entry(enumeration.canonical(enumeration.default_entry()?));
+    Some(quote!{
+        impl ::core::default::Default for #ident {
+            fn default() -> Self {
+                #default
+            }
+        }
+    })
+}
+
+fn entry_doc(enumeration: &Enum, entry: &Entry) -> TokenStream {
+    let alias_of = entry.alias_of.as_ref().map(|_| format!("Alias of `{}`", entry_name(enumeration, enumeration.canonical(entry))));
+    let expression = match &entry.literal {
+        Some(EntryValue::Expression(expression)) => Some(format!("Defined as `{}`", expression.trim())),
+        _ => None
+    };
+    let summary = fallback_summary(&entry.summary, &entry.description, || format!("Entry `{}` of enum `{}`", entry.name, enumeration.name));
+    docs([summary, since_doc(entry.since), alias_of, expression, entry.description.clone()])
+}
+
+/// The lines of a doc comment made up of sections, such as a summary and a description, separated by a blank line.
+/// 
+/// Absent and empty sections are skipped, and runs of blank lines are collapsed so that the rendered docs have no stray
+/// empty paragraphs.
+fn doc_lines<const N: usize>(sections: [Option<String>; N]) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for section in sections.into_iter().flatten() {
+        if !lines.is_empty() {
+            lines.push(String::new());
+        }
+        for line in section.lines() {
+            if !line.trim().is_empty() {
+                lines.push(line.to_owned());
+            } else if lines.last().is_some_and(|last| !last.is_empty()) {
+                lines.push(String::new());
+            }
+        }
+        while lines.last().is_some_and(String::is_empty) {
+            lines.pop();
+        }
+    }
+    lines
+}
+/// Outer doc attributes for the sections of a doc comment, see [`doc_lines`].
+fn docs<const N: usize>(sections: [Option<String>; N]) -> TokenStream {
+    let lines = doc_lines(sections);
+    quote!{#(#[doc = #lines])*}
+}
+/// The opcode and [signature] of a message, to compare with the messages on the wire.
+fn wire_doc(opcode: u16, since: Option<u32>, args: &[Arg]) -> String {
+    format!("Opcode {} — signature `\"{}\"`", opcode, signature(since, args))
+}
+
+/// The summary of an item, or a summary made from its name when the specification gives neither a summary nor a
+/// description, so that every generated item is documented.
+fn fallback_summary(summary: &Option<String>, description: &Option<String>, fallback: impl FnOnce() -> String) -> Option<String> {
+    match (summary, description) {
+        (None, None) => Some(fallback()),
+        _ => summary.clone()
+    }
+}
+
+fn since_doc(since: Option<u32>) -> Option<String> {
+    since.map(|since| format!("`Since version {}`", since))
+}
+
+/// A `#[doc(alias)]` so that rustdoc search finds an item by its protocol name, unless it is already named the same.
+fn doc_alias(name: &str, ident: &Ident) -> Option<TokenStream> {
+    let ident = ident.to_string();
+    (ident.strip_prefix("r#").unwrap_or(&ident) != name).then(|| quote!{#[doc(alias = #name)]})
+}
+
+/// Aliases for a message by its qualified protocol name, such as `wl_surface.attach`, and its unqualified name.
+fn message_aliases(interface: &Interface, name: &str, ident: &Ident) -> TokenStream {
+    let qualified = format!("{}.{}", interface.name, name);
+    let alias = doc_alias(name, ident);
+    quote!{
+        #[doc(alias = #qualified)]
+        #alias
+    }
+}
+
+/// The name of the inert attribute carrying a [source marker](CodegenOptions::source_markers).
+const SOURCE_MARKER: &str = "yutani_codegen_source";
+
+/// A [source marker](CodegenOptions::source_markers) for an item of the specification, such as `request` and
+/// `wl_surface.attach`, as an attribute behind a `cfg` that never holds, which [`render`] turns into a comment.
+fn source_marker(protocol: &Protocol, kind: &str, path: &str, line: Option<usize>, options: &CodegenOptions) -> Option<TokenStream> {
+    if !options.source_markers {
+        return None
+    }
+    let file = protocol.source.as_ref().map(|source| source.display().to_string());
+    let location = match (file, line) {
+        (Some(file), Some(line)) => format!("{}:{}", file, line),
+        (Some(file), None) => file,
+        (None, Some(line)) => format!("{} line {}", protocol.name, line),
+        (None, None) => protocol.name.clone()
+    };
+    let marker = format!("{}: {} `{}`", location, kind, path);
+    let name = Ident::new(SOURCE_MARKER, Span::call_site());
+    Some(quote!{#[cfg_attr(any(), #name = #marker)]})
+}
+
+/// Replace the [source markers](CodegenOptions::source_markers) of rendered code with comments.
+///
+/// Markers are written by [`source_marker`] as `#[cfg_attr(any(), yutani_codegen_source = "...")]`, with any spacing
+/// between tokens, and anything not matching that exactly is left as is.
+fn source_marker_comments(code: &str) -> String {
+    let mut rendered = String::with_capacity(code.len());
+    let mut rest = code;
+    while let Some(start) = rest.find('#') {
+        rendered.push_str(&rest[..start]);
+        match parse_source_marker(&rest[start..]) {
+            Some((text, len)) => {
+                rendered.push_str(&format!("\n// {}\n", text));
+                rest = &rest[start + len..];
+            },
+            None => {
+                rendered.push('#');
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+/// The text of a source marker at the start of `code` and the length of the marker.
+fn parse_source_marker(code: &str) -> Option<(String, usize)> {
+    let mut rest = code;
+    for token in ["#", "[", "cfg_attr", "(", "any", "(", ")", ",", SOURCE_MARKER, "=", "\""] {
+        rest = rest.trim_start().strip_prefix(token)?;
+    }
+    let mut text = String::new();
+    let mut chars = rest.char_indices();
+    let end = loop {
+        match chars.next()? {
+            (i, '"') => break i + 1,
+            (_, '\\') => match chars.next()?.1 {
+                // A comment ends at a line break
+                'n' | 't' => text.push(' '),
+                c @ ('\\' | '"' | '\'') => text.push(c),
+                _ => return None
+            },
+            (_, c) => text.push(c)
+        }
+    };
+    rest = &rest[end..];
+    for token in [")", "]"] {
+        rest = rest.trim_start().strip_prefix(token)?;
+    }
+    Some((text, code.len() - rest.len()))
+}
+
+/// An entry value as a literal of the backing type of its enum.
+fn entry_value(value: i64, signed: bool) -> Literal {
+    if signed {
+        Literal::i32_suffixed(value as i32)
+    } else {
+        Literal::u32_suffixed(value as u32)
+    }
+}
+
+/// The name of the constant generated for an enum entry, prefixed by the enum name when it would otherwise not be a
+/// valid identifier.
+fn entry_name(enumeration: &Enum, entry: &Entry) -> String {
+    let name = ident::shouty_snake_case(&entry.name);
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        ident::shouty_snake_case(&format!("{}_{}", enumeration.name, entry.name))
+    } else {
+        name
+    }
+}
+
+/// The wire type, nullability, interface and enum of an argument, with links relative to the protocol root when
+/// `root` is `""` or relative to an interface module when it is `"super::"`.
+fn arg_type_doc(protocol: &Protocol, interface: &Interface, arg: &Arg, options: &CodegenOptions, root: &str) -> String {
+    let mut doc = format!("`{}`", arg.ty);
+    if let Some(array_type) = arg.array_type {
+        doc += &format!(" of `{}`", array_type.name());
+    }
+    if arg.nullable {
+        doc += " (nullable)";
+    }
+    if let Some(target) = &arg.interface {
+        match (protocol.interface(target), options.external_modules.get(target).filter(|_| protocol.is_external(target))) {
+            (Some(target), _) => doc += &format!(" → [`{}`]({}{})", target.name, root, interface_link(protocol, target, options)),
+            (None, Some(module)) => doc += &format!(" → [`{}`]({}::{})", target, module, ident::name(&ident::snake_case(target))),
+            (None, None) => doc += &format!(" → `{}`", target)
+        }
+    }
+    if let Some((owner, enumeration)) = arg.enumeration.as_ref().and_then(|name| protocol.find_enum(interface, name)) {
+        doc += &format!(
+            ", enum [`{}.{}`]({}{}::{})",
+            owner.name,
+            enumeration.name,
+            root,
+            ident::name(&ident::snake_case(interface_name(protocol, owner, options))),
+            ident::name(&ident::pascal_case(&enumeration.name, options))
+        );
+    } else if let (Some(name), Some(_)) = (&arg.enumeration, external_enum_path(protocol, arg, options)) {
+        let (owner, enumeration) = name.split_once('.').unwrap_or_default();
+        doc += &format!(
+            ", enum [`{}`]({}::{}::{})",
+            name,
+            options.external_modules[owner],
+            ident::name(&ident::snake_case(owner)),
+            ident::name(&ident::pascal_case(enumeration, options))
+        );
+    }
+    doc
+}
+
+/// An `Arguments` section documenting the wire type, nullability, interface and enum of each argument along with its
+/// summary.
+fn arg_docs(protocol: &Protocol, interface: &Interface, args: &[Arg], options: &CodegenOptions) -> Option<String> {
+    if args.is_empty() {
+        return None
+    }
+    let lines = args.iter().map(|arg| {
+        let mut line = format!("`{}`: {}", arg.name, arg_type_doc(protocol, interface, arg, options, ""));
+        if let Some(summary) = &arg.summary {
+            line += &format!(": {}", summary);
+        }
+        line
+    });
+    Some(format!("## Arguments\n\n{}", lines.collect::<Vec<_>>().join("\n\n")))
+}
+
+/// The name of the struct generated for an event, which is suffixed with `Event` as events often share a name with an
+/// enum, such as `wl_output.mode`.
+fn event_struct_name(event: &Event, options: &CodegenOptions) -> String {
+    format!("{}Event", ident::pascal_case(&event.name, options))
+}
+
+/// A struct with a field for each argument of an event that sends the event, generated in the interface module.
+pub fn event_struct(protocol: &Protocol, interface: &Interface, event: &Event, opcode: u16, options: &CodegenOptions) -> TokenStream {
+    let t = state_param(options);
+    let ident = ident::ident(&event_struct_name(event, options));
+    let event_name = &ident::snake_case(&event.name);
+    let lifetime = quote!{'a};
+    let mut borrows = false;
+    let fields: Vec<_> = event.args.iter().map(|a| {
+        let ident = ident::snake(&a.name);
+        let (ty, borrowed) = name_lifetimes(a.send_ty(module_enum_path(protocol, interface, a, options).as_ref(), options), &lifetime);
+        borrows |= borrowed;
+        let docs = docs([a.summary.clone(), Some(arg_type_doc(protocol, interface, a, options, "super::"))]);
+        quote!{
+            #docs
+            pub #ident: #ty
+        }
+    }).collect();
+    let generics = borrows.then(|| quote!{<#lifetime>});
+    let doc = format!(
+        "The arguments of the [`{}`](super::{}::{}) event, which can be sent without an implementation of the interface trait.",
+        event.name,
+        ident::name(&events_trait_name(protocol, interface, options)),
+        ident::name(event_name)
+    );
+    let field_idents = event.args.iter().map(|a| ident::snake(&a.name));
+    let body = event_body(protocol, interface, event, opcode, options, true);
+    let send_docs = docs([Some("Send the event from an object to a client.".into()), destructor_doc(event)]);
+
+    quote!{
+        #[doc = #doc]
+        #[derive(Debug)]
+        pub struct #ident #generics {
+            #(#fields),*
+        }
+        impl #generics #ident #generics {
+            #send_docs
+            pub fn send<O: ?::core::marker::Sized, #t>(self, _this: &::yutani::lease::Lease<O>, _client: &mut ::yutani::server::Client<#t>) -> ::core::result::Result<(), ::yutani::wire::WlError<'static>> {
+                let Self { #(#field_idents),* } = self;
+                #body
+            }
+        }
+    }
+}
+
+/// Replace the anonymous lifetimes in `tokens` with `lifetime`, returning whether there were any.
+fn name_lifetimes(tokens: TokenStream, lifetime: &TokenStream) -> (TokenStream, bool) {
+    let mut named = false;
+    let mut output = TokenStream::new();
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        match token {
+            TokenTree::Punct(punct) if punct.as_char() == '\'' && matches!(tokens.peek(), Some(TokenTree::Ident(ident)) if ident == "_") => {
+                tokens.next();
+                output.extend(lifetime.clone());
+                named = true;
+            },
+            TokenTree::Group(group) => {
+                let (stream, group_named) = name_lifetimes(group.stream(), lifetime);
+                named |= group_named;
+                let mut renamed = Group::new(group.delimiter(), stream);
+                renamed.set_span(group.span());
+                output.extend([TokenTree::Group(renamed)]);
+            },
+            token => output.extend([token])
+        }
+    }
+    (output, named)
+}
+
+/// The generic parameter for the state of the event loop, see [`CodegenOptions::generic_param`].
+fn state_param(options: &CodegenOptions) -> Ident {
+    Ident::new(options.generic_param.as_deref().unwrap_or("T"), Span::call_site())
+}
+/// The declaration of the [state parameter](state_param) of a trait or function implementing one, with its bounds.
+fn state_declaration(options: &CodegenOptions) -> TokenStream {
+    let t = state_param(options);
+    match &options.generic_bounds {
+        Some(bounds) => {
+            let bounds: TokenStream = bounds.parse().expect("validated generic bounds");
+            quote!{#t: #bounds}
+        },
+        None => quote!{#t}
+    }
+}
+
+/// The name of the struct that the arguments of a request are passed in, if any.
+fn args_struct_name(request: &Request, options: &CodegenOptions) -> Option<String> {
+    let threshold = options.args_struct_threshold?;
+    (request.args.len() >= threshold).then(|| format!("{}Args", ident::pascal_case(&request.name, options)))
+}
+
+/// The struct that the arguments of a request are passed in, generated in the interface module, if any.
+pub fn args_struct(protocol: &Protocol, interface: &Interface, request: &Request, options: &CodegenOptions) -> Option<TokenStream> {
+    let ident = ident::ident(&args_struct_name(request, options)?);
+    let doc = format!(
+        "The arguments of [`{}`](super::{}::{}).",
+        request.name,
+        ident::name(&trait_name(protocol, interface, options)),
+        ident::name(&ident::snake_case(&request.name))
+    );
+    let fields = request.args.iter().map(|a| {
+        let ident = ident::snake(&a.name);
+        let ty = a.ty(module_enum_path(protocol, interface, a, options).as_ref(), options);
+        let docs = docs([a.summary.clone(), Some(arg_type_doc(protocol, interface, a, options, "super::"))]);
+        quote!{
+            #docs
+            pub #ident: #ty
+        }
+    });
+    Some(quote!{
+        #[doc = #doc]
+        #[derive(Debug)]
+        pub struct #ident {
+            #(#fields),*
+        }
+    })
+}
+
+/// The parameters of the trait method of a request after the object, event loop and client, with types relative to
+/// the protocol root.
+fn request_params(protocol: &Protocol, interface: &Interface, request: &Request, options: &CodegenOptions) -> Vec<TokenStream> {
+    match args_struct_name(request, options) {
+        Some(args_struct) => {
+            let module = ident::snake(interface_name(protocol, interface, options));
+            let args_struct = ident::ident(&args_struct);
+            vec![quote!{args: #module::#args_struct}]
+        },
+        None => request.args.iter().map(|a| {
+            let ident = ident::snake(&a.name);
+            let ty = a.ty(enum_path(protocol, interface, a, options).as_ref(), options);
+            quote!{
+                #ident: #ty
+            }
+        }).collect()
+    }
+}
+
+pub fn request(protocol: &Protocol, interface: &Interface, request: &Request, opcode: u16, options: &CodegenOptions) -> TokenStream {
+    let t = state_param(options);
+    let ident = ident::snake(&request.name);
+    let aliases = message_aliases(interface, &request.name, &ident);
+    let marker = source_marker(protocol, "request", &format!("{}.{}", interface.name, request.name), request.line, options);
+
+    let args = request_params(protocol, interface, request, options);
+    let docs = docs([
+        fallback_summary(&request.summary, &request.description, || format!("Request `{}` of `{}`", request.name, interface.name)),
+        since_doc(request.since),
+        request.unimplemented.then(|| "<div class=\"warning\">This request is not supported, and fails with a protocol error unless implemented.</div>".into()),
+        request.capability.as_ref().map(|capability| format!(
+            "Only dispatched to clients with the `{}` capability, see [`check_capability`](Self::check_capability).",
+            capability
+        )),
+        request.description.clone(),
+        Some(wire_doc(opcode, request.since, &request.args)),
+        arg_docs(protocol, interface, &request.args, options)
+    ]);
+    let many_params = allow_many_params(3 + args.len());
+    let signature = quote!{
+        #many_params
+        fn #ident(this: ::yutani::lease::Lease<Self>, event_loop: &mut ::yutani::wire::EventLoop<#t>, client: &mut ::yutani::server::Client<#t> #(, #args)*) -> ::core::result::Result<(), ::yutani::wire::WlError<'static>>
+    };
+    if !request.unimplemented {
+        return quote!{
+            #marker
+            #docs
+            #aliases
+            #signature;
+        }
+    }
+
+    let description = format!("{}.{}: request is not supported", interface.name, request.name);
+    let error_entry = request.unimplemented_error.as_ref().and_then(|name| {
+        interface.enums.iter().find(|e| e.name == "error")?.entries.iter().find(|e| &e.name == name)
+    });
+    let (object, error) = match error_entry {
+        Some(entry) => {
+            let value = Literal::u32_suffixed(entry.value as u32);
+            (quote!{this.id()}, quote!{#value})
+        },
+        None => (quote!{::yutani::Id::DISPLAY}, quote!{::yutani::wire::WlError::INVALID_OPCODE.error})
+    };
+    quote!{
+        #marker
+        #docs
+        #aliases
+        #[allow(unused_variables)]
+        #signature {
+            ::core::result::Result::Err(::yutani::wire::WlError {
+                object: #object,
+                error: #error,
+                description: ::std::borrow::Cow::Borrowed(#description)
+            })
+        }
+    }
+}
+
+pub fn event(protocol: &Protocol, interface: &Interface, event: &Event, opcode: u16, options: &CodegenOptions) -> TokenStream {
+    let t = state_param(options);
+    let event_name = &ident::snake_case(&event.name);
+    let ident = ident::ident(event_name);
+    let aliases = message_aliases(interface, &event.name, &ident);
+    let marker = source_marker(protocol, "event", &format!("{}.{}", interface.name, event.name), event.line, options);
+
+    let args = event.args.iter().map(|a| {
+        let ident = ident::snake(&a.name);
+        let ty = a.send_ty(enum_path(protocol, interface, a, options).as_ref(), options);
+        quote!{
+            #ident: #ty
+        }
+    });
+    let docs = docs([
+        fallback_summary(&event.summary, &event.description, || format!("Event `{}` of `{}`", event.name, interface.name)),
+        since_doc(event.since),
+        destructor_doc(event),
+        event.description.clone(),
+        Some(wire_doc(opcode, event.since, &event.args)),
+        arg_docs(protocol, interface, &event.args, options)
+    ]);
+
+    let body = if options.event_structs {
+        let module = ident::snake(interface_name(protocol, interface, options));
+        let event_struct = ident::ident(&event_struct_name(event, options));
+        let fields = event.args.iter().map(|a| ident::snake(&a.name));
+        quote!{#module::#event_struct { #(#fields),* }.send(_this, _client)}
+    } else if event.destructor {
+        event_body(protocol, interface, event, opcode, options, false)
+    } else {
+        let to_stream = ident::ident(&format!("{}_to_stream", event_name));
+        let args = event.args.iter().map(|a| ident::snake(&a.name));
+        quote!{Self::#to_stream(_this, _client.stream() #(, #args)*)}
+    };
+    let broadcast = event_broadcast(protocol, interface, event, opcode, options);
+    let create = event_create(protocol, interface, event, options);
+    let to_stream = event_to_stream(protocol, interface, event, opcode, options);
+    let many_params = allow_many_params(2 + event.args.len());
+
+    quote!{
+        #marker
+        #docs
+        #aliases
+        #many_params
+        fn #ident(_this: &mut ::yutani::lease::Lease<Self>, _client: &mut ::yutani::server::Client<#t> #(, #args)*) -> ::core::result::Result<(), ::yutani::wire::WlError<'static>> {
+            #body
+        }
+        #to_stream
+        #broadcast
+        #create
+    }
+}
+
+/// An extension trait of `Lease` forwarding to the event senders of an interface, so that events are sent with method
+/// call syntax, such as `surface.enter(client, output)`.
+/// 
+/// Interfaces without events have none, as there is nothing to forward.
+fn ext_trait(protocol: &Protocol, interface: &Interface, options: &CodegenOptions) -> Option<TokenStream> {
+    if !options.ext_traits || interface.events.is_empty() {
+        return None
+    }
+    let t = state_param(options);
+    let state = state_declaration(options);
+    let trait_ident = ident::ident(&trait_name(protocol, interface, options));
+    let events_name = events_trait_name(protocol, interface, options);
+    let events_ident = ident::ident(&events_name);
+    let ext_ident = ident::ident(&ext_trait_name(protocol, interface, options));
+    let doc = format!(
+        "The event senders of [`{}`] as methods of a lease of an implementation, such as `lease.{}(client, ..)`.",
+        ident::name(&events_name), ident::name(&ident::snake_case(&interface.events[0].name))
+    );
+    let (declarations, definitions): (Vec<_>, Vec<_>) = interface.events.iter().map(|e| {
+        let event_name = ident::snake_case(&e.name);
+        let ident = ident::ident(&event_name);
+        let doc = format!("Send the [`{}`]({}::{}) event.", event_name, ident::name(&events_name), ident::name(&event_name));
+        let args: Vec<_> = e.args.iter().map(|a| {
+            let ident = ident::snake(&a.name);
+            let ty = a.send_ty(enum_path(protocol, interface, a, options).as_ref(), options);
+            quote!{#ident: #ty}
+        }).collect();
+        let arg_idents = e.args.iter().map(|a| ident::snake(&a.name));
+        let many_params = allow_many_params(2 + e.args.len());
+        let signature = quote!{
+            fn #ident(&mut self, _client: &mut ::yutani::server::Client<#t> #(, #args)*) -> ::core::result::Result<(), ::yutani::wire::WlError<'static>>
+        };
+        let declaration = quote!{
+            #[doc = #doc]
+            #many_params
+            #signature;
+        };
+        let definition = quote!{
+            #many_params
+            #signature {
+                <I as #events_ident<#t>>::#ident(self, _client #(, #arg_idents)*)
+            }
+        };
+        (declaration, definition)
+    }).unzip();
+    Some(quote!{
+        #[doc = #doc]
+        pub trait #ext_ident<#state> {
+            #(#declarations)*
+        }
+        impl<#state, I: #trait_ident<#t>> #ext_ident<#t> for ::yutani::lease::Lease<I> {
+            #(#definitions)*
+        }
+    })
+}
+
+/// A provided method writing an event to the stream of a client rather than taking the client, which the sender of
+/// the event calls with the stream of its client.
+/// 
+/// Destructor events have none, as they also remove the object from the client.
+fn event_to_stream(protocol: &Protocol, interface: &Interface, event: &Event, opcode: u16, options: &CodegenOptions) -> Option<TokenStream> {
+    if event.destructor {
+        return None
+    }
+    let event_name = ident::snake_case(&event.name);
+    let ident = ident::ident(&format!("{}_to_stream", event_name));
+    let doc = format!("Send the [`{}`](Self::{}) event by writing it to the stream of a client.", event_name, ident::name(&event_name));
+    let args = event.args.iter().map(|a| {
+        let ident = ident::snake(&a.name);
+        let ty = a.send_ty(enum_path(protocol, interface, a, options).as_ref(), options);
+        quote!{#ident: #ty}
+    });
+    let size_check = event_size_check(interface, event);
+    let message = event_message(protocol, interface, event, opcode, options, false);
+    let many_params = allow_many_params(2 + event.args.len());
+    Some(quote!{
+        #[doc = #doc]
+        #[doc = ""]
+        #[doc = "The sender taking the client calls this with `Client::stream`, and is simpler to use. This suits callers that already hold the stream, such as to write several events in a row, as it does not need the whole client."]
+        #many_params
+        fn #ident(_this: &::yutani::lease::Lease<Self>, _stream: &mut ::yutani::wire::Stream #(, #args)*) -> ::core::result::Result<(), ::yutani::wire::WlError<'static>> {
+            #size_check
+            #message
+        }
+    })
+}
+
+/// A provided method for an event with a typed `new_id` argument, such as `wl_data_device.data_offer`, that creates
+/// the new object and sends the event with its id.
+/// 
+/// The object is given an id from the server range and added to the client once the event is sent, which is before
+/// the event is flushed, so that the client never sees an id the server does not know, nor a `delete_id` for one it
+/// never saw if the event cannot be sent. Only events with a single `new_id` of an interface in the same
+/// protocol have one, as the object must implement the trait of its interface.
+fn event_create(protocol: &Protocol, interface: &Interface, event: &Event, options: &CodegenOptions) -> Option<TokenStream> {
+    let t = state_param(options);
+    let mut new_ids = event.args.iter().filter(|a| a.ty == DataType::NewId);
+    let (Some(new_id), None) = (new_ids.next(), new_ids.next()) else {
+        return None
+    };
+    let target = protocol.interfaces.iter()
+        .find(|i| Some(&i.name) == new_id.interface.as_ref())
+        .filter(|i| !options.core_interfaces.contains_key(&i.name))?;
+    let target_trait = ident::ident(&trait_name(protocol, target, options));
+    let target_dispatch = ident::ident(&dispatch_trait_name(protocol, target, options));
+    let event_name = ident::snake_case(&event.name);
+    let event_ident = ident::ident(&event_name);
+    let ident = ident::ident(&format!("{}_create", event_name));
+    let new_id_ident = ident::snake(&new_id.name);
+    let doc = format!(
+        "Create an object for the `{}` argument from `{}` and send the [`{}`](Self::{}) event with its id, returning a lease of the object.",
+        new_id.name, ident::name(&ident::snake_case(&new_id.name)), event_name, ident::name(&event_name)
+    );
+    let args = event.args.iter().map(|a| {
+        let ident = ident::snake(&a.name);
+        if a.ty == DataType::NewId {
+            quote!{#ident: O}
+        } else {
+            let ty = a.send_ty(enum_path(protocol, interface, a, options).as_ref(), options);
+            quote!{#ident: #ty}
+        }
+    });
+    let arg_idents = event.args.iter().map(|a| ident::snake(&a.name));
+    let size_check = event_size_check(interface, event);
+    let lease_error = wl_error("INTERNAL", &format!("{}.{}", interface.name, event.name), "created object could not be leased");
+    let version_error = wl_error("UNSUPPORTED_VERSION", &target.name, "version is higher than the highest version of the interface");
+    let many_params = allow_many_params(2 + event.args.len());
+    Some(quote!{
+        #[doc = #doc]
+        #[doc = ""]
+        #[doc = "The object has the version of this object, and is added to the client once the event is sent. If the event cannot be sent, the object is dropped without the client being told of its id. If this object has a version the object does not support, nothing is created and no id is taken."]
+        #[doc = "The lease must be dropped before the client sends a request to the object, which cannot be dispatched while it is leased."]
+        #many_params
+        fn #ident<O>(_this: &mut ::yutani::lease::Lease<Self>, _client: &mut ::yutani::server::Client<#t> #(, #args)*) -> ::core::result::Result<::yutani::lease::Lease<O>, ::yutani::wire::WlError<'static>>
+        where
+            O: #target_trait<#t>
+        {
+            #size_check
+            let _object = #new_id_ident;
+            // An id cannot be handed back once it is taken, so the version is checked before taking one and
+            // converting the object cannot fail
+            if _this.version() > <O as #target_trait<#t>>::VERSION {
+                return ::core::result::Result::Err(#version_error)
+            }
+            let #new_id_ident = ::yutani::Id::new(_client.new_id());
+            let mut _resident = <O as #target_dispatch<#t>>::into_versioned_object(_object, #new_id_ident, _this.version())?;
+            let _lease = _resident.lease().ok_or(#lease_error)?;
+            // The object is only added once the client is told of it, as removing it would send `delete_id` for an
+            // id the client never saw. Dropping the resident unsent leaves the object to be freed with the lease
+            Self::#event_ident(_this, _client #(, #arg_idents)*)?;
+            _client.insert(_resident.into_any())?;
+            ::core::result::Result::Ok(_lease)
+        }
+    })
+}
+
+/// A provided method sending an event from each of many objects to a client, such as to every bound instance of an
+/// interface.
+/// 
+/// The size of the message is checked and the arguments are written to a payload of words once, so only the header and
+/// the payload are written for each object. Arguments without a payload, file descriptors and fixed point numbers
+/// that are not exposed as `f64`, are written for each object between the words of the others.
+fn event_broadcast(protocol: &Protocol, interface: &Interface, event: &Event, opcode: u16, options: &CodegenOptions) -> TokenStream {
+    let t = state_param(options);
+    let name = &interface.name;
+    let event_name = ident::snake_case(&event.name);
+    let ident = ident::ident(&format!("{}_broadcast", event_name));
+    let doc = format!("Send the [`{}`](Self::{}) event from each of `_objects` to a client.", event_name, ident::name(&event_name));
+    let context = format!("{}.{}", name, event.name);
+    let args = event.args.iter().map(|a| {
+        let ident = ident::snake(&a.name);
+        let ty = a.send_ty(enum_path(protocol, interface, a, options).as_ref(), options);
+        quote!{#ident: #ty}
+    });
+    let payload = Ident::new("_payload", Span::call_site());
+    let stream = Ident::new("_stream", Span::call_site());
+    let payloads: Vec<_> = event.args.iter()
+        .map(|a| a.payload(&payload, enum_path(protocol, interface, a, options).as_ref(), &context, options))
+        .collect();
+    let words = payloads.iter().any(Option::is_some);
+    // The arguments are written to the payload once, with the length of the payload recorded before each argument that
+    // has to be written for each object
+    let mut encode = Vec::new();
+    let mut write = Vec::new();
+    let mut from = None;
+    for (i, (arg, arg_words)) in event.args.iter().zip(payloads).enumerate() {
+        if let Some(arg_words) = arg_words {
+            encode.push(arg_words);
+            continue
+        }
+        if words {
+            let split = Ident::new(&format!("_split_{}", i), Span::call_site());
+            encode.push(quote!{let #split = #payload.len();});
+            write.push(quote!{
+                for &word in &#payload[#from..#split] {
+                    #stream.send_u32(word)?;
+                }
+            });
+            from = Some(split);
+        }
+        // Fixed point arrays are sent as an owned `Vec`, which is cloned for each object
+        let owned = (arg.ty == DataType::Array).then(|| {
+            let ident = ident::snake(&arg.name);
+            quote!{let #ident = ::core::clone::Clone::clone(&#ident);}
+        });
+        let sender = arg.sender(&stream, enum_path(protocol, interface, arg, options).as_ref(), &context, options);
+        write.push(quote!{
+            #owned
+            #sender;
+        });
+    }
+    let (payload, rest, allow) = if words {
+        let rest = match from {
+            Some(from) => quote!{&#payload[#from..]},
+            None => quote!{&#payload}
+        };
+        (
+            Some(quote!{let mut #payload: ::std::vec::Vec<::core::primitive::u32> = ::std::vec::Vec::new();}),
+            Some(quote!{
+                for &word in #rest {
+                    #stream.send_u32(word)?;
+                }
+            }),
+            // Arguments are pushed to the payload one statement each, rather than as the `vec![]` Clippy suggests
+            Some(quote!{#[allow(clippy::vec_init_then_push)]})
+        )
+    } else {
+        (None, None, None)
+    };
+    let args_debug_idents = event.args.iter().map(|a| ident::snake(&a.name));
+    let debug_template = debug_template(" -> ", name, &event_name, event.args.len());
+    let module = ident::snake(interface_name(protocol, interface, options));
+    let assertion = opcode_assertion(quote!{#module::EVENT_NAMES}, quote!{names_match}, opcode, &event.name);
+    let remove = event.destructor.then(|| quote!{
+        _client.remove(_this.id())?;
+    });
+    let size_check = event_size_check(interface, event);
+    let many_params = allow_many_params(2 + event.args.len());
+    quote!{
+        #[doc = #doc]
+        #many_params
+        #allow
+        fn #ident<'a, I>(_objects: I, _client: &mut ::yutani::server::Client<#t> #(, #args)*) -> ::core::result::Result<(), ::yutani::wire::WlError<'static>>
+        where
+            I: ::core::iter::IntoIterator<Item = &'a ::yutani::lease::Lease<Self>>,
+            Self: 'a
+        {
+            #assertion
+            #size_check
+            #payload
+            #(#encode)*
+            for _this in _objects {
+                #[cfg(debug_assertions)]
+                {
+                    ::std::println!(#debug_template, _this.id(), #(#args_debug_idents,)*);
+                }
+                let #stream = _client.stream();
+                let _key = #stream.start_message(_this.id(), #opcode);
+                #(#write)*
+                #rest
+                #stream.commit(_key)?;
+                #remove
+            }
+            ::core::result::Result::Ok(())
+        }
+    }
+}
+
+/// Check the size of, log and send an event from the object `_this` to `_client`, with the arguments bound to their
+/// names.
+/// 
+/// `module` is whether the code is in the interface module rather than the protocol root.
+fn event_body(protocol: &Protocol, interface: &Interface, event: &Event, opcode: u16, options: &CodegenOptions, module: bool) -> TokenStream {
+    let size_check = event_size_check(interface, event);
+    let message = event_message(protocol, interface, event, opcode, options, module);
+    quote!{
+        #size_check
+        let _stream = _client.stream();
+        #message
+    }
+}
+
+/// The template logging a message in debug builds, such as `wl_surface@{}.attach({:?}, {:?}, {:?})`, formatting the
+/// id of the object and each argument.
+fn debug_template(prefix: &str, interface: &str, message: &str, args: usize) -> String {
+    format!("{}{}@{{}}.{}({})", prefix, interface, message, vec!["{:?}"; args].join(", "))
+}
+
+/// Log and write an event to `_stream` as in [`event_body`], without checking its size. Destructor events also remove
+/// the object from `_client`, so only events that are not destructors can be written without a client.
+fn event_message(protocol: &Protocol, interface: &Interface, event: &Event, opcode: u16, options: &CodegenOptions, module: bool) -> TokenStream {
+    let name = &interface.name;
+    let event_name = &ident::snake_case(&event.name);
+    let stream = Ident::new("_stream", Span::call_site());
+    let context = format!("{}.{}", name, event.name);
+    let args_senders = event.args.iter().map(|a| {
+        let enumeration = if module {
+            module_enum_path(protocol, interface, a, options)
+        } else {
+            enum_path(protocol, interface, a, options)
+        };
+        a.sender(&stream, enumeration.as_ref(), &context, options)
+    });
+    let args_debug_idents = event.args.iter().map(|a| ident::snake(&a.name));
+    let debug_template = debug_template(" -> ", name, event_name, event.args.len());
+    let assertion = if module {
+        opcode_assertion(quote!{EVENT_NAMES}, quote!{super::names_match}, opcode, &event.name)
+    } else {
+        let module = ident::snake(interface_name(protocol, interface, options));
+        opcode_assertion(quote!{#module::EVENT_NAMES}, quote!{names_match}, opcode, &event.name)
+    };
+    // The object of a destructor event is removed once the event is sent, which also sends `wl_display.delete_id`
+    let commit = if event.destructor {
+        quote!{
+            #stream.commit(_key)?;
+            _client.remove(_this.id()).map(|_| ())
+        }
+    } else {
+        quote!{#stream.commit(_key)}
+    };
+    quote!{
+        #[cfg(debug_assertions)]
+        {
+            ::std::println!(#debug_template, _this.id(), #(#args_debug_idents,)*);
+        }
+        #assertion
+        let _key = #stream.start_message(_this.id(), #opcode);
+        #(#args_senders;)*
+        #commit
+    }
+}
+
+/// Documentation of the teardown of the object a destructor event is sent from.
+fn destructor_doc(event: &Event) -> Option<String> {
+    event.destructor.then(|| "This event is a destructor. Once it is sent, the object is removed from the client and its id is released with `wl_display.delete_id`.".into())
+}
+
+/// The smallest size of a message with these arguments, saturating at the largest size the header can hold.
+fn message_size(args: &[Arg]) -> u16 {
+    min_message_size(args).try_into().unwrap_or(u16::MAX)
+}
+
+/// A check that an event with strings or arrays fits in a message, returning an error before anything is sent rather
+/// than sending a message with a corrupt size.
+fn event_size_check(interface: &Interface, event: &Event) -> Option<TokenStream> {
+    let extra: Vec<_> = event.args.iter().filter_map(Arg::extra_size).collect();
+    if extra.is_empty() {
+        return None
+    }
+    let min_size = min_message_size(&event.args);
+    let template = format!(
+        "{}.{}: event is {{}} bytes, over the {} byte limit of a message",
+        interface.name, event.name, MAX_MESSAGE_SIZE
+    );
+    Some(quote!{
+        let _size: ::core::primitive::usize = #min_size #(+ (#extra))*;
+        if _size > #MAX_MESSAGE_SIZE {
+            return ::core::result::Result::Err(::yutani::wire::WlError {
+                object: ::yutani::Id::DISPLAY,
+                error: ::yutani::wire::WlError::INTERNAL.error,
+                description: ::std::borrow::Cow::Owned(::std::format!(#template, _size))
+            })
+        }
+    })
+}
+
+/// Functions in the interface module that send the events of an interface from an object of any type, along with its
+/// `INTERFACE` and `VERSION`, for objects that are only held as a `Lease<dyn Any>`.
+pub fn erased_senders(protocol: &Protocol, interface: &Interface, options: &CodegenOptions) -> TokenStream {
+    let t = state_param(options);
+    let senders = interface.events_with_opcodes().map(|(opcode, event)| {
+        let ident = ident::snake(&event.name);
+        let docs = docs([Some(format!("Send the `{}` event from an object without requiring its type.", event.name)), destructor_doc(event)]);
+        let args = event.args.iter().map(|a| {
+            let ident = ident::snake(&a.name);
+            let ty = a.send_ty(module_enum_path(protocol, interface, a, options).as_ref(), options);
+            quote!{#ident: #ty}
+        });
+        let body = event_body(protocol, interface, event, opcode, options, true);
+        let many_params = allow_many_params(2 + event.args.len());
+        quote!{
+            #docs
+            #many_params
+            pub fn #ident<O: ?::core::marker::Sized, #t>(_this: &::yutani::lease::Lease<O>, _client: &mut ::yutani::server::Client<#t> #(, #args)*) -> ::core::result::Result<(), ::yutani::wire::WlError<'static>> {
+                #body
+            }
+        }
+    });
+    quote!{
+        #[doc = "The name of the interface."]
+        pub const INTERFACE: &::core::primitive::str = DESCRIPTOR.name;
+        #[doc = "The highest version of the interface."]
+        pub const VERSION: ::core::primitive::u32 = DESCRIPTOR.version;
+        #(#senders)*
     }
 }
\ No newline at end of file
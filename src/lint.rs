@@ -0,0 +1,191 @@
+use std::{collections::BTreeMap, fmt};
+
+use crate::{ident, CodegenOptions, Diagnostic, Interface, Protocol, Severity};
+
+/// The extension key listing the lints allowed for an item of a specification and everything within it, such as
+/// `x-allow = ["naming::version_suffix"]`.
+pub const ALLOW_KEY: &str = "x-allow";
+
+/// A convention of specifications checked by [`validate`](crate::validate) at the [level](LintLevel) configured by
+/// [`CodegenOptions::lints`].
+///
+/// Lints are named by an id, such as `naming::snake_case`, or all lints of a group at once by the group, such as
+/// `naming`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Lint {
+    /// Names of interfaces, messages, arguments, enums and entries are in snake_case, such as `set_title`.
+    SnakeCase,
+    /// Names of interfaces start with the namespace of their protocol, such as `wl_` or `xdg_`.
+    NamespacePrefix,
+    /// A `_vN` suffix of an interface name matches its version.
+    VersionSuffix,
+    /// The enum of protocol errors of an interface is named `error`, rather than `errors`.
+    ErrorEnum
+}
+impl Lint {
+    /// Every lint, in the order they are checked.
+    pub const ALL: &'static [Lint] = &[Lint::SnakeCase, Lint::NamespacePrefix, Lint::VersionSuffix, Lint::ErrorEnum];
+
+    /// The id of the lint, such as `naming::snake_case`.
+    pub fn id(self) -> &'static str {
+        match self {
+            Self::SnakeCase => "naming::snake_case",
+            Self::NamespacePrefix => "naming::namespace_prefix",
+            Self::VersionSuffix => "naming::version_suffix",
+            Self::ErrorEnum => "naming::error_enum"
+        }
+    }
+    /// The group of the lint, such as `naming`.
+    pub fn group(self) -> &'static str {
+        self.id().split_once("::").map_or(self.id(), |(group, _)| group)
+    }
+    /// The lint with an id.
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|lint| lint.id() == id)
+    }
+    /// Whether a lint id or group names this lint.
+    fn is_named(self, name: &str) -> bool {
+        self.id() == name || self.group() == name
+    }
+    /// The level of the lint, set by its id or else by its group, and otherwise allowed.
+    fn level(self, options: &CodegenOptions) -> LintLevel {
+        options.lints.get(self.id())
+            .or_else(|| options.lints.get(self.group()))
+            .copied()
+            .unwrap_or(LintLevel::Allow)
+    }
+}
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
+/// How a [lint](Lint) is reported.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LintLevel {
+    /// Not checked.
+    #[default]
+    Allow,
+    /// Reported as a warning.
+    Warn,
+    /// Reported as an error, failing generation.
+    Deny
+}
+
+/// Check the lints configured by the options, and the names of lints given to the options and to `x-allow`.
+pub(crate) fn lint(diagnostics: &mut Vec<Diagnostic>, protocol: &Protocol, options: &CodegenOptions) {
+    for name in options.lints.keys().filter(|name| !is_lint_name(name)) {
+        diagnostics.push(Diagnostic::error(&protocol.name, format!("unknown lint `{}`", name)));
+    }
+    let mut linter = Linter { diagnostics, options };
+    let allowed = linter.allowed(&[], &protocol.name, &protocol.extensions);
+    for interface in &protocol.interfaces {
+        let allowed = linter.allowed(&allowed, &interface.name, &interface.extensions);
+        linter.interface(protocol, interface, &allowed);
+    }
+}
+
+struct Linter<'a> {
+    diagnostics: &'a mut Vec<Diagnostic>,
+    options: &'a CodegenOptions
+}
+impl Linter<'_> {
+    /// The lints allowed for an item, being those allowed for the item containing it and those in its `x-allow`.
+    fn allowed(&mut self, outer: &[Lint], path: &str, extensions: &BTreeMap<String, toml::Value>) -> Vec<Lint> {
+        let mut allowed = outer.to_vec();
+        let Some(value) = extensions.get(ALLOW_KEY) else {
+            return allowed
+        };
+        let names = match value {
+            toml::Value::String(name) => vec![Some(name.as_str())],
+            toml::Value::Array(names) => names.iter().map(toml::Value::as_str).collect(),
+            _ => vec![None]
+        };
+        for name in names {
+            match name {
+                Some(name) if is_lint_name(name) => allowed.extend(Lint::ALL.iter().filter(|lint| lint.is_named(name))),
+                Some(name) => self.diagnostics.push(Diagnostic::warning(path, format!("`{}` allows the unknown lint `{}`", ALLOW_KEY, name))),
+                None => self.diagnostics.push(Diagnostic::error(path, format!("`{}` must be a lint or an array of lints", ALLOW_KEY)))
+            }
+        }
+        allowed
+    }
+    /// Report a lint at its configured level, unless it is allowed for the item.
+    fn report(&mut self, lint: Lint, allowed: &[Lint], path: &str, message: String) {
+        let severity = match lint.level(self.options) {
+            _ if allowed.contains(&lint) => return,
+            LintLevel::Allow => return,
+            LintLevel::Warn => Severity::Warning,
+            LintLevel::Deny => Severity::Error
+        };
+        self.diagnostics.push(Diagnostic { severity, path: path.to_owned(), message, lint: Some(lint) });
+    }
+    fn snake_case(&mut self, allowed: &[Lint], path: &str, kind: &str, name: &str) {
+        // Entries are often numbers, such as `90` of `wl_output.transform`
+        if !is_snake_case(name, kind == "entry") {
+            self.report(Lint::SnakeCase, allowed, path, format!(
+                "the {} name `{}` is not snake_case, such as `{}`", kind, name, ident::snake_case(name)
+            ));
+        }
+    }
+    fn interface(&mut self, protocol: &Protocol, interface: &Interface, allowed: &[Lint]) {
+        let name = &interface.name;
+        self.snake_case(allowed, name, "interface", name);
+        if !name.split_once('_').is_some_and(|(namespace, rest)| !namespace.is_empty() && !rest.is_empty()) {
+            let example = protocol.interfaces.iter().find_map(|i| i.name.split_once('_').map(|(namespace, _)| namespace))
+                .filter(|namespace| !namespace.is_empty())
+                .unwrap_or("wl");
+            self.report(Lint::NamespacePrefix, allowed, name, format!(
+                "the interface name has no namespace prefix, such as `{}_{}`", example, name
+            ));
+        }
+        let suffix = name.rsplit_once("_v").map(|(_, version)| version)
+            .filter(|version| !version.is_empty() && version.chars().all(|c| c.is_ascii_digit()));
+        if let Some(suffix) = suffix.filter(|suffix| suffix.parse() != Ok(interface.version)) {
+            self.report(Lint::VersionSuffix, allowed, name, format!(
+                "the `_v{}` suffix does not match version {} of the interface", suffix, interface.version
+            ));
+        }
+        // Inherited messages and enums are checked by the interface they are inherited from
+        let messages = interface.requests.iter().filter(|r| !r.inherited).map(|r| ("request", &r.name, &r.args, &r.extensions))
+            .chain(interface.events.iter().filter(|e| !e.inherited).map(|e| ("event", &e.name, &e.args, &e.extensions)));
+        for (kind, message, args, extensions) in messages {
+            let path = format!("{}.{}", name, message);
+            let allowed = self.allowed(allowed, &path, extensions);
+            self.snake_case(&allowed, &path, kind, message);
+            for arg in args {
+                let path = format!("{}.{}", path, arg.name);
+                let allowed = self.allowed(&allowed, &path, &arg.extensions);
+                self.snake_case(&allowed, &path, "argument", &arg.name);
+            }
+        }
+        for enumeration in interface.enums.iter().filter(|e| !e.inherited) {
+            let path = format!("{}.{}", name, enumeration.name);
+            let allowed = self.allowed(allowed, &path, &enumeration.extensions);
+            self.snake_case(&allowed, &path, "enum", &enumeration.name);
+            if enumeration.name == "errors" {
+                self.report(Lint::ErrorEnum, &allowed, &path, "the enum of protocol errors should be named `error`".to_owned());
+            }
+            for entry in &enumeration.entries {
+                let path = format!("{}.{}", path, entry.name);
+                let allowed = self.allowed(&allowed, &path, &entry.extensions);
+                self.snake_case(&allowed, &path, "entry", &entry.name);
+            }
+        }
+    }
+}
+
+/// Whether a name is the id of a lint or the name of a group of lints.
+fn is_lint_name(name: &str) -> bool {
+    Lint::ALL.iter().any(|lint| lint.is_named(name))
+}
+
+/// Whether a name is lower case words joined by single underscores, such as `wl_surface`, optionally starting with a
+/// digit.
+fn is_snake_case(name: &str, leading_digit: bool) -> bool {
+    name.starts_with(|c: char| c.is_ascii_lowercase() || (leading_digit && c.is_ascii_digit()))
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        && !name.ends_with('_')
+        && !name.contains("__")
+}
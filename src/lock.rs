@@ -0,0 +1,245 @@
+use std::{collections::BTreeMap, fmt, fs, path::Path, str::FromStr};
+
+use serde::{de, Deserialize, Deserializer};
+
+use crate::{format::{basic_string, key_name}, signature, Diagnostic, Interface, Protocol, Result};
+
+/// The opcodes and signatures of the messages of protocols, to check specifications against with
+/// [`CodegenOptions::lockfile`](crate::CodegenOptions::lockfile).
+///
+/// Opcodes come from the order of messages in a specification, so reordering them breaks every client built against
+/// the protocol without anything else changing. A lockfile records them as a TOML table for each interface, such as
+/// `[wayland.wl_display]`, listing its requests and events in opcode order along with their
+/// [signatures](crate::signature), such as `"sync(n)"`.
+///
+/// Messages added after the last message of an interface pass the check, as do new interfaces, while messages that
+/// were moved, removed or changed fail it until the lockfile is written again.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct Lockfile {
+    /// The interfaces of each protocol, by protocol name and then interface name.
+    pub protocols: BTreeMap<String, BTreeMap<String, LockedInterface>>
+}
+impl Lockfile {
+    /// Record the messages of every interface of the protocols.
+    pub fn new<'a>(protocols: impl IntoIterator<Item = &'a Protocol>) -> Self {
+        let protocols = protocols.into_iter().map(|protocol| {
+            let interfaces = protocol.interfaces.iter().map(|interface| (interface.name.clone(), LockedInterface::new(interface)));
+            (protocol.name.clone(), interfaces.collect())
+        });
+        Self { protocols: protocols.collect() }
+    }
+    /// Load a lockfile written by [`Display`](fmt::Display), such as with `wl-codegen lock`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        fs::read_to_string(path)?.parse()
+    }
+    /// Check the messages of a protocol against those recorded for it, returning an error for each message that was
+    /// moved, removed, changed or put in place of another since.
+    pub fn check(&self, protocol: &Protocol) -> Vec<Diagnostic> {
+        let Some(locked) = self.protocols.get(&protocol.name) else {
+            return vec![Diagnostic::error(&protocol.name, "the protocol is not in the lockfile, which should be written again")]
+        };
+        let mut diagnostics = Vec::new();
+        for (name, locked) in locked {
+            let Some(interface) = protocol.interfaces.iter().find(|i| &i.name == name) else {
+                diagnostics.push(Diagnostic::error(&protocol.name, format!("the interface `{}` was removed since it was locked", name)));
+                continue
+            };
+            let current = LockedInterface::new(interface);
+            check_messages(&mut diagnostics, name, "request", &locked.requests, &current.requests);
+            check_messages(&mut diagnostics, name, "event", &locked.events, &current.events);
+        }
+        diagnostics
+    }
+}
+impl FromStr for Lockfile {
+    type Err = crate::Error;
+    fn from_str(lockfile: &str) -> Result<Self> {
+        Ok(toml::from_str(lockfile)?)
+    }
+}
+impl fmt::Display for Lockfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# The opcodes of the messages of each interface, in order, written by `wl-codegen lock`.")?;
+        writeln!(f, "# Messages may be added after the last of an interface. Moving, removing or changing a message breaks")?;
+        writeln!(f, "# existing clients, so fails generation until this file is written again.")?;
+        for (protocol, interfaces) in &self.protocols {
+            for (name, interface) in interfaces {
+                write!(f, "\n[{}.{}]\n", key_name(protocol), key_name(name))?;
+                for (key, messages) in [("requests", &interface.requests), ("events", &interface.events)] {
+                    if messages.is_empty() {
+                        continue
+                    }
+                    writeln!(f, "{} = [", key)?;
+                    for message in messages {
+                        writeln!(f, "    {},", basic_string(&message.to_string()))?;
+                    }
+                    writeln!(f, "]")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The messages of an interface recorded in a [`Lockfile`], in opcode order.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LockedInterface {
+    /// The requests, with their opcodes as their positions.
+    #[serde(default)]
+    pub requests: Vec<LockedMessage>,
+    /// The events, with their opcodes as their positions.
+    #[serde(default)]
+    pub events: Vec<LockedMessage>
+}
+impl LockedInterface {
+    fn new(interface: &Interface) -> Self {
+        Self {
+            requests: interface.requests.iter().map(|r| LockedMessage::new(&r.name, signature(None, &r.args))).collect(),
+            events: interface.events.iter().map(|e| LockedMessage::new(&e.name, signature(None, &e.args))).collect()
+        }
+    }
+}
+
+/// A message recorded in a [`Lockfile`], written as its name followed by its signature, such as `attach(?oii)`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LockedMessage {
+    pub name: String,
+    /// The [signature](crate::signature) of the arguments, without the version the message was added in.
+    pub signature: String
+}
+impl LockedMessage {
+    fn new(name: &str, signature: String) -> Self {
+        Self { name: name.to_owned(), signature }
+    }
+}
+impl fmt::Display for LockedMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}({})", self.name, self.signature)
+    }
+}
+impl<'de> Deserialize<'de> for LockedMessage {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let message = String::deserialize(deserializer)?;
+        message.strip_suffix(')')
+            .and_then(|message| message.split_once('('))
+            .map(|(name, signature)| Self::new(name, signature.to_owned()))
+            .ok_or_else(|| de::Error::custom(format!("expected a message and its signature, such as \"sync(n)\", found {:?}", message)))
+    }
+}
+
+/// Report the messages that no longer have the opcode and signature they were locked with.
+fn check_messages(diagnostics: &mut Vec<Diagnostic>, interface: &str, kind: &str, locked: &[LockedMessage], current: &[LockedMessage]) {
+    for (opcode, message) in locked.iter().enumerate() {
+        let path = format!("{}.{}", interface, message.name);
+        match current.iter().position(|m| m.name == message.name) {
+            Some(moved) if moved != opcode => diagnostics.push(Diagnostic::error(&path, format!(
+                "the {} moved from opcode {} to opcode {} since it was locked", kind, opcode, moved
+            ))),
+            Some(_) if current[opcode].signature != message.signature => diagnostics.push(Diagnostic::error(&path, format!(
+                "the signature of the {} changed from `{}` to `{}` since it was locked", kind, message.signature, current[opcode].signature
+            ))),
+            Some(_) => (),
+            None => diagnostics.push(Diagnostic::error(&path, format!(
+                "the {} with opcode {} was removed since it was locked", kind, opcode
+            )))
+        }
+    }
+    // New messages must come after those already locked
+    for (opcode, message) in current.iter().enumerate().take(locked.len()) {
+        if !locked.iter().any(|m| m.name == message.name) {
+            diagnostics.push(Diagnostic::error(format!("{}.{}", interface, message.name), format!(
+                "the new {} takes opcode {} of the locked {} `{}`, rather than coming after the last locked {}",
+                kind, opcode, kind, locked[opcode].name, kind
+            )));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+
+    /// A protocol with an interface whose requests are `requests`, such as `"attach:object"`.
+    fn protocol(requests: &[&str]) -> Protocol {
+        let requests: String = requests.iter().map(|request| {
+            let (name, args) = request.split_once(':').unwrap_or((request, ""));
+            let args: Vec<_> = args.split(',').filter(|arg| !arg.is_empty()).enumerate().map(|(i, ty)| format!("\"a{}:{}\"", i, ty)).collect();
+            format!("[[interface.request]]\nname = \"{}\"\narg = [{}]\n", name, args.join(", "))
+        }).collect();
+        Protocol::from_str(&format!("name = \"acme\"\n[[interface]]\nname = \"acme_thing\"\nversion = 1\n[[interface.event]]\nname = \"done\"\n{}", requests)).unwrap()
+    }
+
+    /// The diagnostics of checking the requests `current` against a lockfile of the requests `locked`.
+    fn check(locked: &[&str], current: &[&str]) -> Vec<String> {
+        Lockfile::new([&protocol(locked)]).check(&protocol(current)).iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let lockfile = Lockfile::new([&protocol(&["destroy", "attach:object,int"])]);
+        let written = lockfile.to_string();
+        assert!(written.contains("[acme.acme_thing]\nrequests = [\n    \"destroy()\",\n    \"attach(oi)\",\n]\nevents = [\n    \"done()\",\n]\n"), "{}", written);
+        assert_eq!(written.parse::<Lockfile>().unwrap(), lockfile);
+    }
+
+    #[test]
+    fn unchanged_and_appended() {
+        assert!(check(&["destroy", "attach:int"], &["destroy", "attach:int"]).is_empty());
+        assert!(check(&["destroy", "attach:int"], &["destroy", "attach:int", "commit"]).is_empty());
+    }
+
+    #[test]
+    fn moved() {
+        assert_eq!(check(&["destroy", "attach:int"], &["attach:int", "destroy"]), [
+            "error: acme_thing.destroy: the request moved from opcode 0 to opcode 1 since it was locked",
+            "error: acme_thing.attach: the request moved from opcode 1 to opcode 0 since it was locked"
+        ]);
+    }
+
+    #[test]
+    fn changed() {
+        assert_eq!(check(&["destroy", "attach:int"], &["destroy", "attach:int,uint"]), [
+            "error: acme_thing.attach: the signature of the request changed from `i` to `iu` since it was locked"
+        ]);
+    }
+
+    #[test]
+    fn removed() {
+        assert_eq!(check(&["destroy", "attach:int"], &["destroy"]), [
+            "error: acme_thing.attach: the request with opcode 1 was removed since it was locked"
+        ]);
+        let lockfile = Lockfile::new([&protocol(&["destroy"])]);
+        let mut removed = protocol(&["destroy"]);
+        removed.interfaces.clear();
+        assert_eq!(lockfile.check(&removed)[0].to_string(), "error: acme: the interface `acme_thing` was removed since it was locked");
+    }
+
+    #[test]
+    fn inserted_in_middle() {
+        assert_eq!(check(&["destroy", "attach:int", "commit"], &["destroy", "damage", "attach:int", "commit"]), [
+            "error: acme_thing.attach: the request moved from opcode 1 to opcode 2 since it was locked",
+            "error: acme_thing.commit: the request moved from opcode 2 to opcode 3 since it was locked",
+            "error: acme_thing.damage: the new request takes opcode 1 of the locked request `attach`, rather than coming after the last locked request"
+        ]);
+    }
+
+    #[test]
+    fn malformed() {
+        for lockfile in [
+            "[acme.acme_thing]\nrequests = [\"destroy\"]\n",
+            "[acme.acme_thing]\nrequests = [\"destroy()\"]\nmessages = []\n",
+            "[acme.acme_thing]\nrequests = [1]\n",
+            "[acme.acme_thing\n"
+        ] {
+            assert!(matches!(lockfile.parse::<Lockfile>(), Err(Error::Toml(_))), "{:?}", lockfile);
+        }
+        let Err(Error::Toml(error)) = "[acme.acme_thing]\nrequests = [\"destroy\"]\n".parse::<Lockfile>() else { unreachable!() };
+        assert!(error.to_string().contains("expected a message and its signature, such as \"sync(n)\", found \"destroy\""), "{}", error);
+        let protocol = protocol(&["destroy"]);
+        let diagnostics = Lockfile::default().check(&protocol);
+        assert_eq!(diagnostics[0].to_string(), "error: acme: the protocol is not in the lockfile, which should be written again");
+    }
+}
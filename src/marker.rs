@@ -0,0 +1,123 @@
+//! The `GENERATED_WITH` marker recording the version and options that generated code, so that checked in code can be
+//! checked for being stale.
+
+use std::{fmt, fs, path::Path};
+
+use crate::{CodegenOptions, Error, Result};
+
+const GENERATOR: &str = "yutani-codegen";
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The marker for code generated by this version of the crate with these options, in the form
+/// `yutani-codegen <version>, options-hash <hash>`.
+pub fn generated_with(options: &CodegenOptions) -> String {
+    format!("{} {}, options-hash {}", GENERATOR, VERSION, options_hash(options))
+}
+
+/// A stable hash of the options that change the generated code, which unlike `std::hash` does not change between Rust
+/// versions.
+/// 
+/// Options that only change [`validate`](crate::validate), such as lints and the lockfile, are left out so that
+/// changing them does not make checked in code stale. Every field is named so that new options must be sorted into one
+/// or the other.
+fn options_hash(options: &CodegenOptions) -> String {
+    let CodegenOptions {
+        rust_enums, serde, validate_enums, unstable_attribute, generic_param, generic_bounds, rust_module, wrap_module,
+        embed_spec, acronyms, acronym_case, strip_unstable_prefix, fixed_as_f64, copyright, allow, split_traits,
+        args_struct_threshold, event_structs, erased_senders, ext_traits, concrete_types, core_interfaces,
+        external_modules, dispatch_metrics, dispatch_strategy, intercept_requests, source_markers,
+        embed_spec_limit: _, lints: _, deny_warnings: _, lockfile: _, strict: _
+    } = options;
+    let hashed: [&dyn fmt::Debug; 27] = [
+        rust_enums, serde, validate_enums, unstable_attribute, generic_param, generic_bounds, rust_module, wrap_module,
+        embed_spec, acronyms, acronym_case, strip_unstable_prefix, fixed_as_f64, copyright, allow, split_traits,
+        args_struct_threshold, event_structs, erased_senders, ext_traits, concrete_types, core_interfaces,
+        external_modules, dispatch_metrics, dispatch_strategy, intercept_requests, source_markers
+    ];
+    // 64-bit FNV-1a
+    let hash = format!("{:?}", hashed).bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    });
+    format!("{:016x}", hash)
+}
+
+/// A difference between the marker of generated code and the code that would be generated now.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Mismatch {
+    /// The code has no `GENERATED_WITH` marker, or it could not be read.
+    Unmarked,
+    /// The code was generated by a different version of the crate.
+    Version { generated: String, current: String },
+    /// The code was generated with different options.
+    Options { generated: String, current: String },
+    /// The code differs from the code generated now, by a unified diff from the code to the code generated now, see
+    /// [`build::verify`](crate::build::verify).
+    Contents { diff: String }
+}
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unmarked => write!(f, "no `GENERATED_WITH` marker was found"),
+            Self::Version { generated, current } => write!(f, "generated by {} {}, but the current version is {}", GENERATOR, generated, current),
+            Self::Options { generated, current } => write!(f, "generated with options hash {}, but the current options hash is {}", generated, current),
+            Self::Contents { diff } => write!(f, "the code differs from the code generated now:\n{}", diff)
+        }
+    }
+}
+
+/// Check that the code at a path was generated by this version of the crate with these options, failing with
+/// [`Error::Stale`] listing the differences if not.
+pub fn verify_generated<P: AsRef<Path>>(path: P, options: &CodegenOptions) -> Result<()> {
+    let code = fs::read_to_string(path)?;
+    let mismatches = match parse(&code) {
+        None => vec![Mismatch::Unmarked],
+        Some((version, hash)) => {
+            let mut mismatches = Vec::new();
+            if version != VERSION {
+                mismatches.push(Mismatch::Version { generated: version.into(), current: VERSION.into() });
+            }
+            let current = options_hash(options);
+            if hash != current {
+                mismatches.push(Mismatch::Options { generated: hash.into(), current });
+            }
+            mismatches
+        }
+    };
+    if mismatches.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Stale(mismatches))
+    }
+}
+
+/// The version and options hash of the `GENERATED_WITH` constant in generated code, formatted or not.
+fn parse(code: &str) -> Option<(&str, &str)> {
+    let (_, rest) = code.split_once("GENERATED_WITH")?;
+    let (_, rest) = rest.split_once('"')?;
+    let (marker, _) = rest.split_once('"')?;
+    let (version, hash) = marker.strip_prefix(GENERATOR)?.trim_start().split_once(", options-hash ")?;
+    Some((version, hash))
+}
+
+/// Code with the version and options hash of every marker removed, so that code generated by another version or with
+/// options that only differ in their hash compares equal if it is otherwise the same.
+pub(crate) fn without_markers(code: &str) -> String {
+    let mut stripped = String::with_capacity(code.len());
+    let mut rest = code;
+    while let Some(start) = rest.find(GENERATOR) {
+        let (before, marker) = rest.split_at(start + GENERATOR.len());
+        stripped.push_str(before);
+        rest = marker;
+        // Such as ` 0.1.0, options-hash 0123456789abcdef`, where the version is a single word
+        let Some(version) = marker.strip_prefix(' ') else {
+            continue
+        };
+        let version_len = version.find(|c: char| c.is_whitespace() || matches!(c, ',' | '"')).unwrap_or(version.len());
+        if let Some(hash) = version[version_len..].strip_prefix(", options-hash ") {
+            let hash_len = hash.find(|c: char| !c.is_ascii_hexdigit()).unwrap_or(hash.len());
+            rest = &hash[hash_len..];
+        }
+    }
+    stripped.push_str(rest);
+    stripped
+}
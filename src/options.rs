@@ -0,0 +1,205 @@
+use std::collections::BTreeMap;
+
+/// Options controlling the shape of the generated code.
+/// 
+/// The defaults generate the same code as [`protocol`](crate::protocol).
+#[derive(Clone, Debug, Default)]
+pub struct CodegenOptions {
+    /// Generate enums as `#[repr(u32)]` Rust enums rather than `u32` newtypes with associated constants.
+    /// 
+    /// Bitfields and enums with multiple entries sharing a value cannot be represented as Rust enums, and fall back
+    /// to the newtype representation with a warning.
+    pub rust_enums: bool,
+    /// Implement `Serialize` and `Deserialize` for enums using the `serde` crate at this path, such as `::serde`.
+    /// 
+    /// Enums are serialized as the name of the matching entry, or as an integer if the value is unknown, and can be
+    /// deserialized from either form. The consuming crate must depend on `serde` itself.
+    pub serde: Option<String>,
+    /// Reject requests with arguments that do not match their enum with a protocol error before dispatch.
+    /// 
+    /// Enums are checked for an entry with the same value, and bitfields for bits set outside of `MASK`.
+    pub validate_enums: bool,
+    /// An attribute applied to the trait and module of unstable and staging interfaces, such as `doc(hidden)` or
+    /// `cfg(feature = "unstable")`.
+    pub unstable_attribute: Option<String>,
+    /// The name of the generic parameter of the interface traits for the state of the event loop, `T` if unset.
+    pub generic_param: Option<String>,
+    /// Bounds on the generic parameter for the state of the event loop, such as `'static + CompositorState`, which
+    /// are required by the interface traits and the functions dispatching to them.
+    pub generic_bounds: Option<String>,
+    /// The name of the module for the generated code, overriding [`Protocol::rust_module`](crate::Protocol::rust_module)
+    /// and the protocol name, see [`module_name`](crate::module_name).
+    pub rust_module: Option<String>,
+    /// Wrap the generated code in `pub mod <name> { ... }`, named by [`module_name`](crate::module_name), with the
+    /// docs and allowed lints as outer attributes of the module.
+    /// 
+    /// The generated code is otherwise the contents of a module, headed by inner attributes that are only valid at
+    /// the start of a file. The wrapped code is valid anywhere an item is, such as in the output of a macro.
+    pub wrap_module: bool,
+    /// Embed the specification in the generated code as `PROTOCOL_SPEC`, written as TOML in the canonical layout of
+    /// [`format_spec`](crate::format_spec), along with the protocol name as `PROTOCOL_NAME`.
+    pub embed_spec: bool,
+    /// The size in bytes above which an embedded specification is warned about, or [`EMBED_SPEC_LIMIT`] if unset.
+    pub embed_spec_limit: Option<usize>,
+    /// Words of names that are acronyms, such as `dmabuf` or `v1`, which are cased by
+    /// [`acronym_case`](Self::acronym_case) in the PascalCase names of traits, enums, entries and structs. Words
+    /// are matched regardless of case, and are split as in `wp_linux_dmabuf_v1`.
+    pub acronyms: Vec<String>,
+    /// How the [acronyms](Self::acronyms) are cased in PascalCase names.
+    pub acronym_case: AcronymCase,
+    /// Strip the `z` prefix that unstable interfaces conventionally carry, such as `zwp_linux_dmabuf_v1`, from the
+    /// names of generated items. The interface name sent over the wire is unchanged.
+    pub strip_unstable_prefix: bool,
+    /// Expose `fixed` arguments as `f64` rather than `::yutani::Fixed`, converting to and from 24.8 fixed point.
+    /// 
+    /// Fixed point numbers have a precision of 1/256, so values are rounded to the nearest 1/256 when sent. Values
+    /// outside of the range of a fixed point number saturate to its minimum or maximum, and NaN is sent as 0.
+    pub fixed_as_f64: bool,
+    /// Where the copyright of the protocol is placed in the generated code.
+    pub copyright: CopyrightStyle,
+    /// Lints allowed for the generated code, such as `dead_code` or `clippy::style`.
+    /// 
+    /// The lints are allowed by an inner `#![allow]` attribute, as the generated code is the contents of a module, or
+    /// by an outer `#[allow]` attribute of the module with [`wrap_module`](Self::wrap_module).
+    pub allow: Vec<String>,
+    /// Split each interface trait into a `Requests` trait with the request handlers, which is implemented, and an
+    /// `Events` trait with the event senders, which is implemented for all implementors of the `Requests` trait.
+    /// 
+    /// `WlSurface` becomes `WlSurfaceRequests` and `WlSurfaceEvents`. `INTERFACE` and `VERSION` are part of the
+    /// `Requests` trait, while `into_object` is part of the `WlSurfaceDispatch` trait.
+    pub split_traits: bool,
+    /// Pass the arguments of requests with at least this many arguments to the trait method as a struct with named
+    /// fields, such as `wl_surface::AttachArgs`, rather than as positional parameters.
+    pub args_struct_threshold: Option<usize>,
+    /// Generate a struct for each event in the interface module, such as `wl_output::GeometryEvent`, with a field for
+    /// each argument and a `send` method. The event senders of the interface trait remain, and send the struct.
+    pub event_structs: bool,
+    /// Generate functions in each interface module that send its events from an object of any type, such as
+    /// `wl_surface::enter`, along with `INTERFACE` and `VERSION` constants.
+    /// 
+    /// Sending an event only needs the id of the object, so objects that are only held as a `Lease<dyn Any>` can send
+    /// events without knowing their type. Dispatching requests always needs the concrete type, as it calls the trait
+    /// implementation.
+    pub erased_senders: bool,
+    /// Generate an extension trait of `Lease` for each interface with events, such as `WlSurfaceExt`, so that events
+    /// are sent with method call syntax, such as `surface.enter(client, output)` rather than
+    /// `WlSurface::enter(&mut surface, client, output)`, which remains.
+    /// 
+    /// The methods of `Lease` itself, such as `id` and `version`, take precedence, so events of those names are
+    /// rejected by [`validate`](crate::validate).
+    pub ext_traits: bool,
+    /// Concrete types implementing an interface trait, by interface name, such as `wl_surface` to `crate::Surface`.
+    /// 
+    /// An inherent function such as `Surface::dispatch_wl_surface` is generated for each, which dispatches requests
+    /// without downcasting from `Lease<dyn Any>` for objects known to be of that type. The types must be defined in
+    /// the crate the code is generated into.
+    pub concrete_types: BTreeMap<String, String>,
+    /// Interfaces implemented by the runtime, such as `wl_display` and `wl_registry`, by interface name, to the path
+    /// of the function dispatching requests to them, such as `::yutani::server::display::dispatch`.
+    /// 
+    /// Only the module of a core interface is generated, with its descriptor, message tables, enums and debug
+    /// printers, along with an `implementation` function pairing the descriptor with the dispatch function of the
+    /// runtime. There is no trait to implement, so the runtime does not conflict with a generated definition.
+    pub core_interfaces: BTreeMap<String, String>,
+    /// The absolute paths of the generated modules of other protocols, by the name of an
+    /// [external interface](crate::Protocol::externals) they define, such as `wl_output` to `crate::wayland`.
+    /// 
+    /// Arguments referring to an enum of an external interface with a module, such as `wl_output.transform`, use the
+    /// enum type, such as `crate::wayland::wl_output::Transform`, named as if generated with the same options, and
+    /// the docs of arguments link to the module of the interface. Without a module, such arguments are plain integers.
+    /// Arguments referring to an interface are always untyped ids, so need no module.
+    pub external_modules: BTreeMap<String, String>,
+    /// Count the requests dispatched to each interface by opcode when this `cfg` predicate holds, such as
+    /// `feature = "metrics"`.
+    /// 
+    /// Each interface module gains a `request_counts` function returning an `AtomicU64` for each request, named by
+    /// `REQUEST_NAMES`, which the dispatch functions increment before handling a request. Nothing is generated when
+    /// unset, and nothing is compiled when the predicate does not hold.
+    pub dispatch_metrics: Option<String>,
+    /// How the dispatch functions find the handler of a request by its opcode.
+    pub dispatch_strategy: DispatchStrategy,
+    /// Give each interface trait with requests `before_request` and `after_request` hooks, called around the handler
+    /// of every request with a known opcode, such as for tracing. The hooks do nothing by default.
+    ///
+    /// The hooks observe the client, the interface, the opcode and the name of the request, and `after_request` its
+    /// result, including errors reading its arguments, but cannot stop the request from being handled.
+    pub intercept_requests: bool,
+    /// Mark each generated trait, method and enum with the item of the specification it was generated from, along
+    /// with the file and line when known, such as `protocol/wayland.toml:120: request wl_surface.attach`.
+    ///
+    /// [`render`](crate::render) writes the markers as comments above the items, for finding the specification of
+    /// some generated code. [`generate`](crate::generate) leaves them as attributes that are never enabled.
+    pub source_markers: bool,
+    /// The [level](crate::LintLevel) of [lints](crate::Lint) of the specification checked by
+    /// [`validate`](crate::validate), by lint id, such as `naming::snake_case`, or by group, such as `naming`.
+    ///
+    /// Lints are allowed unless given a level, and a level given by id overrides one given by group. Lints can also be
+    /// allowed for an item of the specification and everything within it with `x-allow`, such as
+    /// `x-allow = ["naming::version_suffix"]`.
+    pub lints: BTreeMap<String, crate::LintLevel>,
+    /// Fail on the warnings of [`validate`](crate::validate) as on its errors, such as to keep the specifications of a
+    /// crate free of warnings.
+    pub deny_warnings: bool,
+    /// Fail validation if a message was moved, removed or changed since it was recorded in a
+    /// [lockfile](crate::Lockfile), which would break clients built against the protocol. Messages added after the
+    /// last message of an interface, and new interfaces, are allowed.
+    pub lockfile: Option<crate::Lockfile>,
+    /// Reject keys of the specification that are not part of the format, which are otherwise warned about.
+    /// 
+    /// Keys starting with `x-` are [extensions](crate::Protocol::extensions) for other tools, and are always allowed.
+    pub strict: bool
+}
+/// The default size in bytes above which an [embedded specification](CodegenOptions::embed_spec) is warned about.
+pub const EMBED_SPEC_LIMIT: usize = 64 * 1024;
+
+impl CodegenOptions {
+    /// Allow lints for the generated code, in addition to those already allowed.
+    pub fn allow(mut self, lints: &[&str]) -> Self {
+        self.allow.extend(lints.iter().map(|&lint| lint.to_owned()));
+        self
+    }
+    /// Set the level of lints of the specification, by lint id or group.
+    pub fn lint(mut self, lints: &[&str], level: crate::LintLevel) -> Self {
+        self.lints.extend(lints.iter().map(|&lint| (lint.to_owned(), level)));
+        self
+    }
+}
+
+/// How [acronyms](CodegenOptions::acronyms) are cased in PascalCase names.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AcronymCase {
+    /// In upper case, such as `WpLinuxDMABUFV1`.
+    #[default]
+    Upper,
+    /// In lower case after the first letter, as other words are, such as `WpLinuxDmabufV1`.
+    Lower,
+    /// As written in the list of acronyms, such as `WpLinuxDmaBufV1` for `DmaBuf`.
+    Preserve
+}
+
+/// Where the copyright of a protocol is placed in the generated code.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CopyrightStyle {
+    /// A `Copyright` section of the crate-level docs.
+    #[default]
+    Doc,
+    /// `//` comments at the top of the code rendered by [`render`](crate::render).
+    /// 
+    /// Comments cannot be represented in a `TokenStream`, so the copyright is omitted from [`generate`](crate::generate).
+    Comment,
+    /// Omitted entirely.
+    Omit
+}
+
+/// How the [dispatch functions](CodegenOptions::dispatch_strategy) find the handler of a request.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DispatchStrategy {
+    /// A `match` on the opcode, with an arm reading the arguments of each request.
+    #[default]
+    Match,
+    /// A static table of functions indexed by the opcode, one reading the arguments of each request.
+    ///
+    /// Requests are handled the same way, including opcodes past the end of the table being passed to
+    /// `unknown_request`, but each handler is a small function of its own rather than an arm of one large function.
+    Table
+}
@@ -1,88 +1,847 @@
 use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    fmt,
     fs::File,
     io::Read,
-    path::Path
+    path::{Path, PathBuf}
 };
-use heck::ToSnakeCase;
-use proc_macro2::{TokenStream, Ident, Span};
+use proc_macro2::{TokenStream, Ident};
 use quote::quote;
-use serde::Deserialize;
+use serde::{de::{self, MapAccess, SeqAccess, Visitor}, Deserialize, Deserializer, Serialize, Serializer};
+use toml::Spanned;
 
-use crate::Result;
+use crate::{ident, wl_error, CodegenOptions, Error, Result};
 
-#[derive(Debug, Deserialize)]
+/// Strip a leading byte order mark and convert CRLF line endings to LF, so that specifications edited on Windows
+/// parse and produce the same documentation.
+fn normalize(string: &str) -> Cow<'_, str> {
+    let string = string.strip_prefix('\u{feff}').unwrap_or(string);
+    if string.contains("\r\n") {
+        Cow::Owned(string.replace("\r\n", "\n"))
+    } else {
+        Cow::Borrowed(string)
+    }
+}
+
+/// A format protocol specifications can be written in.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Toml,
+    #[cfg(feature = "json")]
+    Json,
+    #[cfg(feature = "yaml")]
+    Yaml,
+    /// The XML format of Wayland protocol specifications.
+    Xml
+}
+impl Format {
+    /// The format of a file by its extension, which is XML for `.xml`, JSON for `.json` and YAML for `.yaml` or `.yml`
+    /// when the respective features are enabled, and TOML otherwise.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        match path.as_ref().extension().and_then(|extension| extension.to_str()) {
+            #[cfg(feature = "json")]
+            Some("json") => Self::Json,
+            #[cfg(feature = "yaml")]
+            Some("yaml" | "yml") => Self::Yaml,
+            Some("xml") => Self::Xml,
+            _ => Self::Toml
+        }
+    }
+}
+
+/// Several protocols specified in one TOML file, as `[[protocol]]` tables.
+#[derive(Deserialize)]
+struct Protocols {
+    #[serde(rename = "protocol")]
+    protocols: Vec<Protocol>
+}
+/// The names of the items of a TOML specification with their spans, as [`Protocol`] is parsed without spans.
+#[derive(Deserialize)]
+struct ProtocolLines {
+    #[serde(rename = "interface", alias = "interfaces", default)]
+    interfaces: Vec<InterfaceLines>
+}
+#[derive(Deserialize)]
+struct ProtocolsLines {
+    #[serde(rename = "protocol")]
+    protocols: Vec<ProtocolLines>
+}
+#[derive(Deserialize)]
+struct InterfaceLines {
+    name: Spanned<String>,
+    #[serde(rename = "enum", alias = "enums", default)]
+    enums: Vec<ItemLine>,
+    #[serde(rename = "request", alias = "requests", default)]
+    requests: Vec<ItemLine>,
+    #[serde(rename = "event", alias = "events", default)]
+    events: Vec<ItemLine>
+}
+#[derive(Deserialize)]
+struct ItemLine {
+    name: Spanned<String>
+}
+
+/// Check if a TOML specification has `[[protocol]]` tables, rather than being a single protocol.
+fn is_multiple(string: &str) -> bool {
+    toml::from_str::<toml::Value>(string).is_ok_and(|value| value.get("protocol").is_some_and(toml::Value::is_array))
+}
+
+#[derive(Debug, Deserialize, Serialize)]
 pub struct Protocol {
     pub name: String,
+    /// The name of the module for the generated code, rather than the protocol name in snake case, see
+    /// [`module_name`](crate::module_name).
+    pub rust_module: Option<String>,
+    /// The module that the module for the generated code is placed in, such as `acme`, so that protocols with
+    /// interfaces of the same name can be generated together, see [`module_path`](crate::module_path()). The names
+    /// sent over the wire are unchanged.
+    pub namespace: Option<String>,
     pub summary: Option<String>,
+    #[serde(default, deserialize_with = "description")]
     pub description: Option<String>,
     pub copyright: Option<String>,
-    #[serde(rename = "interface", default)]
-    pub interfaces: Vec<Interface>
+    /// The stability as written, which is resolved to [`stability`](Self::stability).
+    #[serde(rename = "stability")]
+    pub stability_name: Option<String>,
+    #[serde(skip)]
+    pub stability: Stability,
+    /// Interfaces of other protocols that arguments refer to, such as `wl_surface` for a protocol extending a surface,
+    /// so that [`validate`](crate::validate) can tell them apart from misspelled interfaces.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub externals: Vec<String>,
+    #[serde(rename = "const", alias = "constants", default, skip_serializing_if = "Vec::is_empty")]
+    pub constants: Vec<Constant>,
+    #[serde(rename = "interface", alias = "interfaces", default, skip_serializing_if = "Vec::is_empty")]
+    pub interfaces: Vec<Interface>,
+    /// The file the specification was [loaded](Self::load) from, which along with the [lines](Interface::line) of
+    /// items of a TOML specification is recorded by [source markers](CodegenOptions::source_markers).
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
+    /// Keys that are not part of the format, by name. Keys starting with `x-`, such as `x-owner`, are extensions for
+    /// other tools, while any other key is reported as unknown by [`validate`](crate::validate).
+    #[serde(flatten)]
+    pub extensions: BTreeMap<String, toml::Value>
 }
 impl Protocol {
+    /// Parse a protocol specified in TOML.
+    /// 
+    /// As with the other formats, a leading byte order mark is ignored and CRLF line endings are read as LF. A
+    /// specification of several protocols in `[[protocol]]` tables fails with [`Error::MultipleProtocols`], as it is
+    /// parsed with [`from_str_all`](Self::from_str_all).
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(string: &str) -> Result<Self> {
-        Ok(toml::from_str(string)?)
+        let string = normalize(string);
+        let mut protocol: Self = match toml::from_str(&string) {
+            Ok(protocol) => protocol,
+            Err(_) if is_multiple(&string) => return Err(Error::MultipleProtocols),
+            Err(error) => return Err(error.into())
+        };
+        if protocol.extensions.get("protocol").is_some_and(toml::Value::is_array) {
+            return Err(Error::MultipleProtocols)
+        }
+        if let Ok(lines) = toml::from_str(&string) {
+            protocol.set_lines(&string, lines);
+        }
+        protocol.resolve();
+        Ok(protocol)
+    }
+    /// Parse the protocols specified in TOML, either as several protocols in `[[protocol]]` tables, such as for small
+    /// extensions kept together, or as a single protocol.
+    pub fn from_str_all(string: &str) -> Result<Vec<Self>> {
+        let string = normalize(string);
+        if !is_multiple(&string) {
+            return Self::from_str(&string).map(|protocol| vec![protocol])
+        }
+        let Protocols { mut protocols } = toml::from_str(&string)?;
+        let lines: ProtocolsLines = toml::from_str(&string)?;
+        for (protocol, lines) in protocols.iter_mut().zip(lines.protocols) {
+            protocol.set_lines(&string, lines);
+            protocol.resolve();
+        }
+        Ok(protocols)
     }
+    /// Parse a protocol specified in JSON, where arrays may use either the singular keys of the TOML format, such as
+    /// `interface`, or plural keys such as `interfaces`.
+    #[cfg(feature = "json")]
+    pub fn from_json(string: &str) -> Result<Self> {
+        let mut protocol: Self = serde_json::from_str(&normalize(string))?;
+        protocol.resolve();
+        Ok(protocol)
+    }
+    /// Parse a protocol specified in YAML, where arrays may use either the singular keys of the TOML format, such as
+    /// `interface`, or plural keys such as `interfaces`.
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml(string: &str) -> Result<Self> {
+        let mut protocol: Self = serde_yaml::from_str(&normalize(string))?;
+        protocol.resolve();
+        Ok(protocol)
+    }
+    /// Parse a protocol specified in the XML format of Wayland.
+    /// 
+    /// Descriptions have their indentation removed. The format has no list of [`externals`](Self::externals), so
+    /// every interface that an argument refers to without the protocol defining it is listed.
+    pub fn from_xml(string: &str) -> Result<Self> {
+        let mut protocol: Self = crate::xml::parse(&normalize(string))?.try_into()?;
+        let referenced = protocol.interfaces.iter()
+            .flat_map(|i| i.requests.iter().flat_map(|r| &r.args).chain(i.events.iter().flat_map(|e| &e.args)))
+            .filter_map(|a| a.interface.as_ref());
+        let mut externals: Vec<_> = referenced.filter(|name| protocol.interface(name).is_none()).cloned().collect();
+        externals.sort();
+        externals.dedup();
+        protocol.externals = externals;
+        protocol.resolve();
+        Ok(protocol)
+    }
+    /// Write the protocol as a TOML specification in the canonical layout of [`format_spec`](crate::format_spec).
+    pub fn to_toml(&self) -> String {
+        crate::format::toml(self)
+    }
+    /// Resolve values that depend on other items of the protocol, such as interfaces that extend another, entries that
+    /// are aliases of another entry, and [`Vocabulary`] fields from the names they are written as.
+    /// 
+    /// Anything that cannot be resolved is left as is for [`validate`](crate::validate) to report. Resolving again,
+    /// such as after a [transform](Self::transform), copies inherited items afresh from the interfaces they extend.
+    pub fn resolve(&mut self) {
+        for interface in &mut self.interfaces {
+            interface.enums.retain(|e| !e.inherited);
+            interface.requests.retain(|r| !r.inherited);
+            interface.events.retain(|e| !e.inherited);
+        }
+        let mut expanded = vec![false; self.interfaces.len()];
+        for index in 0..self.interfaces.len() {
+            expand(&mut self.interfaces, index, &mut expanded);
+        }
+        resolve_name(&mut self.stability, self.stability_name.as_deref());
+        for constant in &mut self.constants {
+            resolve_name(&mut constant.ty, Some(&constant.type_name));
+        }
+        for interface in &mut self.interfaces {
+            if let Some(name) = &interface.stability_name {
+                interface.stability = Stability::from_name(name).or(interface.stability);
+            }
+            let args = interface.requests.iter_mut().flat_map(|r| &mut r.args)
+                .chain(interface.events.iter_mut().flat_map(|e| &mut e.args));
+            for arg in args {
+                resolve_name(&mut arg.ty, Some(&arg.type_name));
+                if let Some(name) = &arg.array_type_name {
+                    arg.array_type = ArrayType::from_name(name).or(arg.array_type);
+                }
+            }
+            for enumeration in &mut interface.enums {
+                for i in 0..enumeration.entries.len() {
+                    let entry = &enumeration.entries[i];
+                    let literal = match &entry.alias_of {
+                        Some(target) => enumeration.entries.iter()
+                            .find(|e| &e.name == target && e.alias_of.is_none())
+                            .and_then(|e| e.literal.as_ref()),
+                        None => entry.literal.as_ref()
+                    };
+                    let value = literal.and_then(|literal| literal.evaluate(&self.constants).ok());
+                    enumeration.entries[i].value = value.unwrap_or_default();
+                }
+            }
+        }
+    }
+    /// Change the protocol with a [`Transform`](crate::transform::Transform), then [resolve](Self::resolve) it again
+    /// so that the changes are reflected in inherited items and resolved fields.
+    ///
+    /// Transforms apply to the protocol as parsed, before [`generate`](crate::generate) validates it, so the changed
+    /// protocol is checked as if it were written that way.
+    pub fn transform<T: crate::transform::Transform + ?Sized>(&mut self, transform: &mut T) -> &mut Self {
+        crate::transform::walk(self, transform);
+        self.resolve();
+        self
+    }
+    /// Parse a protocol in the given format.
+    pub fn parse(string: &str, format: Format) -> Result<Self> {
+        match format {
+            Format::Toml => Self::from_str(string),
+            #[cfg(feature = "json")]
+            Format::Json => Self::from_json(string),
+            #[cfg(feature = "yaml")]
+            Format::Yaml => Self::from_yaml(string),
+            Format::Xml => Self::from_xml(string)
+        }
+    }
+    /// Parse a protocol from UTF-8 bytes in the given format, such as those embedded with `include_bytes!`.
+    pub fn from_slice(bytes: &[u8], format: Format) -> Result<Self> {
+        Self::parse(&String::from_utf8(bytes.to_vec())?, format)
+    }
+    /// Read a protocol in the given format, such as from standard input.
+    pub fn from_reader<R: Read>(mut reader: R, format: Format) -> Result<Self> {
+        let mut protocol = Vec::new();
+        reader.read_to_end(&mut protocol)?;
+        Self::from_slice(&protocol, format)
+    }
+    /// Load a protocol from a file in the format given by its extension, see [`Format::from_path`].
+    /// 
+    /// A TOML file of several protocols fails with [`Error::MultipleProtocols`], as it is loaded with
+    /// [`load_all`](Self::load_all).
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
         let path = path.as_ref();
-        let mut protocol = String::new();
-        let mut file = File::open(path)?;
-        file.read_to_string(&mut protocol)?;
-        Ok(Self::from_str(&protocol)?)
+        let mut protocol = Self::from_reader(File::open(path)?, Format::from_path(path))?;
+        protocol.source = Some(path.to_owned());
+        Ok(protocol)
+    }
+    /// Load every protocol from a file in the format given by its extension, which for TOML may specify several
+    /// protocols in `[[protocol]]` tables. Files in the other formats specify a single protocol.
+    pub fn load_all<P: AsRef<Path>>(path: P) -> Result<Vec<Self>> {
+        let path = path.as_ref();
+        let mut protocols = match Format::from_path(path) {
+            Format::Toml => Self::from_str_all(&std::fs::read_to_string(path)?)?,
+            _ => vec![Self::load(path)?]
+        };
+        for protocol in &mut protocols {
+            protocol.source = Some(path.to_owned());
+        }
+        Ok(protocols)
+    }
+    /// Record the lines of the names of the items of the protocol, found by parsing its TOML specification again with
+    /// spans, before [resolving](Self::resolve) copies inherited items.
+    fn set_lines(&mut self, string: &str, lines: ProtocolLines) {
+        let line = |name: &Spanned<String>| string[..name.start()].matches('\n').count() + 1;
+        for (interface, lines) in self.interfaces.iter_mut().zip(lines.interfaces) {
+            interface.line = Some(line(&lines.name));
+            for (enumeration, name) in interface.enums.iter_mut().zip(&lines.enums) {
+                enumeration.line = Some(line(&name.name));
+            }
+            for (request, name) in interface.requests.iter_mut().zip(&lines.requests) {
+                request.line = Some(line(&name.name));
+            }
+            for (event, name) in interface.events.iter_mut().zip(&lines.events) {
+                event.line = Some(line(&name.name));
+            }
+        }
+    }
+    /// Forget the [file](Self::source) and [lines](Interface::line) the protocol was loaded from, such as to compare
+    /// the [`Debug`] output of protocols loaded from different files or formats by their meaning alone.
+    pub fn forget_source(&mut self) {
+        self.source = None;
+        for interface in &mut self.interfaces {
+            interface.line = None;
+            interface.enums.iter_mut().for_each(|e| e.line = None);
+            interface.requests.iter_mut().for_each(|r| r.line = None);
+            interface.events.iter_mut().for_each(|e| e.line = None);
+        }
+    }
+    /// The line of the item of a TOML specification at a path, such as the [path](crate::Diagnostic::path) of a
+    /// diagnostic, or of the closest item containing it with a known line, such as the request of an argument.
+    pub fn line_of(&self, path: &str) -> Option<usize> {
+        let mut names = path.split('.');
+        let interface = self.interface(names.next()?)?;
+        let item = names.next().and_then(|name| {
+            interface.requests.iter().find(|r| r.name == name).map(|r| r.line)
+                .or_else(|| interface.events.iter().find(|e| e.name == name).map(|e| e.line))
+                .or_else(|| interface.enums.iter().find(|e| e.name == name).map(|e| e.line))
+        });
+        item.flatten().or(interface.line)
+    }
+    /// Find the enum an argument of `interface` refers to, either as `name` for an enum of the same interface or as
+    /// `interface.name` for an enum of another interface in this protocol.
+    pub fn find_enum<'a>(&'a self, interface: &'a Interface, name: &str) -> Option<(&'a Interface, &'a Enum)> {
+        let (interface, name) = match name.split_once('.') {
+            Some((owner, name)) => (self.interfaces.iter().find(|i| i.name == owner)?, name),
+            None => (interface, name)
+        };
+        interface.enums.iter().find(|e| e.name == name).map(|e| (interface, e))
+    }
+    /// Every argument in the protocol that refers to `enumeration`.
+    pub fn enum_args<'a>(&'a self, enumeration: &'a Enum) -> impl Iterator<Item = &'a Arg> + 'a {
+        self.interfaces.iter().flat_map(move |interface| {
+            interface.requests.iter().flat_map(|r| &r.args)
+                .chain(interface.events.iter().flat_map(|e| &e.args))
+                .filter(move |arg| {
+                    arg.enumeration.as_ref()
+                        .and_then(|name| self.find_enum(interface, name))
+                        .is_some_and(|(_, e)| std::ptr::eq(e, enumeration))
+                })
+        })
+    }
+    /// The stability of an interface, which is that of the protocol unless the interface overrides it.
+    pub fn stability(&self, interface: &Interface) -> Stability {
+        interface.stability.unwrap_or(self.stability)
+    }
+    /// Check if an enum is referred to by `int` arguments, in which case it is backed by an `i32` rather than a `u32`.
+    pub fn is_signed_enum(&self, enumeration: &Enum) -> bool {
+        self.enum_args(enumeration).any(Arg::is_int)
+    }
+    /// Find the interface named `name`.
+    pub fn interface(&self, name: &str) -> Option<&Interface> {
+        self.interfaces.iter().find(|i| i.name == name)
+    }
+    /// Check if `name` is an interface of another protocol listed in [`externals`](Self::externals).
+    pub fn is_external(&self, name: &str) -> bool {
+        self.externals.iter().any(|external| external == name)
+    }
+    /// Every `object` and `new_id` argument in the protocol of the interface named `interface`, in the order of the
+    /// interfaces and of their requests and then events.
+    pub fn references_of<'a>(&'a self, interface: &str) -> Vec<Reference<'a>> {
+        let mut references = Vec::new();
+        for owner in &self.interfaces {
+            let requests = owner.requests_with_opcodes().map(|(opcode, r)| (MessageKind::Request, opcode, r.name.as_str(), &r.args));
+            let events = owner.events_with_opcodes().map(|(opcode, e)| (MessageKind::Event, opcode, e.name.as_str(), &e.args));
+            for (kind, opcode, message, args) in requests.chain(events) {
+                let args = args.iter().filter(|a| a.interface.as_deref() == Some(interface));
+                references.extend(args.map(|arg| Reference { interface: owner, kind, opcode, message, arg }));
+            }
+        }
+        references
     }
 }
 
-#[derive(Clone, Debug, Deserialize)]
+/// An argument that refers to an interface as the interface of its object, found by [`Protocol::references_of`].
+#[derive(Clone, Copy, Debug)]
+pub struct Reference<'a> {
+    /// The interface of the message with the argument.
+    pub interface: &'a Interface,
+    pub kind: MessageKind,
+    /// The opcode of the message, as in the generated code.
+    pub opcode: u16,
+    /// The name of the message.
+    pub message: &'a str,
+    pub arg: &'a Arg
+}
+/// Whether a message is a request or an event.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MessageKind {
+    Request,
+    Event
+}
+
+/// The largest message the wire format allows, in bytes, as the size in the message header is only ever read as such.
+pub const MAX_MESSAGE_SIZE: usize = 4096;
+
+/// The smallest a message with these arguments can be on the wire in bytes, including the 8 byte header.
+pub fn min_message_size(args: &[Arg]) -> usize {
+    8 + args.iter().map(Arg::min_size).sum::<usize>()
+}
+
+/// Copy the items of the interface that an interface [`extends`](Interface::extends) into it, after expanding that
+/// interface in turn. Interfaces that extend one that does not exist, or that extend themselves, are left as is.
+fn expand(interfaces: &mut [Interface], index: usize, expanded: &mut [bool]) {
+    if expanded[index] {
+        return
+    }
+    expanded[index] = true;
+    let Some(parent) = interfaces[index].extends.as_ref()
+        .and_then(|name| interfaces.iter().position(|i| &i.name == name))
+        .filter(|&parent| parent != index)
+    else {
+        return
+    };
+    // Marking the interface as expanded first stops at a cycle, which is left for `validate` to report
+    expand(interfaces, parent, expanded);
+    let parent = interfaces[parent].clone();
+    let child = &mut interfaces[index];
+    inherit(&mut child.enums, parent.enums, |e| &e.name, |e| e.inherited = true);
+    inherit(&mut child.requests, parent.requests, |r| &r.name, |r| r.inherited = true);
+    inherit(&mut child.events, parent.events, |e| &e.name, |e| e.inherited = true);
+}
+
+/// Put the items of a parent interface before the items of an interface, so that the items keep their opcodes, with
+/// items of the interface replacing parent items of the same name in place.
+fn inherit<T>(items: &mut Vec<T>, parent: Vec<T>, name: fn(&T) -> &str, mark: fn(&mut T)) {
+    let mut own = std::mem::replace(items, parent);
+    for item in items.iter_mut() {
+        mark(item);
+    }
+    for item in own.drain(..) {
+        match items.iter().position(|i| name(i) == name(&item)) {
+            Some(position) => items[position] = item,
+            None => items.push(item)
+        }
+    }
+}
+
+/// The signature of a message as libwayland writes it, such as `2?oui`, with the version the message was added in if
+/// after the first, then a character for each argument's type preceded by `?` if it is nullable.
+pub fn signature(since: Option<u32>, args: &[Arg]) -> String {
+    let mut signature = since.filter(|&since| since > 1).map(|since| since.to_string()).unwrap_or_default();
+    for arg in args {
+        if arg.nullable {
+            signature.push('?');
+        }
+        signature += match arg.ty {
+            DataType::Int => "i",
+            DataType::Uint => "u",
+            DataType::Fixed => "f",
+            DataType::String => "s",
+            DataType::Object => "o",
+            // Without an interface the interface name and version are sent before the id
+            DataType::NewId if arg.interface.is_none() => "sun",
+            DataType::NewId => "n",
+            DataType::Array => "a",
+            DataType::Fd => "h"
+        };
+    }
+    signature
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+/// A field with a fixed set of valid values.
+/// 
+/// These fields are read as the name they are written as and resolved by [`Protocol::resolve`], so that unknown names
+/// can be reported by [`validate`](crate::validate) with suggestions rather than failing to parse.
+pub trait Vocabulary: Copy + PartialEq + 'static {
+    /// What the field describes, such as `type`.
+    const FIELD: &'static str;
+    /// Every valid value with the names it can be written as, the first of which is canonical.
+    const VALUES: &'static [(&'static str, Self)];
+    fn from_name(name: &str) -> Option<Self> {
+        Self::VALUES.iter().find(|(n, _)| *n == name).map(|(_, value)| *value)
+    }
+    fn name(&self) -> &'static str {
+        Self::VALUES.iter().find(|(_, value)| value == self).map_or("", |(name, _)| name)
+    }
+}
+/// Set `value` from its name, if the name is valid.
+fn resolve_name<T: Vocabulary>(value: &mut T, name: Option<&str>) {
+    if let Some(resolved) = name.and_then(T::from_name) {
+        *value = resolved
+    }
+}
+
+/// How much a protocol or interface may change, following the categories used by `wayland-protocols`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Stability {
+    #[default]
+    Stable,
+    /// Ready for wider use, but may still change before being declared stable.
+    Staging,
+    /// Experimental, and backward incompatible changes are made by releasing a new major version under a new name.
+    Unstable
+}
+impl Vocabulary for Stability {
+    const FIELD: &'static str = "stability";
+    const VALUES: &'static [(&'static str, Self)] = &[
+        ("stable", Self::Stable),
+        ("staging", Self::Staging),
+        ("unstable", Self::Unstable)
+    ];
+}
+impl Stability {
+    /// A warning describing what the stability means for users of `item`, if it is not stable.
+    pub fn warning(&self, item: &str) -> Option<String> {
+        match self {
+            Self::Stable => None,
+            Self::Staging => Some(format!(
+                "<div class=\"warning\">This {} is in staging and may change before it is declared stable.</div>",
+                item
+            )),
+            Self::Unstable => Some(format!(
+                "<div class=\"warning\">This {} is unstable. Backward incompatible changes are made under a new name, such as a new <code>_vN</code> suffix.</div>",
+                item
+            ))
+        }
+    }
+}
+
+/// A named value that is not part of any enum, such as a maximum length.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Constant {
+    pub name: String,
+    pub summary: Option<String>,
+    #[serde(default, deserialize_with = "description")]
+    pub description: Option<String>,
+    /// The type as written, which is resolved to [`ty`](Self::ty).
+    #[serde(rename = "type")]
+    pub type_name: String,
+    #[serde(skip)]
+    pub ty: ConstType,
+    pub value: ConstValue,
+    /// Keys that are not part of the format, see [`Protocol::extensions`].
+    #[serde(flatten)]
+    pub extensions: BTreeMap<String, toml::Value>
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ConstType {
+    Int,
+    #[default]
+    Uint,
+    String
+}
+impl Vocabulary for ConstType {
+    const FIELD: &'static str = "type";
+    const VALUES: &'static [(&'static str, Self)] = &[
+        ("int", Self::Int),
+        ("i32", Self::Int),
+        ("uint", Self::Uint),
+        ("u32", Self::Uint),
+        ("string", Self::String)
+    ];
+}
+impl ConstType {
+    pub fn ty(&self) -> TokenStream {
+        match self {
+            Self::Int => quote!{::core::primitive::i32},
+            Self::Uint => quote!{::core::primitive::u32},
+            Self::String => quote!{&::core::primitive::str}
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum ConstValue {
+    Integer(i64),
+    String(String)
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Interface {
     pub name: String,
     pub summary: Option<String>,
+    #[serde(default, deserialize_with = "description")]
     pub description: Option<String>,
     pub version: u32,
-    #[serde(rename = "enum", default)]
+    /// The interface of the protocol whose enums, requests and events are copied into this interface by
+    /// [`resolve`](Protocol::resolve), ahead of those of this interface, which replace any with the same name.
+    pub extends: Option<String>,
+    /// The stability as written, which is resolved to [`stability`](Self::stability).
+    #[serde(rename = "stability")]
+    pub stability_name: Option<String>,
+    /// Overrides the stability of the protocol for this interface.
+    #[serde(skip)]
+    pub stability: Option<Stability>,
+    /// Marks the interface as deprecated, such as once a stable interface replaces it.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub deprecated: bool,
+    /// The interface that replaces a deprecated interface, which may be from another protocol.
+    pub replaced_by: Option<String>,
+    /// Why the interface is deprecated or what to use instead, which defaults to using the replacement.
+    pub deprecation_note: Option<String>,
+    /// The capability a client needs for requests to the interface to be dispatched, such as `screencopy` for a
+    /// privileged interface.
+    pub capability: Option<String>,
+    #[serde(rename = "enum", alias = "enums", default, skip_serializing_if = "Vec::is_empty")]
     pub enums: Vec<Enum>,
-    #[serde(rename = "request", default)]
+    #[serde(rename = "request", alias = "requests", default, skip_serializing_if = "Vec::is_empty")]
     pub requests: Vec<Request>,
-    #[serde(rename = "event", default)]
-    pub events: Vec<Event>
+    #[serde(rename = "event", alias = "events", default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<Event>,
+    /// The line the name of the item is on in a TOML specification, see [`Protocol::source`].
+    #[serde(skip)]
+    pub line: Option<usize>,
+    /// Keys that are not part of the format, see [`Protocol::extensions`].
+    #[serde(flatten)]
+    pub extensions: BTreeMap<String, toml::Value>
 }
 
-#[derive(Clone, Debug, Deserialize)]
+impl Interface {
+    /// The requests with their opcodes, which are their positions in the interface, as in the generated code.
+    pub fn requests_with_opcodes(&self) -> impl Iterator<Item = (u16, &Request)> {
+        self.requests.iter().enumerate().map(|(opcode, r)| (opcode_of(opcode), r))
+    }
+    /// The events with their opcodes, which are their positions in the interface, as in the generated code.
+    pub fn events_with_opcodes(&self) -> impl Iterator<Item = (u16, &Event)> {
+        self.events.iter().enumerate().map(|(opcode, e)| (opcode_of(opcode), e))
+    }
+    /// Find the request named `name`, along with its opcode.
+    pub fn request(&self, name: &str) -> Option<(u16, &Request)> {
+        self.requests_with_opcodes().find(|(_, r)| r.name == name)
+    }
+    /// Find the event named `name`, along with its opcode.
+    pub fn event(&self, name: &str) -> Option<(u16, &Event)> {
+        self.events_with_opcodes().find(|(_, e)| e.name == name)
+    }
+}
+/// Opcodes are 16 bits on the wire, which the generated code relies on, so more messages than that is a bug.
+fn opcode_of(index: usize) -> u16 {
+    index.try_into().expect("more messages than there are opcodes")
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Enum {
     pub name: String,
     pub summary: Option<String>,
+    #[serde(default, deserialize_with = "description")]
     pub description: Option<String>,
     pub since: Option<u32>,
-    #[serde(rename = "entry", default)]
-    pub entries: Vec<Entry>
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub bitfield: bool,
+    /// The entry returned by `Default`, rather than the entry with a value of 0.
+    pub default_entry: Option<String>,
+    /// Whether the enum was copied from the interface that its interface [`extends`](Interface::extends).
+    #[serde(skip)]
+    pub inherited: bool,
+    /// The line the name of the item is on in a TOML specification, see [`Protocol::source`].
+    #[serde(skip)]
+    pub line: Option<usize>,
+    #[serde(rename = "entry", alias = "entries", default, skip_serializing_if = "Vec::is_empty")]
+    pub entries: Vec<Entry>,
+    /// Keys that are not part of the format, see [`Protocol::extensions`].
+    #[serde(flatten)]
+    pub extensions: BTreeMap<String, toml::Value>
 }
-#[derive(Clone, Debug, Deserialize)]
+impl Enum {
+    /// Check if an entry is an alias of another entry, either explicitly or by sharing the value of an earlier entry.
+    pub fn is_alias(&self, entry: &Entry) -> bool {
+        entry.alias_of.is_some() || self.entries.iter()
+            .take_while(|e| !std::ptr::eq(*e, entry))
+            .any(|e| e.alias_of.is_none() && e.value == entry.value)
+    }
+    /// Check if any entry is an alias of another entry.
+    pub fn has_aliases(&self) -> bool {
+        self.entries.iter().any(|entry| self.is_alias(entry))
+    }
+    /// The entry that `entry` is an alias of, or `entry` itself if it is not an alias.
+    pub fn canonical<'a>(&'a self, entry: &'a Entry) -> &'a Entry {
+        self.entries.iter().find(|e| !self.is_alias(e) && e.value == entry.value).unwrap_or(entry)
+    }
+    /// The entry to use as the default value, either named by `default_entry` or the first entry with a value of 0.
+    pub fn default_entry(&self) -> Option<&Entry> {
+        match &self.default_entry {
+            Some(name) => self.entries.iter().find(|e| &e.name == name),
+            None => self.entries.iter().find(|e| e.value == 0)
+        }
+    }
+    /// Check if the enum can be generated as a Rust enum.
+    pub fn is_rust_enum(&self, options: &CodegenOptions) -> bool {
+        options.rust_enums && !self.bitfield && !self.has_aliases()
+    }
+}
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Request {
     pub name: String,
     pub since: Option<u32>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_false")]
     pub destructor: bool,
+    /// Marks a request that implementations do not support, which fails with a protocol error unless implemented.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub unimplemented: bool,
+    /// The entry of the `error` enum of the interface that an unimplemented request fails with, rather than the
+    /// invalid method error of `wl_display`.
+    pub unimplemented_error: Option<String>,
+    /// The capability a client needs for the request to be dispatched, in place of that of its interface.
+    pub capability: Option<String>,
     pub summary: Option<String>,
+    #[serde(default, deserialize_with = "description")]
     pub description: Option<String>,
-    #[serde(rename = "arg", default)]
-    pub args: Vec<Arg>
+    #[serde(rename = "arg", alias = "args", default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<Arg>,
+    /// Whether the request was copied from the interface that its interface [`extends`](Interface::extends).
+    #[serde(skip)]
+    pub inherited: bool,
+    /// The line the name of the item is on in a TOML specification, see [`Protocol::source`].
+    #[serde(skip)]
+    pub line: Option<usize>,
+    /// Keys that are not part of the format, see [`Protocol::extensions`].
+    #[serde(flatten)]
+    pub extensions: BTreeMap<String, toml::Value>
 }
-#[derive(Clone, Debug, Deserialize)]
+impl Request {
+    /// The capability a client needs for the request to be dispatched, which is that of the request or otherwise
+    /// that of its interface.
+    pub fn capability<'a>(&'a self, interface: &'a Interface) -> Option<&'a str> {
+        self.capability.as_deref().or(interface.capability.as_deref())
+    }
+}
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Event {
     pub name: String,
     pub since: Option<u32>,
+    /// Marks an event that destroys the object it is sent from, such as `wl_callback.done`. The generated senders
+    /// remove the object from the client once the event is sent.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub destructor: bool,
     pub summary: Option<String>,
+    #[serde(default, deserialize_with = "description")]
     pub description: Option<String>,
-    #[serde(rename = "arg", default)]
-    pub args: Vec<Arg>
+    #[serde(rename = "arg", alias = "args", default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<Arg>,
+    /// Whether the event was copied from the interface that its interface [`extends`](Interface::extends).
+    #[serde(skip)]
+    pub inherited: bool,
+    /// The line the name of the item is on in a TOML specification, see [`Protocol::source`].
+    #[serde(skip)]
+    pub line: Option<usize>,
+    /// Keys that are not part of the format, see [`Protocol::extensions`].
+    #[serde(flatten)]
+    pub extensions: BTreeMap<String, toml::Value>
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Entry {
     pub name: String,
     pub since: Option<u32>,
     pub summary: Option<String>,
+    #[serde(default, deserialize_with = "description")]
     pub description: Option<String>,
-    pub value: u32
+    /// The value as written in the specification, which is absent for aliases.
+    #[serde(rename = "value", default)]
+    pub literal: Option<EntryValue>,
+    /// The value of the entry once [resolved](Protocol::resolve).
+    #[serde(skip)]
+    pub value: i64,
+    /// The name of another entry this entry is an alias of, instead of specifying a value.
+    pub alias_of: Option<String>,
+    /// Marks an entry of a bitfield as a combination of other flags, exempting it from being a single bit.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub combination: bool,
+    /// Keys that are not part of the format, see [`Protocol::extensions`].
+    #[serde(flatten)]
+    pub extensions: BTreeMap<String, toml::Value>
+}
+
+/// The value of an entry as written in the specification.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum EntryValue {
+    Integer(i64),
+    /// An integer constant of the protocol written as `@name`, optionally followed by `+`, `-` or `<<` and a number,
+    /// such as `@max_name_len + 1`, so that values shared with constants are only written once.
+    Expression(String)
+}
+impl EntryValue {
+    /// The value, evaluating an expression with the constants of the protocol, or why the expression is invalid.
+    pub fn evaluate(&self, constants: &[Constant]) -> std::result::Result<i64, String> {
+        let expression = match self {
+            Self::Integer(value) => return Ok(*value),
+            Self::Expression(expression) => expression.trim()
+        };
+        let rest = expression.strip_prefix('@')
+            .ok_or_else(|| format!("`{}` is not a number or a constant written as `@name`", expression))?;
+        let end = rest.find(|c: char| !c.is_alphanumeric() && c != '_').unwrap_or(rest.len());
+        let (name, rest) = rest.split_at(end);
+        let constant = constants.iter()
+            .find(|c| c.name == name || ident::shouty_snake_case(&c.name) == name)
+            .ok_or_else(|| format!("constant `{}` does not exist", name))?;
+        let ConstValue::Integer(value) = constant.value else {
+            return Err(format!("constant `{}` is not an integer", name))
+        };
+        let rest = rest.trim_start();
+        if rest.is_empty() {
+            return Ok(value)
+        }
+        let (operator, operand) = ["+", "-", "<<"].iter()
+            .find_map(|operator| rest.strip_prefix(operator).map(|operand| (*operator, operand.trim())))
+            .ok_or_else(|| format!("`{}` is not `+`, `-` or `<<` followed by a number", rest))?;
+        let operand = match operand.strip_prefix("0x") {
+            Some(hex) => i64::from_str_radix(hex, 16),
+            None => operand.parse()
+        }.map_err(|_| format!("`{}` is not a number", operand))?;
+        match operator {
+            "+" => value.checked_add(operand),
+            "-" => value.checked_sub(operand),
+            _ => u32::try_from(operand).ok()
+                .and_then(|shift| value.checked_shl(shift))
+                .filter(|shifted| shifted >> operand == value)
+        }.ok_or_else(|| format!("`{}` overflows", expression))
+    }
+}
+impl fmt::Display for EntryValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Integer(value) => write!(f, "{}", value),
+            Self::Expression(expression) => write!(f, "{}", expression)
+        }
+    }
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -91,49 +850,269 @@ pub enum RequestType {
     Destructor
 }
 
-#[derive(Clone, Debug, Deserialize)]
+/// An argument of a message, written either as a table or as a string in the shorthand of
+/// [`from_shorthand`](Self::from_shorthand).
+#[derive(Clone, Debug, Deserialize, Serialize)]
+// The derived implementations are inherent functions, which the trait implementations use for the table form
+#[serde(remote = "Self")]
 pub struct Arg {
     pub name: String,
-    #[serde(rename = "allow-null", default)]
+    /// Whether the argument may be null, which is only representable for `string` and `object` arguments.
+    #[serde(rename = "allow-null", default, skip_serializing_if = "is_false")]
     pub nullable: bool,
+    /// The type as written, which is resolved to [`ty`](Self::ty).
     #[serde(rename = "type")]
+    pub type_name: String,
+    #[serde(skip)]
     pub ty: DataType,
     pub interface: Option<String>,
     #[serde(rename = "enum")]
     pub enumeration: Option<String>,
-    pub summary: Option<String>
+    /// The element type as written, which is resolved to [`array_type`](Self::array_type).
+    #[serde(rename = "array_type")]
+    pub array_type_name: Option<String>,
+    /// The type of the elements of an array, which are otherwise bytes.
+    #[serde(skip)]
+    pub array_type: Option<ArrayType>,
+    pub summary: Option<String>,
+    /// Keys that are not part of the format, see [`Protocol::extensions`].
+    #[serde(flatten)]
+    pub extensions: BTreeMap<String, toml::Value>
+}
+/// Deserialize a description written as a string, or as an array of paragraphs that are joined with blank lines.
+///
+/// Joining the paragraphs gives the same description as writing them in one string separated by blank lines, so both
+/// are documented the same.
+fn description<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Option<String>, D::Error> {
+    struct DescriptionVisitor;
+    impl<'de> Visitor<'de> for DescriptionVisitor {
+        type Value = String;
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a description as a string or as an array of paragraphs")
+        }
+        fn visit_str<E: de::Error>(self, description: &str) -> std::result::Result<String, E> {
+            Ok(description.to_owned())
+        }
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<String, A::Error> {
+            let mut paragraphs = Vec::new();
+            while let Some(paragraph) = seq.next_element::<String>()? {
+                paragraphs.push(paragraph);
+            }
+            Ok(paragraphs.join("\n\n"))
+        }
+    }
+    deserializer.deserialize_any(DescriptionVisitor).map(Some)
+}
+impl<'de> Deserialize<'de> for Arg {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct ArgVisitor;
+        impl<'de> Visitor<'de> for ArgVisitor {
+            type Value = Arg;
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an argument as a table or as a string such as \"x:int\"")
+            }
+            fn visit_str<E: de::Error>(self, shorthand: &str) -> std::result::Result<Arg, E> {
+                Arg::from_shorthand(shorthand).map_err(E::custom)
+            }
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> std::result::Result<Arg, A::Error> {
+                Arg::deserialize(de::value::MapAccessDeserializer::new(map))
+            }
+        }
+        deserializer.deserialize_any(ArgVisitor)
+    }
+}
+impl Serialize for Arg {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        Arg::serialize(self, serializer)
+    }
 }
 impl Arg {
-    pub fn getter(&self, stream: &Ident) -> TokenStream {
+    /// Parse an argument written as `name:type`, such as `x:int`, where the type may be preceded by `?` to allow
+    /// null and followed by an interface or enum in angle brackets, such as `buffer:?object<wl_buffer>`. The
+    /// brackets name the interface of `object` and `new_id` arguments, and the enum of any other argument.
+    /// 
+    /// The error quotes the shorthand and describes what is wrong with it.
+    pub fn from_shorthand(shorthand: &str) -> std::result::Result<Self, String> {
+        let problem = |problem: &str| format!("argument shorthand {:?} {}", shorthand, problem);
+        let (name, ty) = shorthand.split_once(':')
+            .filter(|(name, ty)| !name.trim().is_empty() && !ty.trim().is_empty())
+            .ok_or_else(|| problem("is not written as `name:type`"))?;
+        let ty = ty.trim();
+        let (nullable, ty) = match ty.strip_prefix('?') {
+            Some(ty) => (true, ty),
+            None => (false, ty)
+        };
+        let (type_name, target) = match ty.split_once('<') {
+            Some((type_name, target)) => {
+                let target = target.strip_suffix('>')
+                    .filter(|target| !target.trim().is_empty() && !target.contains(['<', '>']))
+                    .ok_or_else(|| problem("has a malformed `<...>`, which must name one interface or enum"))?;
+                (type_name, Some(target.trim().to_owned()))
+            },
+            None if ty.contains('>') => return Err(problem("has a `>` without a matching `<`")),
+            None => (ty, None)
+        };
+        let type_name = type_name.trim();
+        if type_name.is_empty() || type_name.contains(char::is_whitespace) {
+            return Err(problem("does not have a type after the `:`"))
+        }
+        let (interface, enumeration) = match type_name {
+            "object" | "new_id" => (target, None),
+            _ => (None, target)
+        };
+        Ok(Self {
+            name: name.trim().to_owned(),
+            nullable,
+            type_name: type_name.to_owned(),
+            ty: DataType::default(),
+            interface,
+            enumeration,
+            array_type_name: None,
+            array_type: None,
+            summary: None,
+            extensions: BTreeMap::new()
+        })
+    }
+    /// The smallest the argument can be on the wire in bytes, which is with empty strings and arrays.
+    pub fn min_size(&self) -> usize {
+        match self.ty {
+            DataType::Fd => 0,
+            DataType::String if !self.nullable => 8,
+            DataType::NewId if self.interface.is_none() => 16,
+            _ => 4
+        }
+    }
+    /// An expression for the size of the argument beyond [`min_size`](Self::min_size) in bytes, for arguments that
+    /// vary in size.
+    pub fn extra_size(&self) -> Option<TokenStream> {
+        let ident = ident::snake(&self.name);
+        match (self.ty, self.array_type) {
+            (DataType::String, _) if self.nullable => Some(quote!{#ident.map_or(0, |s| (s.len() + 4) & !3)}),
+            (DataType::String, _) => Some(quote!{((#ident.len() + 4) & !3) - 4}),
+            (DataType::Array, Some(_)) => Some(quote!{#ident.len() * 4}),
+            (DataType::Array, None) => Some(quote!{(#ident.len() + 3) & !3}),
+            (DataType::NewId, _) if self.interface.is_none() => Some(quote!{((#ident.interface().len() + 4) & !3) - 4}),
+            _ => None
+        }
+    }
+    /// Check if the argument, or the elements of an array argument, are signed integers.
+    pub fn is_int(&self) -> bool {
+        matches!(self.ty, DataType::Int) || matches!(self.array_type, Some(ArrayType::Int))
+    }
+    /// Check if the argument, or the elements of an array argument, are unsigned integers.
+    pub fn is_uint(&self) -> bool {
+        matches!(self.ty, DataType::Uint) || matches!(self.array_type, Some(ArrayType::Uint))
+    }
+    /// The type of the elements of a typed array, using the enum type at `enumeration` if the argument has one.
+    fn element_ty(&self, array_type: ArrayType, enumeration: Option<&(TokenStream, bool)>, options: &CodegenOptions) -> TokenStream {
+        match (array_type, enumeration) {
+            (ArrayType::Int | ArrayType::Uint, Some((path, _))) => path.clone(),
+            (ArrayType::Int, None) => quote!{::core::primitive::i32},
+            (ArrayType::Uint, None) => quote!{::core::primitive::u32},
+            (ArrayType::Fixed, _) => fixed_ty(options)
+        }
+    }
+    /// Read a typed array element by element, converting integers to the enum type at `enumeration` if the argument
+    /// has one.
+    fn array_getter(&self, stream: &Ident, array_type: ArrayType, enumeration: Option<&(TokenStream, bool)>, context: &str, options: &CodegenOptions) -> TokenStream {
+        let ty = self.element_ty(array_type, enumeration, options);
+        let unknown = wl_error("CORRUPT", context, &format!("argument `{}` has an element that is not an entry of its enum", self.name));
+        let misaligned = format!("{}: argument `{}` has a length that is not a multiple of 4", context, self.name);
+        let element = match (array_type, enumeration) {
+            (ArrayType::Int | ArrayType::Uint, Some((_, signed))) => {
+                let raw = match (array_type, signed) {
+                    (ArrayType::Int, true) => quote!{#stream.i32()?},
+                    (ArrayType::Int, false) => quote!{#stream.i32()? as ::core::primitive::u32},
+                    (_, true) => quote!{#stream.u32()? as ::core::primitive::i32},
+                    (_, false) => quote!{#stream.u32()?}
+                };
+                quote!{{
+                    // Converting to a newtype enum cannot fail, unlike to a Rust enum, but both are converted the same way
+                    #[allow(clippy::unnecessary_fallible_conversions)]
+                    let element = ::core::convert::TryFrom::try_from(#raw).map_err(|_| #unknown);
+                    element
+                }}
+            },
+            (ArrayType::Int, None) => quote!{#stream.i32()},
+            (ArrayType::Uint, None) => quote!{#stream.u32()},
+            (ArrayType::Fixed, _) if options.fixed_as_f64 => {
+                let fixed = fixed_getter(stream, options);
+                quote!{::core::result::Result::Ok(#fixed)}
+            },
+            (ArrayType::Fixed, _) => quote!{#stream.fixed()}
+        };
+        quote!{#stream.array::<#ty>(#misaligned, |#stream| #element)?}
+    }
+    /// Send a typed array element by element, converting enums at `enumeration` to integers.
+    fn array_sender(&self, stream: &Ident, array_type: ArrayType, enumeration: Option<&(TokenStream, bool)>, context: &str, options: &CodegenOptions) -> TokenStream {
+        let ident = ident::snake(&self.name);
+        let too_long = wl_error("INTERNAL", context, &format!("argument `{}` is too long to send", self.name));
+        let send = match (array_type, enumeration) {
+            (ArrayType::Int | ArrayType::Uint, Some((path, signed))) => {
+                let raw = quote!{#path::to_raw(*element)};
+                match (array_type, signed) {
+                    (ArrayType::Int, true) => quote!{#stream.send_i32(#raw)?},
+                    (ArrayType::Int, false) => quote!{#stream.send_i32(#raw as ::core::primitive::i32)?},
+                    (_, true) => quote!{#stream.send_u32(#raw as ::core::primitive::u32)?},
+                    (_, false) => quote!{#stream.send_u32(#raw)?}
+                }
+            },
+            (ArrayType::Int, None) => quote!{#stream.send_i32(*element)?},
+            (ArrayType::Uint, None) => quote!{#stream.send_u32(*element)?},
+            (ArrayType::Fixed, _) if options.fixed_as_f64 => fixed_sender(stream, quote!{*element}, options),
+            (ArrayType::Fixed, _) => fixed_sender(stream, quote!{element}, options)
+        };
+        quote!{
+            #stream.send_u32(::core::convert::TryInto::try_into(#ident.len() * 4).map_err(|_| #too_long)?)?;
+            for element in #ident {
+                #send;
+            }
+        }
+    }
+    /// Read the argument from the stream, with errors described as occurring in `context`, such as
+    /// `wl_surface.attach`.
+    ///
+    /// Arguments that can fail to read other than by the stream failing are read by the methods of the `ReadArgs`
+    /// trait that [`generate`](crate::generate) defines for the stream, which must be in scope.
+    pub fn getter(&self, stream: &Ident, enumeration: Option<&(TokenStream, bool)>, context: &str, options: &CodegenOptions) -> TokenStream {
+        if let (DataType::Array, Some(array_type)) = (self.ty, self.array_type) {
+            return self.array_getter(stream, array_type, enumeration, context, options)
+        }
+        let null = format!("{}: argument `{}` is null but is not nullable", context, self.name);
         match self.ty {
             DataType::Int => quote!{#stream.i32()?},
             DataType::Uint => quote!{#stream.u32()?},
-            DataType::Fixed => quote!{#stream.fixed()?},
+            DataType::Fixed => fixed_getter(stream, options),
             DataType::String => if self.nullable {
                 quote!{#stream.string()?}
             } else {
-                quote!{#stream.string()?.ok_or(::yutani::wire::WlError::NON_NULLABLE)?}
+                quote!{#stream.string_required(#null)?}
             },
             DataType::Array => quote!{#stream.bytes()?},
             DataType::Fd => quote!{#stream.file()?},
             DataType::Object => if self.nullable {
                 quote!{#stream.object()?}
             } else {
-                quote!{#stream.object()?.ok_or(::yutani::wire::WlError::NON_NULLABLE)?}
+                quote!{#stream.object_required(#null)?}
             },
-            DataType::NewId => if let Some(_) = self.interface.as_ref() {
-                quote!{#stream.object()?.ok_or(::yutani::wire::WlError::NON_NULLABLE)?}
+            DataType::NewId => if self.interface.is_some() {
+                quote!{#stream.object_required(#null)?}
             } else {
                 quote!{#stream.new_id()?}
             }
         }
     }
-    pub fn sender(&self, stream: &Ident) -> TokenStream {
-        let ident = Ident::new_raw(&self.name.to_snake_case(), Span::call_site());
+    /// Write the argument to the stream, with errors described as occurring in `context`, such as
+    /// `wl_surface.enter`.
+    pub fn sender(&self, stream: &Ident, enumeration: Option<&(TokenStream, bool)>, context: &str, options: &CodegenOptions) -> TokenStream {
+        if let (DataType::Array, Some(array_type)) = (self.ty, self.array_type) {
+            return self.array_sender(stream, array_type, enumeration, context, options)
+        }
+        let ident = ident::snake(&self.name);
         match self.ty {
             DataType::Int => quote!{#stream.send_i32(#ident)?},
             DataType::Uint => quote!{#stream.send_u32(#ident)?},
-            DataType::Fixed => quote!{#stream.send_fixed(#ident)?},
+            DataType::Fixed => fixed_sender(stream, quote!{#ident}, options),
             DataType::String => if self.nullable {
                 quote!{#stream.send_string(#ident)?}
             } else {
@@ -146,18 +1125,131 @@ impl Arg {
             } else {
                 quote!{#stream.send_object(Some(#ident))?}
             },
-            DataType::NewId => if let Some(_) = self.interface.as_ref() {
+            DataType::NewId => if self.interface.is_some() {
                 quote!{#stream.send_object(Some(#ident))?}
             } else {
                 quote!{#stream.send_new_id(#ident)?}
             }
         }
     }
-    pub fn ty(&self) -> TokenStream {
+    /// Append the words of the argument to the `Vec<u32>` at `payload`, as [`sender`](Self::sender) writes them to a
+    /// stream, so that they can be written for many messages.
+    ///
+    /// File descriptors are not part of the words of a message, and fixed point numbers cannot be converted to words
+    /// unless exposed as `f64`, so have no payload.
+    pub fn payload(&self, payload: &Ident, enumeration: Option<&(TokenStream, bool)>, context: &str, options: &CodegenOptions) -> Option<TokenStream> {
+        let ident = ident::snake(&self.name);
+        if let (DataType::Array, Some(array_type)) = (self.ty, self.array_type) {
+            let too_long = wl_error("INTERNAL", context, &format!("argument `{}` is too long to send", self.name));
+            let word = match (array_type, enumeration) {
+                (ArrayType::Int | ArrayType::Uint, Some((path, true))) => quote!{#path::to_raw(*element) as ::core::primitive::u32},
+                (ArrayType::Int | ArrayType::Uint, Some((path, false))) => quote!{#path::to_raw(*element)},
+                (ArrayType::Int, None) => quote!{*element as ::core::primitive::u32},
+                (ArrayType::Uint, None) => return Some(quote!{
+                    #payload.push(::core::convert::TryInto::try_into(#ident.len() * 4).map_err(|_| #too_long)?);
+                    #payload.extend_from_slice(#ident);
+                }),
+                (ArrayType::Fixed, _) if options.fixed_as_f64 => quote!{(*element * 256.0).round() as ::core::primitive::i32 as ::core::primitive::u32},
+                (ArrayType::Fixed, _) => return None
+            };
+            return Some(quote!{
+                #payload.push(::core::convert::TryInto::try_into(#ident.len() * 4).map_err(|_| #too_long)?);
+                #payload.extend(#ident.iter().map(|element| #word));
+            })
+        }
+        Some(match self.ty {
+            DataType::Int => quote!{#payload.push(#ident as ::core::primitive::u32);},
+            DataType::Uint => quote!{#payload.push(#ident);},
+            DataType::Fixed if options.fixed_as_f64 => quote!{#payload.push((#ident * 256.0).round() as ::core::primitive::i32 as ::core::primitive::u32);},
+            DataType::Fixed | DataType::Fd => return None,
+            DataType::String => if self.nullable {
+                quote!{WriteArgs::string(&mut #payload, #ident);}
+            } else {
+                quote!{WriteArgs::string(&mut #payload, ::core::option::Option::Some(#ident));}
+            },
+            DataType::Array => quote!{WriteArgs::bytes(&mut #payload, #ident);},
+            DataType::Object => if self.nullable {
+                quote!{#payload.push(#ident.map_or(0, ::core::convert::Into::into));}
+            } else {
+                quote!{#payload.push(#ident.into());}
+            },
+            DataType::NewId => if self.interface.is_some() {
+                quote!{#payload.push(#ident.into());}
+            } else {
+                quote!{
+                    WriteArgs::string(&mut #payload, ::core::option::Option::Some(#ident.interface()));
+                    #payload.push(#ident.version());
+                    #payload.push(#ident.id().into());
+                }
+            }
+        })
+    }
+    /// An expression converting the decoded argument to the backing type of its enum.
+    pub fn enum_value(&self, signed: bool) -> TokenStream {
+        let ident = ident::snake(&self.name);
+        match (self.ty, signed) {
+            (DataType::Uint, true) => quote!{#ident as ::core::primitive::i32},
+            (DataType::Int, false) => quote!{#ident as ::core::primitive::u32},
+            _ => quote!{#ident}
+        }
+    }
+    /// The template formatting the decoded argument for debug output, such as `{:?}`, and the arguments to it,
+    /// rendering enums with the enum type at `enumeration`, which is signed if backed by an `i32`. Arguments that the
+    /// template can format are passed as they are rather than formatted into a string first.
+    pub fn debug(&self, enumeration: Option<(TokenStream, bool)>) -> (&'static str, TokenStream) {
+        let ident = ident::snake(&self.name);
+        match self.ty {
+            DataType::Int | DataType::Uint => match enumeration {
+                Some((path, signed)) => {
+                    let raw = self.enum_value(signed);
+                    ("{}", quote!{
+                        match #path::try_from_raw(#raw) {
+                            ::core::result::Result::Ok(value) => ::std::format!("{:?}", value),
+                            ::core::result::Result::Err(error) => ::std::format!("UNKNOWN({})", error.value)
+                        }
+                    })
+                },
+                None => ("{}", quote!{#ident})
+            },
+            DataType::Fixed => ("{:?}", quote!{#ident}),
+            DataType::String => if self.nullable {
+                ("{}", quote!{#ident.as_ref().map_or_else(|| ::std::string::String::from("null"), |s| ::std::format!("{:?}", s))})
+            } else {
+                ("{:?}", quote!{#ident})
+            },
+            DataType::Array => {
+                let unit = if self.array_type.is_some() { "elements" } else { "bytes" };
+                let template = format!("{{:?}}.. ({{}} {})", unit);
+                ("{}", quote!{
+                    if #ident.len() > DEBUG_ARRAY_LIMIT {
+                        ::std::format!(#template, &#ident[..DEBUG_ARRAY_LIMIT], #ident.len())
+                    } else {
+                        ::std::format!("{:?}", #ident)
+                    }
+                })
+            },
+            DataType::Fd => ("fd {:?}", quote!{#ident}),
+            DataType::Object => if self.nullable {
+                ("{}", quote!{#ident.map_or_else(|| ::std::string::String::from("null"), |id| ::std::format!("{}", id))})
+            } else {
+                ("{}", quote!{#ident})
+            },
+            DataType::NewId => if self.interface.is_some() {
+                ("new id {}", quote!{#ident})
+            } else {
+                ("new id {}@{} (version {})", quote!{#ident.interface(), #ident.id(), #ident.version()})
+            }
+        }
+    }
+    pub fn ty(&self, enumeration: Option<&(TokenStream, bool)>, options: &CodegenOptions) -> TokenStream {
+        if let (DataType::Array, Some(array_type)) = (self.ty, self.array_type) {
+            let ty = self.element_ty(array_type, enumeration, options);
+            return quote!{::std::vec::Vec<#ty>}
+        }
         match self.ty {
             DataType::Int => quote!{::core::primitive::i32},
             DataType::Uint => quote!{::core::primitive::u32},
-            DataType::Fixed => quote!{::yutani::Fixed},
+            DataType::Fixed => fixed_ty(options),
             DataType::String => if self.nullable {
                 quote!{::core::option::Option<::std::string::String>}
             } else {
@@ -170,18 +1262,28 @@ impl Arg {
             } else {
                 quote!{::yutani::Id}
             },
-            DataType::NewId => if let Some(_) = self.interface.as_ref() {
+            DataType::NewId => if self.interface.is_some() {
                 quote!{::yutani::Id}
             } else {
                 quote!{::yutani::NewId}
             }
         }
     }
-    pub fn send_ty(&self) -> TokenStream {
+    /// The type of the argument when sending, borrowing where possible.
+    /// 
+    /// Arrays of fixed point numbers are taken by value as `Fixed` cannot be copied out of a slice.
+    pub fn send_ty(&self, enumeration: Option<&(TokenStream, bool)>, options: &CodegenOptions) -> TokenStream {
+        if let (DataType::Array, Some(array_type)) = (self.ty, self.array_type) {
+            let ty = self.element_ty(array_type, enumeration, options);
+            return match array_type {
+                ArrayType::Fixed if !options.fixed_as_f64 => quote!{::std::vec::Vec<#ty>},
+                _ => quote!{&'_ [#ty]}
+            }
+        }
         match self.ty {
             DataType::Int => quote!{::core::primitive::i32},
             DataType::Uint => quote!{::core::primitive::u32},
-            DataType::Fixed => quote!{::yutani::Fixed},
+            DataType::Fixed => fixed_ty(options),
             DataType::String => if self.nullable {
                 quote!{::core::option::Option<&'_ ::core::primitive::str>}
             } else {
@@ -194,7 +1296,7 @@ impl Arg {
             } else {
                 quote!{::yutani::Id}
             },
-            DataType::NewId => if let Some(_) = self.interface.as_ref() {
+            DataType::NewId => if self.interface.is_some() {
                 quote!{::yutani::Id}
             } else {
                 quote!{&'_ ::yutani::NewId}
@@ -203,10 +1305,53 @@ impl Arg {
     }
 }
 
-#[derive(Copy, Clone, Debug, Deserialize)]
-#[serde(rename_all = "snake_case")]
+/// The type fixed point numbers are exposed as.
+fn fixed_ty(options: &CodegenOptions) -> TokenStream {
+    if options.fixed_as_f64 {
+        quote!{::core::primitive::f64}
+    } else {
+        quote!{::yutani::Fixed}
+    }
+}
+/// Read a fixed point number, converting it from 24.8 fixed point if exposed as an `f64`.
+fn fixed_getter(stream: &Ident, options: &CodegenOptions) -> TokenStream {
+    if options.fixed_as_f64 {
+        quote!{#stream.i32()? as ::core::primitive::f64 / 256.0}
+    } else {
+        quote!{#stream.fixed()?}
+    }
+}
+/// Send a fixed point number, converting `value` to 24.8 fixed point if exposed as an `f64`.
+/// 
+/// Values are rounded to the nearest 1/256, while `as` saturates values out of range and sends NaN as 0.
+fn fixed_sender(stream: &Ident, value: TokenStream, options: &CodegenOptions) -> TokenStream {
+    if options.fixed_as_f64 {
+        quote!{#stream.send_i32((#value * 256.0).round() as ::core::primitive::i32)?}
+    } else {
+        quote!{#stream.send_fixed(#value)?}
+    }
+}
+
+/// The type of the elements of an array argument, which are sent as 32-bit values in the native byte order.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ArrayType {
+    Int,
+    Uint,
+    Fixed
+}
+impl Vocabulary for ArrayType {
+    const FIELD: &'static str = "array type";
+    const VALUES: &'static [(&'static str, Self)] = &[
+        ("int", Self::Int),
+        ("uint", Self::Uint),
+        ("fixed", Self::Fixed)
+    ];
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
 pub enum DataType {
     Int,
+    #[default]
     Uint,
     Fixed,
     String,
@@ -214,4 +1359,22 @@ pub enum DataType {
     Fd,
     Object,
     NewId
+}
+impl Vocabulary for DataType {
+    const FIELD: &'static str = "type";
+    const VALUES: &'static [(&'static str, Self)] = &[
+        ("int", Self::Int),
+        ("uint", Self::Uint),
+        ("fixed", Self::Fixed),
+        ("string", Self::String),
+        ("array", Self::Array),
+        ("fd", Self::Fd),
+        ("object", Self::Object),
+        ("new_id", Self::NewId)
+    ];
+}
+impl fmt::Display for DataType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
 }
\ No newline at end of file
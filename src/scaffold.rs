@@ -0,0 +1,187 @@
+//! Writing the skeleton of an implementation of an interface, as a starting point to be edited by hand.
+//!
+//! The skeleton is source text rather than a `TokenStream`, as it is meant to be read and has comments, so tokens are
+//! written with the spacing of hand-written Rust.
+
+use std::fmt::Write;
+
+use proc_macro2::{Delimiter, Spacing, TokenStream, TokenTree};
+use quote::quote;
+
+use crate::{ident, request_params, state_declaration, state_param, trait_name, validate, CodegenOptions, Diagnostic, Error, Interface, Protocol, Result, Severity};
+
+/// Write a Rust source file with a unit struct `ty` and an implementation of the trait of `interface` for it, with
+/// every request stubbed with `todo!()` below a comment with its summary.
+///
+/// The generated code of the protocol is imported from `module`, such as `crate::protocol::xdg_shell`, and the trait
+/// is implemented for any state type, with the [generic parameter](CodegenOptions::generic_param) and bounds of
+/// `options`. Requests have the same signatures as in the code generated with `options`.
+pub fn scaffold(protocol: &Protocol, interface: &str, ty: &str, module: &str, options: &CodegenOptions) -> Result<String> {
+    let mut errors: Vec<_> = validate(protocol, options).into_iter().filter(|d| d.severity == Severity::Error).collect();
+    let found = protocol.interfaces.iter().find(|i| i.name == interface);
+    if found.is_none() {
+        errors.push(Diagnostic::error(&protocol.name, format!("interface `{}` does not exist", interface)));
+    } else if options.core_interfaces.contains_key(interface) {
+        errors.push(Diagnostic::error(interface, "interface is a core interface implemented by the runtime"));
+    }
+    if syn::parse_str::<syn::Ident>(ty).is_err() {
+        errors.push(Diagnostic::error(interface, format!("type name `{}` is not a valid identifier", ty)));
+    }
+    if syn::parse_str::<syn::Path>(module).is_err() {
+        errors.push(Diagnostic::error(interface, format!("module `{}` is not a valid path", module)));
+    }
+    let Some(interface) = found.filter(|_| errors.is_empty()) else {
+        return Err(Error::Validation(errors))
+    };
+
+    let mut source = String::new();
+    writeln!(source, "// Skeleton of an implementation of `{}`, generated by yutani-codegen.", interface.name).unwrap();
+    writeln!(source, "use {}::*;\n", module).unwrap();
+    writeln!(source, "pub struct {};\n", ty).unwrap();
+    implementation(&mut source, protocol, interface, ty, options);
+    Ok(source)
+}
+
+/// Write an implementation of the trait of `interface` for `ty`, with every request stubbed with `todo!()`.
+pub(crate) fn implementation(source: &mut String, protocol: &Protocol, interface: &Interface, ty: &str, options: &CodegenOptions) {
+    writeln!(source, "#[allow(unused_variables)]").unwrap();
+    let t = state_param(options);
+    writeln!(source, "impl<{}> {}<{}> for {} {{", rust(state_declaration(options)), ident::name(&trait_name(protocol, interface, options)), t, ty).unwrap();
+    for (i, request) in interface.requests.iter().enumerate() {
+        if i != 0 {
+            source.push('\n');
+        }
+        let summary = request.summary.as_deref()
+            .and_then(|summary| summary.lines().next())
+            .map_or_else(|| format!("Request `{}`", request.name), str::to_owned);
+        writeln!(source, "    // {}", summary.trim()).unwrap();
+        if request.unimplemented {
+            writeln!(source, "    // Optional, the request fails with a protocol error unless it is implemented").unwrap();
+        }
+        let ident = ident::snake(&request.name);
+        writeln!(source, "    fn {}(", rust(quote!{#ident})).unwrap();
+        let params = [
+            "this: ::yutani::lease::Lease<Self>".to_owned(),
+            format!("event_loop: &mut ::yutani::wire::EventLoop<{}>", t),
+            format!("client: &mut ::yutani::server::Client<{}>", t)
+        ].into_iter().chain(request_params(protocol, interface, request, options).into_iter().map(rust));
+        for param in params {
+            writeln!(source, "        {},", param).unwrap();
+        }
+        writeln!(source, "    ) -> ::core::result::Result<(), ::yutani::wire::WlError<'static>> {{").unwrap();
+        writeln!(source, "        todo!()").unwrap();
+        writeln!(source, "    }}").unwrap();
+    }
+    *source += "}\n";
+}
+
+/// Write tokens with the spacing of hand-written Rust, for the types and parameters of a signature.
+pub(crate) fn rust(tokens: TokenStream) -> String {
+    let mut words = Vec::new();
+    split(tokens, &mut words);
+    let mut source = String::new();
+    let mut previous: Option<&str> = None;
+    for word in &words {
+        let spaced = match previous {
+            None => false,
+            Some("::" | "<" | "&" | "(" | "[") => false,
+            Some(previous) => match word.as_str() {
+                "," | ">" | ":" | ")" | "]" | "<" => false,
+                // A path separator joins the words either side of it, unless it starts an absolute path
+                "::" => matches!(previous, "mut" | "dyn" | "impl" | ":" | "," | "->") || previous.starts_with('\''),
+                _ => true
+            }
+        };
+        if spaced {
+            source.push(' ');
+        }
+        source += word;
+        previous = Some(word);
+    }
+    source
+}
+/// Split tokens into words, joining punctuation such as `::` and lifetimes such as `'static`.
+fn split(tokens: TokenStream, words: &mut Vec<String>) {
+    let mut punct = String::new();
+    for token in tokens {
+        match token {
+            TokenTree::Punct(p) => {
+                punct.push(p.as_char());
+                if p.as_char() != '\'' && p.spacing() == Spacing::Alone {
+                    words.push(std::mem::take(&mut punct));
+                }
+            },
+            TokenTree::Ident(ident) => {
+                let ident = ident.to_string();
+                // Raw identifiers are only kept where they are needed, for keywords
+                let ident = match ident.strip_prefix("r#") {
+                    Some(name) if syn::parse_str::<syn::Ident>(name).is_ok() => name.to_owned(),
+                    _ => ident
+                };
+                words.push(std::mem::take(&mut punct) + &ident)
+            },
+            TokenTree::Literal(literal) => words.push(literal.to_string()),
+            TokenTree::Group(group) => {
+                let (open, close) = match group.delimiter() {
+                    Delimiter::Parenthesis => ("(", ")"),
+                    Delimiter::Bracket => ("[", "]"),
+                    Delimiter::Brace => ("{", "}"),
+                    Delimiter::None => {
+                        split(group.stream(), words);
+                        continue
+                    }
+                };
+                words.push(open.into());
+                split(group.stream(), words);
+                words.push(close.into());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_and_event() {
+        let protocol = Protocol::from_str(r#"
+            name = "acme"
+            [[interface]]
+            name = "acme_thing"
+            version = 1
+            [[interface.request]]
+            name = "set_size"
+            summary = "Set the size"
+            arg = [{ name = "width", type = "uint" }, { name = "label", type = "string", allow-null = true }]
+            [[interface.event]]
+            name = "resized"
+            arg = [{ name = "width", type = "uint" }]
+        "#).unwrap();
+        let source = scaffold(&protocol, "acme_thing", "Thing", "crate::acme", &CodegenOptions::default()).unwrap();
+        // Events are sent with the provided methods of the trait, so only requests are stubbed
+        assert_eq!(source, r#"// Skeleton of an implementation of `acme_thing`, generated by yutani-codegen.
+use crate::acme::*;
+
+pub struct Thing;
+
+#[allow(unused_variables)]
+impl<T> AcmeThing<T> for Thing {
+    // Set the size
+    fn set_size(
+        this: ::yutani::lease::Lease<Self>,
+        event_loop: &mut ::yutani::wire::EventLoop<T>,
+        client: &mut ::yutani::server::Client<T>,
+        width: ::core::primitive::u32,
+        label: ::core::option::Option<::std::string::String>,
+    ) -> ::core::result::Result<(), ::yutani::wire::WlError<'static>> {
+        todo!()
+    }
+}
+"#);
+        // With split traits, the requests are stubbed in the `Requests` trait
+        let options = CodegenOptions { split_traits: true, ..CodegenOptions::default() };
+        let source = scaffold(&protocol, "acme_thing", "Thing", "crate::acme", &options).unwrap();
+        assert!(source.contains("\nimpl<T> AcmeThingRequests<T> for Thing {\n"), "{}", source);
+    }
+}
@@ -0,0 +1,357 @@
+//! A JSON Schema of the TOML specification format, for autocompletion and validation in editors such as those using
+//! `taplo`.
+
+use std::fmt::Write;
+
+use crate::{ArrayType, ConstType, DataType, Stability, Vocabulary};
+
+/// The JSON Schema of a TOML specification, as a JSON document.
+///
+/// The schema describes what the parser accepts: a single protocol or several in `[[protocol]]` tables, the names
+/// each type, array type, constant type and stability can be written as, the fields each item requires, and that only
+/// `string` and `object` arguments may allow null. Keys starting with `x-` are [extensions](crate::Protocol::extensions)
+/// and are allowed anywhere, while any other unknown key is rejected as it would be with
+/// [`strict`](crate::CodegenOptions::strict).
+///
+/// Checks that need the whole protocol, such as whether an interface named by an argument exists, are left to
+/// [`validate`](crate::validate).
+pub fn json() -> String {
+    let mut json = String::new();
+    schema().write(&mut json, 0);
+    json.push('\n');
+    json
+}
+
+/// The schema, with a definition for each item of the format.
+fn schema() -> Json {
+    let multiple = object([
+        ("type", string("object")),
+        ("description", string("Several protocols kept together in one file.")),
+        ("properties", object([("protocol", array_of("protocol", "The protocols of the file."))])),
+        ("required", strings(["protocol"])),
+        ("additionalProperties", Json::Bool(false))
+    ]);
+    object([
+        ("$schema", string("http://json-schema.org/draft-07/schema#")),
+        ("title", string("Protocol specification")),
+        ("description", string("A protocol specification for yutani-codegen.")),
+        ("oneOf", Json::Array(vec![reference("protocol"), multiple])),
+        ("definitions", object([
+            ("protocol", protocol()),
+            ("constant", constant()),
+            ("interface", interface()),
+            ("enum", enumeration()),
+            ("entry", entry()),
+            ("request", request()),
+            ("event", event()),
+            ("arg", arg()),
+            ("arg_table", arg_table())
+        ]))
+    ])
+}
+
+fn protocol() -> Json {
+    let constants = "Named values that are not part of any enum, such as a maximum length.";
+    let interfaces = "The interfaces of the protocol.";
+    table("A protocol.", ["name"], [
+        ("name", text("The name of the protocol.")),
+        ("rust_module", text("The name of the module for the generated code, rather than the protocol name in snake case.")),
+        ("namespace", text("The module that the module for the generated code is placed in, such as `acme`.")),
+        ("summary", text("A one line summary.")),
+        ("description", description()),
+        ("copyright", text("The copyright of the protocol.")),
+        ("stability", vocabulary::<Stability>("How much the protocol may change.")),
+        ("externals", object([
+            ("type", string("array")),
+            ("description", string("Interfaces of other protocols that arguments refer to.")),
+            ("items", object([("type", string("string"))]))
+        ])),
+        ("const", array_of("constant", constants)),
+        ("constants", array_of("constant", constants)),
+        ("interface", array_of("interface", interfaces)),
+        ("interfaces", array_of("interface", interfaces))
+    ])
+}
+
+fn constant() -> Json {
+    table("A named value that is not part of any enum.", ["name", "type", "value"], [
+        ("name", text("The name of the constant.")),
+        ("summary", text("A one line summary.")),
+        ("description", description()),
+        ("type", vocabulary::<ConstType>("The type of the constant.")),
+        ("value", object([
+            ("type", strings(["integer", "string"])),
+            ("description", string("The value, an integer or a string as given by the type."))
+        ]))
+    ])
+}
+
+fn interface() -> Json {
+    let enums = "The enums of the interface.";
+    let requests = "The requests of the interface, in opcode order.";
+    let events = "The events of the interface, in opcode order.";
+    table("An interface.", ["name", "version"], [
+        ("name", text("The name of the interface.")),
+        ("summary", text("A one line summary.")),
+        ("description", description()),
+        ("version", version("The highest version of the interface.")),
+        ("extends", text("The interface of the protocol whose enums, requests and events are copied into this interface.")),
+        ("stability", vocabulary::<Stability>("Overrides the stability of the protocol for this interface.")),
+        ("deprecated", flag("Marks the interface as deprecated.")),
+        ("replaced_by", text("The interface that replaces a deprecated interface.")),
+        ("deprecation_note", text("Why the interface is deprecated or what to use instead.")),
+        ("capability", text("The capability a client needs for requests to the interface to be dispatched.")),
+        ("enum", array_of("enum", enums)),
+        ("enums", array_of("enum", enums)),
+        ("request", array_of("request", requests)),
+        ("requests", array_of("request", requests)),
+        ("event", array_of("event", events)),
+        ("events", array_of("event", events))
+    ])
+}
+
+fn enumeration() -> Json {
+    let entries = "The entries of the enum.";
+    table("An enum.", ["name"], [
+        ("name", text("The name of the enum.")),
+        ("summary", text("A one line summary.")),
+        ("description", description()),
+        ("since", version("The version of the interface that added the enum.")),
+        ("bitfield", flag("Marks the enum as a set of flags.")),
+        ("default_entry", text("The entry returned by `Default`, rather than the entry with a value of 0.")),
+        ("entry", array_of("entry", entries)),
+        ("entries", array_of("entry", entries))
+    ])
+}
+
+fn entry() -> Json {
+    let mut entry = table("An entry of an enum, which has either a value or is an alias of another entry.", ["name"], [
+        ("name", text("The name of the entry.")),
+        ("since", version("The version of the interface that added the entry.")),
+        ("summary", text("A one line summary.")),
+        ("description", description()),
+        ("value", object([
+            ("type", strings(["integer", "string"])),
+            ("description", string("The value, or a constant written as `@name`, optionally followed by `+`, `-` or `<<` and a number."))
+        ])),
+        ("alias_of", text("The name of another entry this entry is an alias of.")),
+        ("combination", flag("Marks an entry of a bitfield as a combination of other flags."))
+    ]);
+    entry.insert("oneOf", Json::Array(vec![
+        object([("required", strings(["value"]))]),
+        object([("required", strings(["alias_of"]))])
+    ]));
+    entry
+}
+
+fn request() -> Json {
+    let args = "The arguments of the request.";
+    table("A request, sent by clients.", ["name"], [
+        ("name", text("The name of the request.")),
+        ("since", version("The version of the interface that added the request.")),
+        ("destructor", flag("Marks a request that destroys the object it is sent to.")),
+        ("unimplemented", flag("Marks a request that implementations do not support.")),
+        ("unimplemented_error", text("The entry of the `error` enum of the interface that an unimplemented request fails with.")),
+        ("capability", text("The capability a client needs for the request to be dispatched.")),
+        ("summary", text("A one line summary.")),
+        ("description", description()),
+        ("arg", array_of("arg", args)),
+        ("args", array_of("arg", args))
+    ])
+}
+
+fn event() -> Json {
+    let args = "The arguments of the event.";
+    table("An event, sent by the server.", ["name"], [
+        ("name", text("The name of the event.")),
+        ("since", version("The version of the interface that added the event.")),
+        ("destructor", flag("Marks an event that destroys the object it is sent from.")),
+        ("summary", text("A one line summary.")),
+        ("description", description()),
+        ("arg", array_of("arg", args)),
+        ("args", array_of("arg", args))
+    ])
+}
+
+fn arg() -> Json {
+    let shorthand = object([
+        ("type", string("string")),
+        ("description", string("An argument written as `name:type`, such as `buffer:?object<wl_buffer>`.")),
+        ("pattern", string("^[^:]+:.+$"))
+    ]);
+    object([("anyOf", Json::Array(vec![shorthand, reference("arg_table")]))])
+}
+
+fn arg_table() -> Json {
+    let nullable = names::<DataType>(|ty| matches!(ty, DataType::String | DataType::Object));
+    let array = names::<DataType>(|ty| ty == DataType::Array);
+    let mut arg = table("An argument of a message.", ["name", "type"], [
+        ("name", text("The name of the argument.")),
+        ("allow-null", flag("Whether the argument may be null, which only `string` and `object` arguments can.")),
+        ("type", vocabulary::<DataType>("The type of the argument.")),
+        ("interface", text("The interface of an `object` or `new_id` argument.")),
+        ("enum", text("The enum of the argument, as `name` or `interface.name`.")),
+        ("array_type", vocabulary::<ArrayType>("The type of the elements of an `array` argument, which are otherwise bytes.")),
+        ("summary", text("A one line summary."))
+    ]);
+    arg.insert("allOf", Json::Array(vec![
+        implies("allow-null", object([("const", Json::Bool(true))]), "type", object([("enum", nullable)])),
+        implies("array_type", object([]), "type", object([("enum", array)]))
+    ]));
+    arg
+}
+
+/// A table with the properties, which requires the `required` properties and rejects other keys than extensions.
+fn table<const P: usize, const R: usize>(description: &str, required: [&str; R], properties: [(&str, Json); P]) -> Json {
+    object([
+        ("type", string("object")),
+        ("description", string(description)),
+        ("properties", object(properties)),
+        ("required", strings(required)),
+        ("patternProperties", object([("^x-", object([]))])),
+        ("additionalProperties", Json::Bool(false))
+    ])
+}
+
+/// That when `property` is present and matches `condition`, `then` also matches `other`.
+fn implies(property: &str, condition: Json, other: &str, then: Json) -> Json {
+    object([
+        ("if", object([
+            ("properties", object([(property, condition)])),
+            ("required", strings([property]))
+        ])),
+        ("then", object([("properties", object([(other, then)]))]))
+    ])
+}
+
+fn text(description: &str) -> Json {
+    object([("type", string("string")), ("description", string(description))])
+}
+
+/// A description, which may be written as an array of paragraphs rather than one string.
+fn description() -> Json {
+    object([
+        ("type", strings(["string", "array"])),
+        ("items", object([("type", string("string"))])),
+        ("description", string("A description, in Markdown, or an array of paragraphs that are joined with blank lines."))
+    ])
+}
+
+fn flag(description: &str) -> Json {
+    object([("type", string("boolean")), ("description", string(description))])
+}
+
+fn version(description: &str) -> Json {
+    object([("type", string("integer")), ("minimum", Json::Integer(1)), ("description", string(description))])
+}
+
+/// Any of the names a value of the vocabulary can be written as.
+fn vocabulary<T: Vocabulary>(description: &str) -> Json {
+    object([("type", string("string")), ("enum", names::<T>(|_| true)), ("description", string(description))])
+}
+
+/// The names of the values of a vocabulary that match `filter`.
+fn names<T: Vocabulary>(filter: impl Fn(T) -> bool) -> Json {
+    Json::Array(T::VALUES.iter().filter(|(_, value)| filter(*value)).map(|(name, _)| string(name)).collect())
+}
+
+fn array_of(definition: &str, description: &str) -> Json {
+    object([("type", string("array")), ("description", string(description)), ("items", reference(definition))])
+}
+
+fn reference(definition: &str) -> Json {
+    object([("$ref", string(&format!("#/definitions/{}", definition)))])
+}
+
+fn object<const N: usize>(members: [(&str, Json); N]) -> Json {
+    Json::Object(members.into_iter().map(|(key, value)| (key.to_owned(), value)).collect())
+}
+
+fn strings<const N: usize>(strings: [&str; N]) -> Json {
+    Json::Array(strings.into_iter().map(string).collect())
+}
+
+fn string(string: &str) -> Json {
+    Json::String(string.to_owned())
+}
+
+/// A JSON value, with the members of objects in the order they are written.
+enum Json {
+    Bool(bool),
+    Integer(i64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>)
+}
+impl Json {
+    /// Add a member to an object.
+    fn insert(&mut self, key: &str, value: Json) {
+        if let Self::Object(members) = self {
+            members.push((key.to_owned(), value))
+        }
+    }
+    /// Write the value indented by `indent` levels of two spaces.
+    fn write(&self, json: &mut String, indent: usize) {
+        match self {
+            Self::Bool(value) => { let _ = write!(json, "{}", value); },
+            Self::Integer(value) => { let _ = write!(json, "{}", value); },
+            Self::String(value) => quote(json, value),
+            Self::Array(values) if values.is_empty() => json.push_str("[]"),
+            Self::Object(members) if members.is_empty() => json.push_str("{}"),
+            // Arrays of scalars, such as the names of a vocabulary, are short enough to fit on one line
+            Self::Array(values) if values.iter().all(|value| !matches!(value, Self::Array(_) | Self::Object(_))) => {
+                json.push('[');
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        json.push_str(", ");
+                    }
+                    value.write(json, indent);
+                }
+                json.push(']');
+            },
+            Self::Array(values) => {
+                json.push('[');
+                for (i, value) in values.iter().enumerate() {
+                    json.push_str(if i > 0 { ",\n" } else { "\n" });
+                    push_indent(json, indent + 1);
+                    value.write(json, indent + 1);
+                }
+                json.push('\n');
+                push_indent(json, indent);
+                json.push(']');
+            },
+            Self::Object(members) => {
+                json.push('{');
+                for (i, (key, value)) in members.iter().enumerate() {
+                    json.push_str(if i > 0 { ",\n" } else { "\n" });
+                    push_indent(json, indent + 1);
+                    quote(json, key);
+                    json.push_str(": ");
+                    value.write(json, indent + 1);
+                }
+                json.push('\n');
+                push_indent(json, indent);
+                json.push('}');
+            }
+        }
+    }
+}
+
+fn push_indent(json: &mut String, indent: usize) {
+    json.extend(std::iter::repeat_n("  ", indent));
+}
+
+/// Write a string as a JSON string literal.
+fn quote(json: &mut String, string: &str) {
+    json.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            c if c.is_control() => { let _ = write!(json, "\\u{:04x}", c as u32); },
+            c => json.push(c)
+        }
+    }
+    json.push('"');
+}
@@ -0,0 +1,114 @@
+//! Changing a protocol between parsing and generating it, so that an upstream specification can be adapted, such as
+//! by renaming an interface or dropping an event that is not supported, without keeping a modified copy of it.
+//!
+//! A [`Transform`] is applied with [`Protocol::transform`]. Inherited items are not visited, as they are copied again
+//! from the interface they are inherited from once the transform is done, so they are changed through that interface.
+
+use crate::{Enum, Event, Interface, Protocol, Request};
+
+/// Changes to make to a protocol, with a method for each kind of item that does nothing by default.
+///
+/// The protocol is visited first, then each interface followed by its enums, requests and events, in the order they
+/// are specified. Items added or removed while visiting the protocol or an interface are visited as they are after
+/// the change.
+///
+/// A closure taking `&mut Protocol` is a transform that only visits the protocol.
+pub trait Transform {
+    /// Visit the protocol, before any of its interfaces.
+    fn visit_protocol(&mut self, _protocol: &mut Protocol) {}
+    /// Visit an interface, before any of its items.
+    fn visit_interface(&mut self, _interface: &mut Interface) {}
+    /// Visit an enum of the interface named `interface`.
+    fn visit_enum(&mut self, _interface: &str, _enumeration: &mut Enum) {}
+    /// Visit a request of the interface named `interface`.
+    fn visit_request(&mut self, _interface: &str, _request: &mut Request) {}
+    /// Visit an event of the interface named `interface`.
+    fn visit_event(&mut self, _interface: &str, _event: &mut Event) {}
+}
+impl<F: FnMut(&mut Protocol)> Transform for F {
+    fn visit_protocol(&mut self, protocol: &mut Protocol) {
+        self(protocol)
+    }
+}
+
+pub(crate) fn walk<T: Transform + ?Sized>(protocol: &mut Protocol, transform: &mut T) {
+    transform.visit_protocol(protocol);
+    for interface in &mut protocol.interfaces {
+        transform.visit_interface(interface);
+        let name = interface.name.clone();
+        for enumeration in interface.enums.iter_mut().filter(|e| !e.inherited) {
+            transform.visit_enum(&name, enumeration);
+        }
+        for request in interface.requests.iter_mut().filter(|r| !r.inherited) {
+            transform.visit_request(&name, request);
+        }
+        for event in interface.events.iter_mut().filter(|e| !e.inherited) {
+            transform.visit_event(&name, event);
+        }
+    }
+}
+
+/// Rename an interface, along with the references to it by name: the interfaces that extend it or are replaced by
+/// it, arguments that are objects of it and arguments that refer to its enums as `interface.enum`.
+///
+/// Nothing is changed if there is no interface named `from`.
+pub fn rename_interface(from: &str, to: &str) -> impl Transform {
+    let (from, to) = (from.to_owned(), to.to_owned());
+    move |protocol: &mut Protocol| {
+        if !protocol.interfaces.iter().any(|i| i.name == from) {
+            return
+        }
+        let rename = |name: &mut Option<String>| if name.as_deref() == Some(&from) {
+            *name = Some(to.clone())
+        };
+        for interface in &mut protocol.interfaces {
+            if interface.name == from {
+                interface.name = to.clone();
+            }
+            rename(&mut interface.extends);
+            rename(&mut interface.replaced_by);
+            let args = interface.requests.iter_mut().flat_map(|r| &mut r.args)
+                .chain(interface.events.iter_mut().flat_map(|e| &mut e.args));
+            for arg in args {
+                rename(&mut arg.interface);
+                let Some(enumeration) = &mut arg.enumeration else {
+                    continue
+                };
+                if let Some(name) = enumeration.strip_prefix(&from).and_then(|name| name.strip_prefix('.')) {
+                    *enumeration = format!("{}.{}", to, name);
+                }
+            }
+        }
+    }
+}
+
+/// Remove an event from an interface.
+///
+/// The events after it take the opcodes one lower, so unless the event is the last of its interface, the result is
+/// only compatible with clients built from the same changed specification. Nothing is changed if there is no such
+/// event.
+pub fn remove_event(interface: &str, event: &str) -> impl Transform {
+    let (interface, event) = (interface.to_owned(), event.to_owned());
+    move |protocol: &mut Protocol| {
+        if let Some(interface) = protocol.interfaces.iter_mut().find(|i| i.name == interface) {
+            interface.events.retain(|e| e.name != event);
+        }
+    }
+}
+
+/// Add an enum to an interface, replacing any enum of the same name in place.
+///
+/// Nothing is changed if there is no such interface. The values of the entries are resolved from their literals
+/// once the transform is done.
+pub fn add_enum(interface: &str, enumeration: Enum) -> impl Transform {
+    let interface = interface.to_owned();
+    move |protocol: &mut Protocol| {
+        let Some(interface) = protocol.interfaces.iter_mut().find(|i| i.name == interface) else {
+            return
+        };
+        match interface.enums.iter_mut().find(|e| e.name == enumeration.name) {
+            Some(existing) => *existing = enumeration.clone(),
+            None => interface.enums.push(enumeration.clone())
+        }
+    }
+}
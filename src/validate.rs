@@ -0,0 +1,565 @@
+use std::{collections::BTreeMap, fmt};
+
+use crate::{ident, lint, Arg, ArrayType, CodegenOptions, ConstType, ConstValue, DataType, EntryValue, Interface, Lint, Protocol, Stability, Vocabulary, EMBED_SPEC_LIMIT, MAX_MESSAGE_SIZE, min_message_size};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error
+}
+/// A problem found with a protocol specification.
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// The protocol item the diagnostic refers to, such as `wl_output.transform`.
+    pub path: String,
+    pub message: String,
+    /// The [lint](Lint) reporting the diagnostic, if it is reported by one.
+    pub lint: Option<Lint>
+}
+impl Diagnostic {
+    pub fn error(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self { severity: Severity::Error, path: path.into(), message: message.into(), lint: None }
+    }
+    pub fn warning(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self { severity: Severity::Warning, path: path.into(), message: message.into(), lint: None }
+    }
+}
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error"
+        };
+        match self.lint {
+            Some(lint) => write!(f, "{}[{}]: {}: {}", severity, lint, self.path, self.message),
+            None => write!(f, "{}: {}: {}", severity, self.path, self.message)
+        }
+    }
+}
+
+/// Check a protocol for problems that would otherwise produce incorrect or uncompilable code.
+pub fn validate(protocol: &Protocol, options: &CodegenOptions) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    if let Some(serde) = &options.serde {
+        if syn::parse_str::<syn::Path>(serde).is_err() {
+            diagnostics.push(Diagnostic::error(&protocol.name, format!("serde path `{}` is not a valid path", serde)));
+        }
+    }
+    for lint in &options.allow {
+        if syn::parse_str::<syn::Path>(lint).is_err() {
+            diagnostics.push(Diagnostic::error(&protocol.name, format!("allowed lint `{}` is not a valid lint name", lint)));
+        }
+    }
+    for (interface, ty) in &options.concrete_types {
+        if syn::parse_str::<syn::Type>(ty).is_err() {
+            diagnostics.push(Diagnostic::error(interface, format!("concrete type `{}` is not a valid type", ty)));
+        }
+        if !protocol.interfaces.iter().any(|i| &i.name == interface) {
+            diagnostics.push(Diagnostic::warning(interface, "concrete type given for an interface that does not exist"));
+        }
+        if options.core_interfaces.contains_key(interface) {
+            diagnostics.push(Diagnostic::error(interface, "concrete type given for a core interface, which has no trait to implement"));
+        }
+    }
+    for (interface, dispatch) in &options.core_interfaces {
+        if syn::parse_str::<syn::Path>(dispatch).is_err() {
+            diagnostics.push(Diagnostic::error(interface, format!("core dispatch function `{}` is not a valid path", dispatch)));
+        }
+        if !protocol.interfaces.iter().any(|i| &i.name == interface) {
+            diagnostics.push(Diagnostic::warning(interface, "core dispatch function given for an interface that does not exist"));
+        }
+    }
+    for acronym in &options.acronyms {
+        if acronym.is_empty() || !acronym.chars().all(|c| c.is_ascii_alphanumeric()) {
+            diagnostics.push(Diagnostic::error(&protocol.name, format!("acronym `{}` is not a single word of letters and digits", acronym)));
+        }
+    }
+    let module = crate::module_name(protocol, options);
+    if syn::parse_str::<syn::Ident>(&module).is_err() {
+        diagnostics.push(Diagnostic::error(&protocol.name, format!("module name `{}` is not a valid identifier", module)));
+    }
+    if let Some(namespace) = &protocol.namespace {
+        if namespace.split("::").any(|module| syn::parse_str::<syn::Ident>(module).is_err()) {
+            diagnostics.push(Diagnostic::error(&protocol.name, format!("namespace `{}` is not a valid module path", namespace)));
+        }
+    }
+    let param = options.generic_param.as_deref().unwrap_or("T");
+    if syn::parse_str::<syn::Ident>(param).is_err() {
+        diagnostics.push(Diagnostic::error(&protocol.name, format!("generic parameter `{}` is not a valid identifier", param)));
+    } else if matches!(param, "I" | "O") {
+        diagnostics.push(Diagnostic::error(&protocol.name, format!("generic parameter `{}` is used by the generated code", param)));
+    } else if let Some(bounds) = &options.generic_bounds {
+        if syn::parse_str::<syn::TypeParam>(&format!("{}: {}", param, bounds)).is_err() {
+            diagnostics.push(Diagnostic::error(&protocol.name, format!("generic bounds `{}` are not valid bounds", bounds)));
+        }
+    }
+    if options.embed_spec {
+        let size = protocol.to_toml().len();
+        let limit = options.embed_spec_limit.unwrap_or(EMBED_SPEC_LIMIT);
+        if size > limit {
+            diagnostics.push(Diagnostic::warning(&protocol.name, format!(
+                "the embedded specification is {} bytes, over the limit of {} bytes set by `embed_spec_limit`", size, limit
+            )));
+        }
+    }
+    if let Some(attribute) = &options.unstable_attribute {
+        if syn::parse_str::<syn::Meta>(attribute).is_err() {
+            diagnostics.push(Diagnostic::error(&protocol.name, format!("unstable attribute `{}` is not a valid attribute", attribute)));
+        }
+    }
+    if let Some(predicate) = &options.dispatch_metrics {
+        if syn::parse_str::<syn::Meta>(predicate).is_err() {
+            diagnostics.push(Diagnostic::error(&protocol.name, format!("dispatch metrics predicate `{}` is not a valid `cfg` predicate", predicate)));
+        }
+    }
+    for external in &protocol.externals {
+        if protocol.interface(external).is_some() {
+            diagnostics.push(Diagnostic::warning(&protocol.name, format!(
+                "`{}` is listed in `externals` but is an interface of the protocol", external
+            )));
+        }
+    }
+    for (interface, module) in &options.external_modules {
+        if syn::parse_str::<syn::Path>(module).is_err() {
+            diagnostics.push(Diagnostic::error(&protocol.name, format!("external module `{}` of `{}` is not a valid path", module, interface)));
+        }
+    }
+    if let Some(name) = &protocol.stability_name {
+        check_vocabulary::<Stability>(&mut diagnostics, &protocol.name, name);
+    }
+    check_keys(&mut diagnostics, &protocol.name, &protocol.extensions, options);
+    for (i, constant) in protocol.constants.iter().enumerate() {
+        let path = format!("{}.{}", protocol.name, constant.name);
+        check_keys(&mut diagnostics, &path, &constant.extensions, options);
+        let known = check_vocabulary::<ConstType>(&mut diagnostics, &path, &constant.type_name);
+        check_name(&mut diagnostics, &path, &constant.name, &ident::shouty_snake_case(&constant.name));
+        if protocol.constants[..i].iter().any(|c| ident::shouty_snake_case(&c.name) == ident::shouty_snake_case(&constant.name)) {
+            diagnostics.push(Diagnostic::error(&path, "constant is defined more than once"));
+        }
+        match (&constant.value, constant.ty) {
+            _ if !known => (),
+            (ConstValue::Integer(_), ConstType::String) => diagnostics.push(Diagnostic::error(&path, "value must be a string")),
+            (ConstValue::String(_), ConstType::Int | ConstType::Uint) => diagnostics.push(Diagnostic::error(&path, "value must be an integer")),
+            (ConstValue::Integer(value), ConstType::Int) if i32::try_from(*value).is_err() => diagnostics.push(Diagnostic::error(
+                &path,
+                format!("value {} is out of range for the type `int`", value)
+            )),
+            (ConstValue::Integer(value), ConstType::Uint) if u32::try_from(*value).is_err() => diagnostics.push(Diagnostic::error(
+                &path,
+                format!("value {} is out of range for the type `uint`", value)
+            )),
+            _ => ()
+        }
+    }
+    for interface in &protocol.interfaces {
+        if let Some(name) = &interface.stability_name {
+            check_vocabulary::<Stability>(&mut diagnostics, &interface.name, name);
+        }
+        check_keys(&mut diagnostics, &interface.name, &interface.extensions, options);
+        let rust_name = crate::interface_name(protocol, interface, options);
+        // Keywords such as `self` are only keywords in one case, so both the module and trait names are checked
+        let (module, trait_name) = (ident::snake_case(rust_name), ident::pascal_case(rust_name, options));
+        check_name(&mut diagnostics, &interface.name, &interface.name, &module);
+        if ident::keyword_fallback(&module).is_none() && ident::problem(&module).is_none() {
+            check_name(&mut diagnostics, &interface.name, &interface.name, &trait_name);
+        }
+        if protocol.stability(interface) == Stability::Unstable {
+            let versioned = interface.name.rsplit_once("_v")
+                .is_some_and(|(_, version)| !version.is_empty() && version.chars().all(|c| c.is_ascii_digit()));
+            if !versioned {
+                diagnostics.push(Diagnostic::warning(&interface.name, "unstable interfaces should have a `_vN` suffix"));
+            }
+        }
+        if let Some(parent) = &interface.extends {
+            check_extends(&mut diagnostics, protocol, interface, parent);
+        }
+        if !interface.deprecated && (interface.replaced_by.is_some() || interface.deprecation_note.is_some()) {
+            diagnostics.push(Diagnostic::warning(&interface.name, "a replacement or deprecation note has no effect unless the interface is `deprecated`"));
+        }
+        if !options.core_interfaces.contains_key(&interface.name) {
+            check_companion_trait(&mut diagnostics, protocol, interface, "dispatch", crate::dispatch_trait_name(protocol, interface, options), options);
+        }
+        if options.ext_traits && !interface.events.is_empty() && !options.core_interfaces.contains_key(&interface.name) {
+            check_ext_trait(&mut diagnostics, protocol, interface, options);
+        }
+        for request in &interface.requests {
+            let Some(entry) = &request.unimplemented_error else {
+                continue
+            };
+            let path = format!("{}.{}", interface.name, request.name);
+            if !request.unimplemented {
+                diagnostics.push(Diagnostic::warning(&path, "an unimplemented error has no effect unless the request is `unimplemented`"));
+            }
+            let known = interface.enums.iter().find(|e| e.name == "error").is_some_and(|e| e.entries.iter().any(|e| &e.name == entry));
+            if !known {
+                diagnostics.push(Diagnostic::error(&path, format!("unimplemented error `{}` is not an entry of the `error` enum", entry)));
+            }
+        }
+        // Inherited messages are checked by the interface they are inherited from
+        let messages = interface.requests.iter().map(|r| (&r.name, &r.args, &r.extensions, r.inherited))
+            .chain(interface.events.iter().map(|e| (&e.name, &e.args, &e.extensions, e.inherited)));
+        for (message, args, extensions, inherited) in messages {
+            let message_path = format!("{}.{}", interface.name, message);
+            check_name(&mut diagnostics, &message_path, message, &ident::snake_case(message));
+            if !inherited {
+                check_keys(&mut diagnostics, &message_path, extensions, options);
+                for arg in args {
+                    check_keys(&mut diagnostics, &format!("{}.{}", message_path, arg.name), &arg.extensions, options);
+                }
+            }
+            for (i, arg) in args.iter().enumerate() {
+                let path = format!("{}.{}", message_path, arg.name);
+                let converted = ident::snake_case(&arg.name);
+                check_name(&mut diagnostics, &path, &arg.name, &converted);
+                // Each argument is a parameter and a binding of the same name, so names must differ once converted
+                if let Some(earlier) = args[..i].iter().find(|a| ident::snake_case(&a.name) == converted) {
+                    diagnostics.push(Diagnostic::error(&path, if earlier.name == arg.name {
+                        "argument is defined more than once".to_string()
+                    } else {
+                        format!("argument is named `{}` in Rust, the same as `{}`", converted, earlier.name)
+                    }));
+                }
+                let known_type = check_vocabulary::<DataType>(&mut diagnostics, &path, &arg.type_name);
+                let known_array_type = arg.array_type_name.as_ref()
+                    .is_none_or(|name| check_vocabulary::<ArrayType>(&mut diagnostics, &path, name));
+                if !known_type || !known_array_type {
+                    continue
+                }
+                if !inherited {
+                    check_references(&mut diagnostics, protocol, interface, &path, arg, options);
+                }
+                if arg.nullable && !matches!(arg.ty, DataType::String | DataType::Object) {
+                    diagnostics.push(Diagnostic::error(&path, format!(
+                        "`{}` arguments have no null representation on the wire, only `string` and `object` arguments can allow null",
+                        arg.ty
+                    )));
+                }
+                match (arg.ty, arg.array_type, &arg.enumeration) {
+                    (DataType::Array, None, Some(_)) => diagnostics.push(Diagnostic::error(&path, "arrays of enums require an `int` or `uint` array type")),
+                    (DataType::Array, Some(ArrayType::Fixed), Some(_)) => diagnostics.push(Diagnostic::error(&path, "arrays of fixed point numbers cannot be enums")),
+                    (DataType::Array, _, _) | (_, None, _) => (),
+                    (_, Some(_), _) => diagnostics.push(Diagnostic::error(&path, "only arrays can have an array type"))
+                }
+            }
+            // Each message creates at most one object, which the helpers creating objects and the dispatch of
+            // requests rely on
+            let new_ids: Vec<_> = args.iter().filter(|a| a.ty == DataType::NewId).map(|a| format!("`{}`", a.name)).collect();
+            if let [rest @ .., last] = &new_ids[..] {
+                if !rest.is_empty() {
+                    diagnostics.push(Diagnostic::error(&message_path, format!(
+                        "a message can create at most one object, but has the `new_id` arguments {} and {}",
+                        rest.join(", "),
+                        last
+                    )));
+                }
+            }
+            let size = min_message_size(args);
+            if size > MAX_MESSAGE_SIZE {
+                diagnostics.push(Diagnostic::error(&message_path, format!(
+                    "the message is at least {} bytes, over the {} byte limit of a message", size, MAX_MESSAGE_SIZE
+                )));
+            } else if size * 4 >= MAX_MESSAGE_SIZE * 3 {
+                diagnostics.push(Diagnostic::warning(&message_path, format!(
+                    "the message is at least {} bytes before any strings or arrays, close to the {} byte limit of a message",
+                    size, MAX_MESSAGE_SIZE
+                )));
+            }
+        }
+        for enumeration in &interface.enums {
+            let path = format!("{}.{}", interface.name, enumeration.name);
+            check_name(&mut diagnostics, &path, &enumeration.name, &ident::pascal_case(&enumeration.name, options));
+            if !enumeration.inherited {
+                check_keys(&mut diagnostics, &path, &enumeration.extensions, options);
+                for entry in &enumeration.entries {
+                    check_keys(&mut diagnostics, &format!("{}.{}", path, entry.name), &entry.extensions, options);
+                }
+            }
+            if let Some(default) = &enumeration.default_entry {
+                if enumeration.default_entry().is_none() {
+                    diagnostics.push(Diagnostic::error(&path, format!("default entry `{}` does not exist", default)));
+                }
+            }
+            let (backing, range) = if protocol.is_signed_enum(enumeration) {
+                ("i32", i64::from(i32::MIN)..=i64::from(i32::MAX))
+            } else {
+                ("u32", 0..=i64::from(u32::MAX))
+            };
+            for entry in &enumeration.entries {
+                let entry_path = format!("{}.{}", path, entry.name);
+                // Entries are only in PascalCase, in which `self` becomes a keyword, as variants of Rust enums
+                let entry_name = crate::entry_name(enumeration, entry);
+                let converted = if enumeration.is_rust_enum(options) { ident::pascal_case(&entry_name, options) } else { entry_name };
+                check_name(&mut diagnostics, &entry_path, &entry.name, &converted);
+                match (&entry.alias_of, &entry.literal) {
+                    (Some(_), Some(_)) => diagnostics.push(Diagnostic::error(&entry_path, "entries cannot have both a value and be an alias")),
+                    (None, None) => diagnostics.push(Diagnostic::error(&entry_path, "entry has no value")),
+                    (Some(target), None) => match enumeration.entries.iter().find(|e| &e.name == target) {
+                        None => diagnostics.push(Diagnostic::error(&entry_path, format!("alias of `{}`, which does not exist", target))),
+                        Some(target) if target.alias_of.is_some() => diagnostics.push(Diagnostic::error(
+                            &entry_path,
+                            format!("alias of `{}`, which is itself an alias", target.name)
+                        )),
+                        Some(_) => ()
+                    },
+                    (None, Some(literal)) => if let Err(problem) = literal.evaluate(&protocol.constants) {
+                        diagnostics.push(Diagnostic::error(&entry_path, format!("invalid value, {}", problem)));
+                    }
+                }
+                if !range.contains(&entry.value) {
+                    // Flags are easier to recognise in hex, such as a flag shifted past the 32nd bit
+                    let value = if enumeration.bitfield && entry.value > 0 {
+                        format!("{:#x}", entry.value)
+                    } else {
+                        entry.value.to_string()
+                    };
+                    let written = match entry.literal.as_ref().filter(|_| entry.alias_of.is_none()) {
+                        Some(literal @ EntryValue::Expression(_)) => format!(" (`{}`)", literal),
+                        _ => String::new()
+                    };
+                    diagnostics.push(Diagnostic::error(&entry_path, format!(
+                        "value {}{} is out of range for the backing type `{}`, which is {} to {}{}",
+                        value,
+                        written,
+                        backing,
+                        range.start(),
+                        range.end(),
+                        if entry.value < 0 { ", negative values require the enum to be used by `int` arguments" } else { "" }
+                    )));
+                }
+            }
+            let int = protocol.enum_args(enumeration).any(Arg::is_int);
+            let uint = protocol.enum_args(enumeration).any(Arg::is_uint);
+            if int && uint {
+                diagnostics.push(Diagnostic::error(&path, "enum is referred to by both `int` and `uint` arguments"));
+            }
+            if enumeration.bitfield {
+                let offenders: Vec<_> = enumeration.entries.iter()
+                    // An alias has the value of its target, which is checked itself
+                    .filter(|e| !e.combination && e.alias_of.is_none() && e.value != 0 && !(e.value > 0 && e.value.count_ones() == 1))
+                    .map(|e| format!("`{}` ({})", e.name, e.value))
+                    .collect();
+                if !offenders.is_empty() {
+                    diagnostics.push(Diagnostic::error(&path, format!(
+                        "bitfield entries must be a single bit unless marked as a combination: {}",
+                        offenders.join(", ")
+                    )));
+                }
+            }
+            if options.rust_enums {
+                if enumeration.bitfield {
+                    diagnostics.push(Diagnostic::warning(&path, "bitfields cannot be generated as Rust enums"));
+                } else if enumeration.has_aliases() {
+                    diagnostics.push(Diagnostic::warning(&path, "enums with entries sharing a value cannot be generated as Rust enums"));
+                }
+            }
+        }
+    }
+    lint::lint(&mut diagnostics, protocol, options);
+    if let Some(lockfile) = &options.lockfile {
+        diagnostics.extend(lockfile.check(protocol));
+    }
+    diagnostics
+}
+
+/// Check protocols that are generated together, with the same options, for names that collide: interfaces of the
+/// same name in the same [namespace](Protocol::namespace), and protocols generated into the same module.
+/// 
+/// Each protocol is still checked on its own by [`validate`].
+pub fn validate_protocols(protocols: &[Protocol], options: &CodegenOptions) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for (i, protocol) in protocols.iter().enumerate() {
+        let earlier = &protocols[..i];
+        let module = crate::module_path(protocol, options);
+        if let Some(other) = earlier.iter().find(|p| crate::module_path(p, options) == module) {
+            diagnostics.push(Diagnostic::error(&protocol.name, format!(
+                "generated into the module `{}`, as is the protocol `{}`", module, other.name
+            )));
+        }
+        for interface in &protocol.interfaces {
+            let other = earlier.iter()
+                .filter(|p| p.namespace == protocol.namespace)
+                .find(|p| p.interfaces.iter().any(|i| i.name == interface.name));
+            if let Some(other) = other {
+                let namespace = match &protocol.namespace {
+                    Some(namespace) => format!("the namespace `{}`", namespace),
+                    None => "no namespace".to_owned()
+                };
+                diagnostics.push(Diagnostic::error(&interface.name, format!(
+                    "declared by both the protocols `{}` and `{}` in {}", other.name, protocol.name, namespace
+                )));
+            }
+        }
+    }
+    diagnostics
+}
+
+/// Check that a trait generated alongside the trait of an interface, such as its dispatch trait, is not named the same
+/// as a trait of another interface.
+fn check_companion_trait(diagnostics: &mut Vec<Diagnostic>, protocol: &Protocol, interface: &Interface, kind: &str, name: String, options: &CodegenOptions) {
+    let other = protocol.interfaces.iter()
+        .filter(|i| i.name != interface.name && !options.core_interfaces.contains_key(&i.name))
+        .find(|i| trait_names(protocol, i, options).contains(&name));
+    if let Some(other) = other {
+        diagnostics.push(Diagnostic::error(&interface.name, format!(
+            "the {} trait `{}` has the same name as a trait of `{}`", kind, name, other.name
+        )));
+    }
+}
+
+/// The names of the traits generated for an interface that is not a core interface.
+fn trait_names(protocol: &Protocol, interface: &Interface, options: &CodegenOptions) -> Vec<String> {
+    let mut names = vec![
+        crate::trait_name(protocol, interface, options),
+        crate::events_trait_name(protocol, interface, options),
+        crate::dispatch_trait_name(protocol, interface, options)
+    ];
+    if options.ext_traits && !interface.events.is_empty() {
+        names.push(crate::ext_trait_name(protocol, interface, options));
+    }
+    names
+}
+
+/// Check that the [extension trait](CodegenOptions::ext_traits) of an interface is not named the same as the trait of
+/// another interface, and that its methods are not shadowed by the methods of `Lease`.
+fn check_ext_trait(diagnostics: &mut Vec<Diagnostic>, protocol: &Protocol, interface: &Interface, options: &CodegenOptions) {
+    check_companion_trait(diagnostics, protocol, interface, "extension", crate::ext_trait_name(protocol, interface, options), options);
+    for event in &interface.events {
+        let method = ident::snake_case(&event.name);
+        if LEASE_METHODS.contains(&method.as_str()) {
+            diagnostics.push(Diagnostic::error(format!("{}.{}", interface.name, event.name), format!(
+                "the method `{}` of the extension trait is shadowed by `Lease::{}`", method, method
+            )));
+        }
+    }
+}
+
+/// Methods of `Lease`, which take precedence over the methods of an extension trait of the same name.
+const LEASE_METHODS: &[&str] = &["id", "interface", "version", "into_any", "downcast"];
+
+/// Check that an interface extends an interface of the protocol, without it leading back to the interface.
+fn check_extends(diagnostics: &mut Vec<Diagnostic>, protocol: &Protocol, interface: &Interface, parent: &str) {
+    let mut chain = vec![interface.name.as_str()];
+    let mut next = Some(parent);
+    while let Some(name) = next {
+        chain.push(name);
+        if name == interface.name {
+            diagnostics.push(Diagnostic::error(&interface.name, format!("extends itself through `{}`", chain.join("` → `"))));
+            return
+        }
+        match protocol.interfaces.iter().find(|i| i.name == name) {
+            Some(parent) => next = parent.extends.as_deref(),
+            None if name == parent => {
+                diagnostics.push(Diagnostic::error(&interface.name, format!("extends `{}`, which does not exist", parent)));
+                return
+            },
+            // The interface that extends a missing interface reports it
+            None => return
+        }
+        // A cycle that does not include this interface is reported by the interfaces in it
+        if chain.len() > protocol.interfaces.len() + 1 {
+            return
+        }
+    }
+}
+
+/// Report keys that are not part of the format, other than extensions starting with `x-`, as errors if the options
+/// are [strict](CodegenOptions::strict).
+fn check_keys(diagnostics: &mut Vec<Diagnostic>, path: &str, extensions: &BTreeMap<String, toml::Value>, options: &CodegenOptions) {
+    for key in extensions.keys().filter(|key| !key.starts_with("x-")) {
+        let message = format!("unknown key `{}`, keys for other tools must start with `x-`", key);
+        diagnostics.push(if options.strict {
+            Diagnostic::error(path, message)
+        } else {
+            Diagnostic::warning(path, message)
+        });
+    }
+}
+
+/// Report an argument referring to an interface, or to an enum of an interface, that is neither an interface of the
+/// protocol nor one of its [`externals`](Protocol::externals), suggesting the closest interface for a typo.
+/// 
+/// References are only errors when [`strict`](CodegenOptions::strict), as specifications written before `externals`
+/// refer to interfaces of other protocols without listing them.
+fn check_references(diagnostics: &mut Vec<Diagnostic>, protocol: &Protocol, interface: &Interface, path: &str, arg: &Arg, options: &CodegenOptions) {
+    let mut report = |message: String| diagnostics.push(if options.strict {
+        Diagnostic::error(path, message)
+    } else {
+        Diagnostic::warning(path, message)
+    });
+    if let Some(target) = arg.interface.as_deref().filter(|target| protocol.interface(target).is_none() && !protocol.is_external(target)) {
+        report(format!("refers to the interface `{}`{}", target, unknown_interface(protocol, target)));
+    }
+    if let Some(name) = &arg.enumeration {
+        match name.split_once('.') {
+            Some((owner, _)) if protocol.is_external(owner) => (),
+            Some((owner, _)) if protocol.interface(owner).is_none() => report(format!(
+                "refers to the enum `{}` of the interface `{}`{}", name, owner, unknown_interface(protocol, owner)
+            )),
+            _ if protocol.find_enum(interface, name).is_none() => report(format!("refers to the enum `{}`, which is not defined", name)),
+            _ => ()
+        }
+    }
+}
+
+/// Explain that an interface is unknown, suggesting the closest known interface if it is plausibly a typo.
+fn unknown_interface(protocol: &Protocol, name: &str) -> String {
+    let known = protocol.interfaces.iter().map(|i| i.name.as_str()).chain(protocol.externals.iter().map(String::as_str));
+    let closest = known.map(|known| (edit_distance(name, known), known)).min().filter(|(distance, _)| *distance <= 2);
+    let mut message = ", which is neither an interface of the protocol nor listed in `externals`".to_owned();
+    if let Some((_, closest)) = closest {
+        message += &format!(", did you mean `{}`?", closest);
+    }
+    message
+}
+
+/// Report a name that cannot be made into an identifier once converted, or that is renamed as it would be a keyword,
+/// see [`ident`](crate::ident).
+fn check_name(diagnostics: &mut Vec<Diagnostic>, path: &str, name: &str, converted: &str) {
+    if let Some(fallback) = ident::keyword_fallback(converted) {
+        diagnostics.push(Diagnostic::warning(path, format!(
+            "the name `{}` becomes the keyword `{}`, which cannot be an identifier, so `{}` is generated instead", name, converted, fallback
+        )));
+    } else if let Some(problem) = ident::problem(converted) {
+        diagnostics.push(Diagnostic::error(path, format!("the name `{}` {}", name, problem)));
+    }
+}
+
+/// Report a name that is not in the vocabulary of a field, suggesting the closest valid names, returning whether the
+/// name is valid.
+fn check_vocabulary<T: Vocabulary>(diagnostics: &mut Vec<Diagnostic>, path: &str, name: &str) -> bool {
+    if T::from_name(name).is_some() {
+        return true
+    }
+    let distances: Vec<_> = T::VALUES.iter().map(|(valid, _)| (edit_distance(name, valid), *valid)).collect();
+    let closest = distances.iter().map(|(distance, _)| *distance).min().unwrap_or(0);
+    // Suggest only names that are plausibly a typo, otherwise list all of them
+    let suggestions: Vec<_> = if closest <= 2.max(name.len() / 3) {
+        distances.iter().filter(|(distance, _)| *distance == closest).map(|(_, valid)| format!("`{}`", valid)).collect()
+    } else {
+        Vec::new()
+    };
+    let message = if suggestions.is_empty() {
+        let valid: Vec<_> = T::VALUES.iter().map(|(valid, _)| format!("`{}`", valid)).collect();
+        format!("unknown {} `{}`, expected one of {}", T::FIELD, name, valid.join(", "))
+    } else {
+        format!("unknown {} `{}`, did you mean {}?", T::FIELD, name, suggestions.join(" or "))
+    };
+    diagnostics.push(Diagnostic::error(path, message));
+    false
+}
+
+/// The Levenshtein distance between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
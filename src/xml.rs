@@ -0,0 +1,328 @@
+//! Reading the XML protocol specifications used by Wayland.
+//!
+//! The XML is parsed into a tree and mapped onto the TOML layout of a specification, which is then deserialized as
+//! usual so that both formats share their defaults. Only the parts of XML that protocol specifications use are
+//! supported: elements, attributes, text, comments, CDATA and the predefined and numeric entities.
+
+use std::fmt;
+
+use toml::value::{Table, Value};
+
+/// A problem with the XML of a specification.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct XmlError {
+    pub line: usize,
+    pub message: String
+}
+impl fmt::Display for XmlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+impl std::error::Error for XmlError {}
+
+struct Element {
+    name: String,
+    line: usize,
+    attributes: Vec<(String, String)>,
+    children: Vec<Element>,
+    text: String
+}
+impl Element {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|(n, _)| n == name).map(|(_, value)| value.as_str())
+    }
+    fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> + 'a {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+    fn error(&self, message: impl Into<String>) -> XmlError {
+        XmlError { line: self.line, message: message.into() }
+    }
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    position: usize
+}
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.source[self.position..]
+    }
+    fn line(&self) -> usize {
+        self.source[..self.position].matches('\n').count() + 1
+    }
+    fn error(&self, message: impl Into<String>) -> XmlError {
+        XmlError { line: self.line(), message: message.into() }
+    }
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+    /// Skip past `end`, failing with `what` if it never appears.
+    fn skip_past(&mut self, end: &str, what: &str) -> Result<&'a str, XmlError> {
+        match self.rest().find(end) {
+            Some(index) => {
+                let skipped = &self.rest()[..index];
+                self.position += index + end.len();
+                Ok(skipped)
+            },
+            None => Err(self.error(format!("unterminated {}", what)))
+        }
+    }
+    /// Skip the prolog, comments and processing instructions before the root element.
+    fn skip_misc(&mut self) -> Result<(), XmlError> {
+        loop {
+            self.skip_whitespace();
+            let rest = self.rest();
+            if rest.starts_with("<?") {
+                self.skip_past("?>", "processing instruction")?;
+            } else if rest.starts_with("<!--") {
+                self.skip_past("-->", "comment")?;
+            } else if rest.starts_with("<!") {
+                self.skip_past(">", "declaration")?;
+            } else {
+                return Ok(())
+            }
+        }
+    }
+    fn name(&mut self) -> Result<&'a str, XmlError> {
+        let rest = self.rest();
+        let len = rest.find(|c: char| c.is_whitespace() || matches!(c, '/' | '>' | '=')).unwrap_or(rest.len());
+        if len == 0 {
+            return Err(self.error("expected a name"))
+        }
+        self.position += len;
+        Ok(&rest[..len])
+    }
+    fn element(&mut self) -> Result<Element, XmlError> {
+        let line = self.line();
+        if !self.rest().starts_with('<') {
+            return Err(self.error("expected an element"))
+        }
+        self.position += 1;
+        let name = self.name()?.to_owned();
+        let mut element = Element { name, line, attributes: Vec::new(), children: Vec::new(), text: String::new() };
+        loop {
+            self.skip_whitespace();
+            let rest = self.rest();
+            if rest.starts_with("/>") {
+                self.position += 2;
+                return Ok(element)
+            } else if rest.starts_with('>') {
+                self.position += 1;
+                break
+            }
+            let attribute = self.name()?.to_owned();
+            self.skip_whitespace();
+            if !self.rest().starts_with('=') {
+                return Err(self.error(format!("expected a value for the attribute `{}`", attribute)))
+            }
+            self.position += 1;
+            self.skip_whitespace();
+            let quote = match self.rest().chars().next() {
+                Some(quote @ ('"' | '\'')) => quote,
+                _ => return Err(self.error(format!("expected a quoted value for the attribute `{}`", attribute)))
+            };
+            self.position += 1;
+            let line = self.line();
+            let value = self.skip_past(&quote.to_string(), "attribute value")?;
+            let value = unescape(value).map_err(|message| XmlError { line, message })?;
+            element.attributes.push((attribute, value));
+        }
+        loop {
+            let rest = self.rest();
+            if rest.starts_with("</") {
+                self.position += 2;
+                let name = self.name()?;
+                if name != element.name {
+                    return Err(self.error(format!("expected `</{}>`, found `</{}>`", element.name, name)))
+                }
+                self.skip_whitespace();
+                self.skip_past(">", "end tag")?;
+                return Ok(element)
+            } else if rest.starts_with("<!--") {
+                self.skip_past("-->", "comment")?;
+            } else if rest.starts_with("<![CDATA[") {
+                self.position += "<![CDATA[".len();
+                let text = self.skip_past("]]>", "CDATA section")?;
+                element.text.push_str(text);
+            } else if rest.starts_with('<') {
+                let child = self.element()?;
+                element.children.push(child);
+            } else if rest.is_empty() {
+                return Err(self.error(format!("unterminated element `{}`", element.name)))
+            } else {
+                let line = self.line();
+                let len = rest.find('<').unwrap_or(rest.len());
+                let text = unescape(&rest[..len]).map_err(|message| XmlError { line, message })?;
+                element.text.push_str(&text);
+                self.position += len;
+            }
+        }
+    }
+}
+
+/// Replace the predefined and numeric entities of XML with the characters they represent.
+fn unescape(text: &str) -> Result<String, String> {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        let end = rest[start..].find(';').ok_or_else(|| "unterminated entity".to_owned())? + start;
+        let entity = &rest[start + 1..end];
+        let c = match entity {
+            "lt" => '<',
+            "gt" => '>',
+            "amp" => '&',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => entity.strip_prefix("#x").map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse))
+                .and_then(|code| code.ok())
+                .and_then(char::from_u32)
+                .ok_or_else(|| format!("unknown entity `&{};`", entity))?
+        };
+        unescaped.push(c);
+        rest = &rest[end + 1..];
+    }
+    unescaped.push_str(rest);
+    Ok(unescaped)
+}
+
+/// Remove the indentation common to every line of text, along with leading and trailing blank lines.
+fn dedent(text: &str) -> Option<String> {
+    let lines: Vec<_> = text.lines().map(str::trim_end).collect();
+    let start = lines.iter().position(|line| !line.is_empty())?;
+    let end = lines.iter().rposition(|line| !line.is_empty())? + 1;
+    let lines = &lines[start..end];
+    // Indentation is counted in characters, as whitespace such as a no-break space is more than one byte
+    let indent = lines.iter()
+        .filter(|line| !line.is_empty())
+        .map(|line| line.chars().take_while(|c| c.is_whitespace()).count())
+        .min()
+        .unwrap_or(0);
+    Some(lines.iter().map(|line| line.char_indices().nth(indent).map_or("", |(i, _)| &line[i..])).collect::<Vec<_>>().join("\n"))
+}
+
+/// Mapping the XML tree onto the TOML layout of a specification.
+impl Element {
+    fn string(&self, table: &mut Table, attribute: &str, key: &str) {
+        if let Some(value) = self.attribute(attribute) {
+            table.insert(key.into(), Value::String(value.into()));
+        }
+    }
+    fn integer(&self, table: &mut Table, attribute: &str, key: &str) -> Result<(), XmlError> {
+        if let Some(value) = self.attribute(attribute) {
+            let parsed = match value.strip_prefix("0x") {
+                Some(hex) => i64::from_str_radix(hex, 16),
+                None => value.parse()
+            };
+            let parsed = parsed.map_err(|_| self.error(format!("`{}` is not an integer", value)))?;
+            table.insert(key.into(), Value::Integer(parsed));
+        }
+        Ok(())
+    }
+    fn flag(&self, table: &mut Table, attribute: &str, key: &str) {
+        if self.attribute(attribute) == Some("true") {
+            table.insert(key.into(), Value::Boolean(true));
+        }
+    }
+    /// The `summary` attribute, and the `description` child with its own summary.
+    fn description(&self, table: &mut Table) {
+        self.string(table, "summary", "summary");
+        if let Some(description) = self.children("description").next() {
+            if !table.contains_key("summary") {
+                description.string(table, "summary", "summary");
+            }
+            if let Some(text) = dedent(&description.text) {
+                table.insert("description".into(), Value::String(text));
+            }
+        }
+    }
+    fn array(&self, table: &mut Table, child: &str, key: &str, map: impl Fn(&Element) -> Result<Table, XmlError>) -> Result<(), XmlError> {
+        let values = self.children(child).map(|child| map(child).map(Value::Table)).collect::<Result<Vec<_>, _>>()?;
+        if !values.is_empty() {
+            table.insert(key.into(), Value::Array(values));
+        }
+        Ok(())
+    }
+    fn required(&self, attribute: &str) -> Result<&str, XmlError> {
+        self.attribute(attribute).ok_or_else(|| self.error(format!("`<{}>` is missing the attribute `{}`", self.name, attribute)))
+    }
+
+    fn protocol(&self) -> Result<Table, XmlError> {
+        if self.name != "protocol" {
+            return Err(self.error(format!("expected `<protocol>`, found `<{}>`", self.name)))
+        }
+        let mut table = Table::new();
+        table.insert("name".into(), Value::String(self.required("name")?.into()));
+        self.description(&mut table);
+        if let Some(copyright) = self.children("copyright").next().and_then(|c| dedent(&c.text)) {
+            table.insert("copyright".into(), Value::String(copyright));
+        }
+        self.array(&mut table, "interface", "interface", Self::interface)?;
+        Ok(table)
+    }
+    fn interface(&self) -> Result<Table, XmlError> {
+        let mut table = Table::new();
+        table.insert("name".into(), Value::String(self.required("name")?.into()));
+        self.required("version")?;
+        self.integer(&mut table, "version", "version")?;
+        self.description(&mut table);
+        self.array(&mut table, "enum", "enum", Self::enumeration)?;
+        self.array(&mut table, "request", "request", Self::message)?;
+        self.array(&mut table, "event", "event", Self::message)?;
+        Ok(table)
+    }
+    fn message(&self) -> Result<Table, XmlError> {
+        let mut table = Table::new();
+        table.insert("name".into(), Value::String(self.required("name")?.into()));
+        self.integer(&mut table, "since", "since")?;
+        if self.attribute("type") == Some("destructor") {
+            table.insert("destructor".into(), Value::Boolean(true));
+        }
+        self.description(&mut table);
+        self.array(&mut table, "arg", "arg", Self::arg)?;
+        Ok(table)
+    }
+    fn arg(&self) -> Result<Table, XmlError> {
+        let mut table = Table::new();
+        table.insert("name".into(), Value::String(self.required("name")?.into()));
+        table.insert("type".into(), Value::String(self.required("type")?.into()));
+        self.flag(&mut table, "allow-null", "allow-null");
+        self.string(&mut table, "interface", "interface");
+        self.string(&mut table, "enum", "enum");
+        self.string(&mut table, "summary", "summary");
+        Ok(table)
+    }
+    fn enumeration(&self) -> Result<Table, XmlError> {
+        let mut table = Table::new();
+        table.insert("name".into(), Value::String(self.required("name")?.into()));
+        self.integer(&mut table, "since", "since")?;
+        self.flag(&mut table, "bitfield", "bitfield");
+        self.description(&mut table);
+        self.array(&mut table, "entry", "entry", Self::entry)?;
+        Ok(table)
+    }
+    fn entry(&self) -> Result<Table, XmlError> {
+        let mut table = Table::new();
+        table.insert("name".into(), Value::String(self.required("name")?.into()));
+        self.required("value")?;
+        self.integer(&mut table, "value", "value")?;
+        self.integer(&mut table, "since", "since")?;
+        self.description(&mut table);
+        Ok(table)
+    }
+}
+
+/// Parse a Wayland XML specification into the TOML layout of a specification.
+pub(crate) fn parse(string: &str) -> Result<Value, XmlError> {
+    let mut parser = Parser { source: string, position: 0 };
+    parser.skip_misc()?;
+    let root = parser.element()?;
+    parser.skip_misc()?;
+    if !parser.rest().is_empty() {
+        return Err(parser.error("unexpected content after the root element"))
+    }
+    root.protocol().map(Value::Table)
+}
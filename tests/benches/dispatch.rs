@@ -0,0 +1,55 @@
+//! Criterion benchmarks of dispatching `rt_surface.damage` requests through the type-erased `dispatch` of the
+//! interface trait, which downcasts each object from `Lease<dyn Any>`, and through the dispatch function generated for
+//! a concrete type, which does not.
+use std::{io::Write, os::unix::net::UnixListener, time::{Duration, Instant}};
+
+use compile::{runtime_concrete::RtSurfaceDispatch, Damage};
+use criterion::{criterion_group, criterion_main, Criterion};
+use yutani::{lease::{Lease, Resident}, server::Client, wire::{EventLoop, Message, Stream}, Id};
+
+/// The requests read at once, which must fit in the receive buffer of a stream of 1024 words.
+const BATCH: u64 = 200;
+
+type Dispatch = fn(Lease<Damage>, &mut EventLoop<()>, &mut Client<()>, Message);
+
+/// Time `iters` `damage` requests dispatched to one object by `dispatch`, with the requests read from the socket
+/// outside of the measurement.
+fn damage(iters: u64, dispatch: Dispatch) -> Duration {
+    let path = std::env::temp_dir().join(format!("yutani-codegen-bench-dispatch-{}", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path).unwrap();
+    let mut client: Client<()> = Client::new(Stream::connect(&path).unwrap());
+    let (mut peer, _) = listener.accept().unwrap();
+    let mut event_loop = EventLoop::new(()).unwrap();
+    let mut object: Resident<Damage, (), Client<()>> = Damage::default().into_object(Id::new(3));
+    let request: Vec<u8> = [3u32, 16 << 16 | 1, 1, 2].iter().flat_map(|word| word.to_ne_bytes()).collect();
+
+    let mut elapsed = Duration::ZERO;
+    let mut remaining = iters;
+    while remaining > 0 {
+        let batch = remaining.min(BATCH);
+        peer.write_all(&request.repeat(batch as usize)).unwrap();
+        client.stream().recvmsg().unwrap();
+        let start = Instant::now();
+        for _ in 0..batch {
+            let message = client.stream().message().unwrap().unwrap();
+            dispatch(object.lease().unwrap(), &mut event_loop, &mut client, message);
+        }
+        elapsed += start.elapsed();
+        remaining -= batch;
+    }
+    let _ = std::fs::remove_file(&path);
+    elapsed
+}
+
+pub fn dispatch(c: &mut Criterion) {
+    c.bench_function("rt_surface.damage (type-erased)", |b| b.iter_custom(|iters| damage(iters, |this, event_loop, client, message| {
+        Damage::dispatch(this.into_any(), event_loop, client, message).unwrap()
+    })));
+    c.bench_function("rt_surface.damage (concrete type)", |b| b.iter_custom(|iters| damage(iters, |this, event_loop, client, message| {
+        Damage::dispatch_rt_surface(this, event_loop, client, message).unwrap()
+    })));
+}
+
+criterion_group!(benches, dispatch);
+criterion_main!(benches);
@@ -0,0 +1,54 @@
+//! Checks the `cargo:warning` lines that the build helpers print for the warnings of a specification.
+
+use std::path::Path;
+
+use yutani_codegen::{build::{cargo_warnings, WARNING_LIMIT}, validate, CodegenOptions, Diagnostic, LintLevel, Protocol};
+
+const SPEC: &str = r#"
+name = "acme"
+
+[[interface]]
+name = "acme_thing"
+version = 1
+
+[[interface.request]]
+name = "setValue"
+arg = ["value:uint"]
+"#;
+
+fn spec() -> &'static Path {
+    Path::new("protocol/acme.toml")
+}
+
+#[test]
+fn warnings_are_located() {
+    let protocol = Protocol::from_str(SPEC).unwrap();
+    let diagnostics = validate(&protocol, &CodegenOptions::default().lint(&["naming::snake_case"], LintLevel::Warn));
+    assert_eq!(cargo_warnings(spec(), &protocol, &diagnostics), [
+        "cargo:warning=protocol/acme.toml:9: acme_thing.setValue: the request name `setValue` is not snake_case, such as `set_value` [naming::snake_case]"
+    ]);
+}
+
+#[test]
+fn warnings_without_a_line_name_the_file() {
+    let protocol = Protocol::from_str(SPEC).unwrap();
+    let diagnostics = [
+        Diagnostic::warning("acme", "a warning\nover two lines"),
+        Diagnostic::warning("acme_thing.setValue.value", "a warning of an argument"),
+        Diagnostic::error("acme", "an error")
+    ];
+    assert_eq!(cargo_warnings(spec(), &protocol, &diagnostics), [
+        "cargo:warning=protocol/acme.toml: acme: a warning over two lines",
+        "cargo:warning=protocol/acme.toml:9: acme_thing.setValue.value: a warning of an argument"
+    ]);
+}
+
+#[test]
+fn warnings_are_capped() {
+    let protocol = Protocol::from_str(SPEC).unwrap();
+    let diagnostics: Vec<_> = (0..WARNING_LIMIT + 3).map(|i| Diagnostic::warning("acme", format!("warning {}", i))).collect();
+    let lines = cargo_warnings(spec(), &protocol, &diagnostics);
+    assert_eq!(lines.len(), WARNING_LIMIT + 1);
+    assert_eq!(lines[WARNING_LIMIT - 1], format!("cargo:warning=protocol/acme.toml: acme: warning {}", WARNING_LIMIT - 1));
+    assert_eq!(lines[WARNING_LIMIT], "cargo:warning=protocol/acme.toml: 3 more warnings not shown, see `yutani_codegen::validate`");
+}
@@ -0,0 +1,86 @@
+//! A specification with an item in every position that a name or description is accepted, shared by the tests that
+//! check each position.
+#![allow(dead_code)]
+
+/// The TOML values written in each position, such as `"\"acme_thing\""` as the name of the interface.
+pub struct Spec {
+    pub interface: String,
+    pub request: String,
+    pub event: String,
+    pub enumeration: String,
+    pub entry: String,
+    pub arg: String,
+    /// The description of every item that has one, or none when `None`.
+    pub description: Option<String>
+}
+impl Default for Spec {
+    fn default() -> Self {
+        Self {
+            interface: "\"acme_thing\"".into(),
+            request: "\"go\"".into(),
+            event: "\"done\"".into(),
+            enumeration: "\"mode\"".into(),
+            entry: "\"on\"".into(),
+            arg: "\"x\"".into(),
+            description: None
+        }
+    }
+}
+impl Spec {
+    /// The name in one position, written as a TOML string.
+    pub fn named(position: &str, name: &str) -> Self {
+        let name = format!("{:?}", name);
+        let mut spec = Self::default();
+        match position {
+            "interface" => spec.interface = name,
+            "request" => spec.request = name,
+            "event" => spec.event = name,
+            "enum" => spec.enumeration = name,
+            "entry" => spec.entry = name,
+            "arg" => spec.arg = name,
+            position => unreachable!("unknown position `{}`", position)
+        }
+        spec
+    }
+    /// The same description in every position.
+    pub fn described(description: &str) -> Self {
+        Self { description: Some(description.into()), ..Self::default() }
+    }
+    pub fn toml(&self) -> String {
+        let Self { interface, request, event, enumeration, entry, arg, description } = self;
+        let description = description.as_ref().map(|description| format!("description = {description}")).unwrap_or_default();
+        format!(r#"
+            name = "acme"
+            {description}
+
+            [[const]]
+            name = "max"
+            type = "uint"
+            value = 1
+            {description}
+
+            [[interface]]
+            name = {interface}
+            version = 1
+            {description}
+
+            [[interface.request]]
+            name = {request}
+            arg = [{{ name = {arg}, type = "uint" }}]
+            {description}
+
+            [[interface.event]]
+            name = {event}
+            {description}
+
+            [[interface.enum]]
+            name = {enumeration}
+            {description}
+
+            [[interface.enum.entry]]
+            name = {entry}
+            value = 0
+            {description}
+        "#)
+    }
+}
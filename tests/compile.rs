@@ -0,0 +1,242 @@
+//! Checks that generated code compiles against the `yutani` runtime.
+//!
+//! This is slow and needs the runtime, so it only runs when `YUTANI_CODEGEN_COMPILE_TEST` is set to the dependency
+//! on `yutani` to check against, such as `{ path = "../yutani" }` or `"0.1"`. The code for `protocol/compile.toml`
+//! is generated with the default options and with most options enabled into a temporary crate, which is then checked
+//! with `cargo clippy` under `deny(missing_docs)` and with warnings denied, so that generated code stays free of
+//! Clippy's default lints, along with code using it as handlers would. The skeleton of a new specification and
+//! `fixtures/xdg-shell.xml` are generated into it with the default options too.
+//!
+//! The code for `protocol/runtime.toml` is generated alongside it with a few sets of options, and the tests in
+//! `runtime/` are copied into the crate and run against it, dispatching requests from a socket to the generated code
+//! and reading the events it sends back.
+//!
+//! If `YUTANI_CODEGEN_BENCH_TEST` is also set to the dependency on `criterion`, the benchmarks written by `benches`
+//! for both specifications and the benchmarks in `benches/` are added as bench targets, checked with the rest of the
+//! crate and run once each.
+//!
+//! The skeletons written by `scaffold` for each interface of `protocol/compile.toml` are checked with the code they
+//! implement the traits of, so that their signatures stay those of the generated traits.
+//!
+//! The skeleton written by `new_spec` is generated and checked alongside it, and is validated whether or not the
+//! runtime is available.
+
+use std::{env, fs, path::{Path, PathBuf}, process::Command};
+
+use yutani_codegen::{benches, new_spec, render, scaffold, validate, CodegenOptions, DispatchStrategy, LintLevel, Protocol};
+
+const RUNTIME: &str = "YUTANI_CODEGEN_COMPILE_TEST";
+const CRITERION: &str = "YUTANI_CODEGEN_BENCH_TEST";
+
+/// Code using the generated code, which must compile for it to be usable.
+const USAGE: &str = r#"
+/// An implementation of `rt_surface` with a dispatch function of its own, summing the damage it is sent.
+#[allow(missing_docs)]
+#[derive(Default)]
+pub struct Damage {
+    pub damaged: i32
+}
+impl runtime_concrete::RtSurface<()> for Damage {
+    fn destroy(_: yutani::lease::Lease<Self>, _: &mut yutani::wire::EventLoop<()>, _: &mut yutani::server::Client<()>) -> Result<(), yutani::wire::WlError<'static>> {
+        Ok(())
+    }
+    fn damage(mut this: yutani::lease::Lease<Self>, _: &mut yutani::wire::EventLoop<()>, _: &mut yutani::server::Client<()>, x: i32, y: i32) -> Result<(), yutani::wire::WlError<'static>> {
+        this.damaged = this.damaged.wrapping_add(x).wrapping_add(y);
+        Ok(())
+    }
+    fn set_title(_: yutani::lease::Lease<Self>, _: &mut yutani::wire::EventLoop<()>, _: &mut yutani::server::Client<()>, _: String) -> Result<(), yutani::wire::WlError<'static>> {
+        Ok(())
+    }
+    fn r#move(_: yutani::lease::Lease<Self>, _: &mut yutani::wire::EventLoop<()>, _: &mut yutani::server::Client<()>, _: yutani::Fixed, _: Vec<yutani::Fixed>) -> Result<(), yutani::wire::WlError<'static>> {
+        Ok(())
+    }
+}
+
+/// Enums compare with their raw values in either order.
+#[allow(missing_docs)]
+pub mod enum_comparisons {
+    macro_rules! newtypes {
+        ($module:ident) => {
+            pub fn $module(transform: i32, flags: u32) -> bool {
+                use crate::$module::wl_everything::{Flags, Transform};
+                if transform == Transform::NORMAL || Transform::TRANSFORM_90 == transform {
+                    return true
+                }
+                match flags {
+                    flags if flags == Flags::A => true,
+                    flags if Flags::B == flags => true,
+                    _ => Flags::BOTH != flags
+                }
+            }
+        };
+    }
+    newtypes!(default);
+    newtypes!(options);
+    newtypes!(rust_enums);
+
+    pub fn rust_enums_error(error: u32) -> bool {
+        use crate::rust_enums::wl_everything::Error;
+        match error {
+            error if error == Error::Unsupported => true,
+            error => Error::Unsupported != error
+        }
+    }
+}
+
+/// Objects are made and dispatched through a bound on the interface trait alone.
+#[allow(missing_docs)]
+pub mod objects {
+    use yutani::{lease::Resident, server::Client, Id};
+    macro_rules! objects {
+        ($module:ident, $trait:ident) => {
+            pub fn $module<I: crate::$module::$trait<()>>(object: I, id: Id) -> Result<Resident<I, (), Client<()>>, yutani::wire::WlError<'static>> {
+                assert_eq!(I::IMPLEMENTATION.descriptor.name, I::INTERFACE);
+                let _ = I::dispatch;
+                object.into_versioned_object(id, I::VERSION)
+            }
+        };
+    }
+    objects!(default, WlEverything);
+    objects!(options, WlEverythingRequests);
+    objects!(rust_enums, WlEverythingRequests);
+}
+"#;
+
+#[test]
+fn generated_code_compiles() {
+    let Ok(runtime) = env::var(RUNTIME) else {
+        eprintln!("skipping, set {RUNTIME} to the yutani dependency to check generated code against");
+        return
+    };
+    let protocol = Protocol::load(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/protocol/compile.toml")).unwrap();
+
+    let mut options = CodegenOptions::default().allow(&["dead_code"]);
+    options.validate_enums = true;
+    options.strip_unstable_prefix = true;
+    options.fixed_as_f64 = true;
+    options.split_traits = true;
+    options.args_struct_threshold = Some(4);
+    options.event_structs = true;
+    options.erased_senders = true;
+    options.embed_spec = true;
+    options.ext_traits = true;
+    options.source_markers = true;
+    options.dispatch_strategy = DispatchStrategy::Table;
+    options.intercept_requests = true;
+    options.generic_param = Some("State".into());
+    options.generic_bounds = Some("'static + ::core::default::Default".into());
+    options.dispatch_metrics = Some("feature = \"metrics\"".into());
+    let variants = [
+        ("default", CodegenOptions::default().allow(&["dead_code"])),
+        ("options", options.clone()),
+        ("rust_enums", CodegenOptions { rust_enums: true, ..options })
+    ];
+
+    let runtime_protocol = Protocol::load(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/protocol/runtime.toml")).unwrap();
+    let mut runtime_options = CodegenOptions::default().allow(&["dead_code"]);
+    runtime_options.dispatch_metrics = Some("feature = \"metrics\"".into());
+    let runtime_variants = [
+        ("runtime", runtime_options.clone()),
+        ("runtime_table", CodegenOptions { dispatch_strategy: DispatchStrategy::Table, ..runtime_options.clone() }),
+        ("runtime_enums", CodegenOptions { rust_enums: true, ..runtime_options.clone() }),
+        ("runtime_f64", CodegenOptions { fixed_as_f64: true, ..runtime_options.clone() }),
+        ("runtime_concrete", CodegenOptions { concrete_types: [("rt_surface".into(), "crate::Damage".into())].into(), ..runtime_options })
+    ];
+
+    let dir = env::temp_dir().join(format!("yutani-codegen-compile-{}", std::process::id()));
+    fs::create_dir_all(dir.join("src")).unwrap();
+    let mut manifest = format!(
+        "[package]\nname = \"compile\"\nversion = \"0.0.0\"\nedition = \"2021\"\n\n[features]\ndefault = [\"metrics\"]\nmetrics = []\n\n[dependencies]\nyutani = {}\n\n[workspace]\n",
+        runtime
+    );
+    let criterion = env::var(CRITERION).ok();
+    if let Some(criterion) = &criterion {
+        // Fixed point numbers are exposed as `f64` so that the events with them are benchmarked too
+        let benchmarks = [("options", &protocol, &variants[1].1), ("runtime_f64", &runtime_protocol, &runtime_variants[3].1)];
+        fs::create_dir_all(dir.join("benches")).unwrap();
+        manifest += &format!("\n[dev-dependencies]\ncriterion = {}\n", criterion);
+        for (name, protocol, options) in benchmarks {
+            fs::write(dir.join("benches").join(format!("{name}.rs")), benches(protocol, &format!("compile::{name}"), options).unwrap()).unwrap();
+            manifest += &format!("\n[[bench]]\nname = \"{name}\"\nharness = false\n");
+        }
+        for entry in fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/benches")).unwrap() {
+            let path = entry.unwrap().path();
+            fs::copy(&path, dir.join("benches").join(path.file_name().unwrap())).unwrap();
+            manifest += &format!("\n[[bench]]\nname = \"{}\"\nharness = false\n", path.file_stem().unwrap().to_str().unwrap());
+        }
+    }
+    fs::write(dir.join("Cargo.toml"), manifest).unwrap();
+    let mut lib = String::from("//! Generated code for the specifications in `protocol/`.\n#![deny(missing_docs)]\n");
+    for (name, options) in &variants {
+        fs::write(dir.join("src").join(format!("{name}.rs")), render(&protocol, options).unwrap()).unwrap();
+        lib += &format!("pub mod {name};\n");
+    }
+    for (name, options) in &runtime_variants {
+        fs::write(dir.join("src").join(format!("{name}.rs")), render(&runtime_protocol, options).unwrap()).unwrap();
+        lib += &format!("pub mod {name};\n");
+    }
+    // Scaffolds implement the traits generated with the same options as is
+    for (name, options) in &variants {
+        for interface in &protocol.interfaces {
+            let module = format!("scaffold_{name}_{}", interface.name);
+            let source = scaffold(&protocol, &interface.name, "Scaffold", &format!("crate::{name}"), options).unwrap();
+            fs::write(dir.join("src").join(format!("{module}.rs")), source).unwrap();
+            lib += &format!("#[allow(dead_code)]\nmod {module};\n");
+        }
+    }
+    copy_dir(&PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/runtime")), &dir.join("tests"));
+    // The skeleton of a new specification generates code that compiles as is
+    let template = Protocol::from_str(&new_spec("compile_template")).unwrap();
+    fs::write(dir.join("src/template.rs"), render(&template, &CodegenOptions::default().allow(&["dead_code"])).unwrap()).unwrap();
+    lib += "pub mod template;\n";
+    // As does a protocol in use, imported from its XML
+    let xdg_shell = Protocol::load(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/xdg-shell.xml")).unwrap();
+    fs::write(dir.join("src/xdg_shell.rs"), render(&xdg_shell, &CodegenOptions::default().allow(&["dead_code"])).unwrap()).unwrap();
+    lib += "pub mod xdg_shell;\n";
+    lib += USAGE;
+    fs::write(dir.join("src/lib.rs"), lib).unwrap();
+
+    let target: PathBuf = [env!("CARGO_MANIFEST_DIR"), "target", "compile-test"].iter().collect();
+    let cargo = |args: &[&str]| Command::new(env::var("CARGO").unwrap_or_else(|_| "cargo".into()))
+        .args(args)
+        .env("CARGO_TARGET_DIR", &target)
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    let output = cargo(&["clippy", "--quiet", "--all-targets", "--message-format=short", "--", "-D", "warnings"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "generated code in {} failed to compile or has warnings:\n{}", dir.display(), stderr);
+    let output = cargo(&["test", "--quiet"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "tests of generated code in {} failed:\n{}\n{}", dir.display(), stdout, stderr);
+    if criterion.is_some() {
+        // Each benchmark is run once as a test, rather than measured
+        let output = cargo(&["bench", "--quiet", "--", "--test"]);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(output.status.success(), "benchmarks of generated code in {} failed:\n{}", dir.display(), stderr);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Copy a directory and everything within it.
+fn copy_dir(from: &Path, to: &Path) {
+    fs::create_dir_all(to).unwrap();
+    for entry in fs::read_dir(from).unwrap() {
+        let entry = entry.unwrap();
+        if entry.file_type().unwrap().is_dir() {
+            copy_dir(&entry.path(), &to.join(entry.file_name()));
+        } else {
+            fs::copy(entry.path(), to.join(entry.file_name())).unwrap();
+        }
+    }
+}
+
+#[test]
+fn new_spec_is_valid() {
+    let protocol = Protocol::from_str(&new_spec("acme_frobnicator")).unwrap();
+    // The skeleton is an example to follow, so keeps to the naming conventions
+    let options = CodegenOptions { strict: true, ..CodegenOptions::default() }.lint(&["naming"], LintLevel::Deny);
+    let diagnostics = validate(&protocol, &options);
+    assert!(diagnostics.is_empty(), "the skeleton of a new specification is not valid: {:?}", diagnostics);
+}
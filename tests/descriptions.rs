@@ -0,0 +1,29 @@
+//! Checks that descriptions written as arrays of paragraphs are documented the same as the same paragraphs written in
+//! one string, in every position a description is accepted.
+
+mod common;
+
+use common::Spec;
+use yutani_codegen::{generate, CodegenOptions, Protocol};
+
+const PARAGRAPHS: &[&str] = &["The first paragraph,\nover two lines.", "The second paragraph.", "- a list\n- of items"];
+
+#[test]
+fn paragraphs_are_documented_as_a_string() {
+    let options = CodegenOptions::default();
+    let string = Protocol::from_str(&Spec::described(&format!("\"\"\"\n{}\n\"\"\"", PARAGRAPHS.join("\n\n"))).toml()).unwrap();
+    let array = Protocol::from_str(&Spec::described(&format!("{:?}", PARAGRAPHS)).toml()).unwrap();
+    assert_eq!(array.description.as_deref(), Some(PARAGRAPHS.join("\n\n").as_str()));
+    assert_eq!(array.interfaces[0].enums[0].entries[0].description, array.description);
+    let generated = generate(&array, &options).unwrap().to_string();
+    assert!(generated.contains("\"The second paragraph.\""), "{}", generated);
+    assert_eq!(generate(&string, &options).unwrap().to_string(), generated);
+}
+
+#[test]
+fn descriptions_must_be_strings() {
+    let error = format!("{:?}", Protocol::from_str(&Spec::described("[1, 2]").toml()).unwrap_err());
+    assert!(error.contains("invalid type: integer `1`, expected a string"), "{}", error);
+    let error = format!("{:?}", Protocol::from_str(&Spec::described("1").toml()).unwrap_err());
+    assert!(error.contains("expected a description as a string or as an array of paragraphs"), "{}", error);
+}
@@ -0,0 +1,152 @@
+//! Snapshots of the doc comments of the crate and of an item for each combination of a summary and a description, so
+//! that separators and headers only appear next to the sections that are present, and of the doc aliases of items
+//! named differently from their protocol names.
+
+use proc_macro2::{Delimiter, TokenStream, TokenTree};
+use yutani_codegen::{generate, CodegenOptions, Protocol};
+
+/// A specification with `docs`, such as `summary = "A summary"`, as the docs of the protocol and of a constant.
+fn spec(docs: &str) -> Protocol {
+    Protocol::from_str(&format!(r#"
+        name = "acme"
+        {docs}
+
+        [[const]]
+        name = "max"
+        type = "uint"
+        value = 1
+        {docs}
+    "#)).unwrap()
+}
+
+/// The docs and doc aliases of an item, with its tokens up to its body, such as
+/// `pub const r#MAX : :: core :: primitive :: u32 = 1u32`.
+struct Item {
+    docs: Vec<String>,
+    aliases: Vec<String>,
+    tokens: String
+}
+
+/// Collect the inner doc lines of the crate, and the items with doc attributes at any depth of `tokens`.
+fn items(tokens: TokenStream, krate: &mut Vec<String>, items: &mut Vec<Item>) {
+    let mut docs = Vec::new();
+    let mut aliases = Vec::new();
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        match token {
+            TokenTree::Punct(p) if p.as_char() == '#' => {
+                let inner = tokens.next_if(|t| matches!(t, TokenTree::Punct(p) if p.as_char() == '!')).is_some();
+                let Some(TokenTree::Group(attribute)) = tokens.next() else { panic!("an attribute without brackets") };
+                let attribute: Vec<_> = attribute.stream().into_iter().collect();
+                match &attribute[..] {
+                    [TokenTree::Ident(doc), TokenTree::Punct(_), TokenTree::Literal(line)] if doc == "doc" => {
+                        let line = syn::parse_str::<syn::LitStr>(&line.to_string()).unwrap().value();
+                        if inner { krate.push(line) } else { docs.push(line) }
+                    },
+                    [TokenTree::Ident(doc), TokenTree::Group(alias)] if doc == "doc" => {
+                        let Some(TokenTree::Literal(alias)) = alias.stream().into_iter().last() else { panic!("a doc attribute without a value") };
+                        aliases.push(syn::parse_str::<syn::LitStr>(&alias.to_string()).unwrap().value());
+                    },
+                    _ => ()
+                }
+            },
+            TokenTree::Group(group) => self::items(group.stream(), &mut Vec::new(), items),
+            token if !docs.is_empty() || !aliases.is_empty() => {
+                let rest = std::iter::from_fn(|| tokens.next_if(|t| match t {
+                    TokenTree::Punct(p) => p.as_char() != '#' && p.as_char() != ';',
+                    TokenTree::Group(group) => group.delimiter() != Delimiter::Brace,
+                    _ => true
+                }));
+                let tokens: TokenStream = std::iter::once(token).chain(rest).collect();
+                items.push(Item { docs: std::mem::take(&mut docs), aliases: std::mem::take(&mut aliases), tokens: tokens.to_string() });
+            },
+            _ => ()
+        }
+    }
+}
+
+/// The doc lines of the crate and of the constant generated for a specification with `docs`.
+fn docs(docs: &str) -> (Vec<String>, Vec<String>) {
+    let (mut krate, mut generated) = (Vec::new(), Vec::new());
+    items(generate(&spec(docs), &CodegenOptions::default()).unwrap(), &mut krate, &mut generated);
+    let constant = generated.into_iter().find(|item| item.tokens.starts_with("pub const r#MAX :")).unwrap();
+    (krate, constant.docs)
+}
+
+#[test]
+fn summary_only() {
+    let (krate, constant) = docs(r#"summary = "A summary""#);
+    assert_eq!(krate, ["# acme", "", "A summary"]);
+    assert_eq!(constant, ["A summary"]);
+}
+
+#[test]
+fn description_only() {
+    let (krate, constant) = docs(r#"description = "A description,\n\nin two paragraphs""#);
+    assert_eq!(krate, ["# acme", "", "A description,", "", "in two paragraphs"]);
+    assert_eq!(constant, ["A description,", "", "in two paragraphs"]);
+}
+
+#[test]
+fn summary_and_description() {
+    let (krate, constant) = docs("summary = \"A summary\"\ndescription = \"A description\"");
+    assert_eq!(krate, ["# acme", "", "A summary", "", "A description"]);
+    assert_eq!(constant, ["A summary", "", "A description"]);
+}
+
+#[test]
+fn neither() {
+    let (krate, constant) = docs("");
+    assert_eq!(krate, ["# acme"]);
+    // Every item is documented, so one without docs is summarised by its name
+    assert_eq!(constant, ["Constant `max`"]);
+}
+
+#[test]
+fn aliases() {
+    let protocol = Protocol::from_str(r#"
+        name = "acme"
+
+        [[const]]
+        name = "max"
+        type = "uint"
+        value = 1
+
+        [[interface]]
+        name = "acme_thing"
+        version = 1
+
+        [[interface.request]]
+        name = "set_mode"
+        arg = [{ name = "mode", type = "uint", enum = "mode" }]
+
+        [[interface.event]]
+        name = "done"
+
+        [[interface.enum]]
+        name = "mode"
+        entry = [{ name = "on", value = 0 }, { name = "90", value = 1 }]
+    "#).unwrap();
+    let aliases = |options: &CodegenOptions| {
+        let mut generated = Vec::new();
+        items(generate(&protocol, options).unwrap(), &mut Vec::new(), &mut generated);
+        generated.into_iter()
+            .filter(|item| !item.aliases.is_empty())
+            .map(|item| format!("{}: {}", item.tokens.split(['(', ':', '<', '=']).next().unwrap().trim(), item.aliases.join(", ")))
+            .collect::<Vec<_>>()
+    };
+    let expected = |mode: &str, on: &str, rotated: &str| vec![
+        "pub const r#MAX: max".to_owned(),
+        // The module is already named `acme_thing`, so has no alias
+        "pub trait r#AcmeThing: acme_thing".to_owned(),
+        // Messages are already named the same, so only have the qualified alias
+        "fn r#set_mode: acme_thing.set_mode".to_owned(),
+        "fn r#done: acme_thing.done".to_owned(),
+        format!("{mode}: mode"),
+        format!("{on}: on"),
+        format!("{rotated}: 90")
+    ];
+    assert_eq!(aliases(&CodegenOptions::default()), expected("pub struct r#Mode", "pub const r#ON", "pub const r#MODE_90"));
+    let options = CodegenOptions { rust_enums: true, ..CodegenOptions::default() };
+    assert_eq!(aliases(&options), expected("pub enum r#Mode", "r#On", "r#Mode90"));
+}
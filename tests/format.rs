@@ -0,0 +1,47 @@
+//! Checks that formatting specifications only changes how they are written, and that comments are never dropped.
+
+use std::fs;
+
+use yutani_codegen::{format_spec, generate, new_spec, CodegenOptions, Error, Protocol};
+
+#[test]
+fn formatting_keeps_generated_code() {
+    let options = [CodegenOptions::default(), CodegenOptions { rust_enums: true, fixed_as_f64: true, ..CodegenOptions::default() }];
+    for entry in fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/protocol")).unwrap() {
+        let path = entry.unwrap().path();
+        let spec = fs::read_to_string(&path).unwrap();
+        let formatted = format_spec(&spec).unwrap();
+        assert_eq!(format_spec(&formatted).unwrap(), formatted, "formatting {} is not idempotent", path.display());
+        let (original, formatted) = (Protocol::from_str(&spec).unwrap(), Protocol::from_str(&formatted).unwrap());
+        for options in &options {
+            assert_eq!(
+                generate(&original, options).unwrap().to_string(),
+                generate(&formatted, options).unwrap().to_string(),
+                "formatting {} changed the generated code", path.display()
+            );
+        }
+    }
+}
+
+#[test]
+fn comments_are_not_dropped() {
+    let spec = r#"# The protocol
+name = "acme" # trailing
+summary = "A # in a string"
+description = """
+# A heading in a description,
+over lines
+"""
+
+[[interface]] # an interface
+name = 'acme_#thing'
+version = 1
+"#;
+    let Err(Error::Comments(lines)) = format_spec(spec) else { panic!("formatted a specification with comments") };
+    assert_eq!(lines, [1, 2, 9]);
+    // The skeleton of a new specification is commented until it is written
+    assert!(matches!(format_spec(&new_spec("acme")), Err(Error::Comments(_))));
+
+    let formatted = format_spec(&spec.replace("# The protocol\n", "").replace(" # trailing", "").replace(" # an interface", "")).unwrap();
+    assert!(formatted.contains("A # in a string") && formatted.contains("# A heading in a description,"), "{}", formatted);
+}
@@ -0,0 +1,35 @@
+//! Checks that a specification generates the same code whichever format it is written in, and whether or not it has
+//! a byte order mark and CRLF line endings.
+
+use yutani_codegen::{generate, CodegenOptions, Protocol};
+
+fn fixture(name: &str) -> Protocol {
+    Protocol::load(format!("{}/tests/fixtures/{name}", env!("CARGO_MANIFEST_DIR"))).unwrap()
+}
+fn options() -> [CodegenOptions; 2] {
+    [CodegenOptions::default(), CodegenOptions { rust_enums: true, fixed_as_f64: true, ..CodegenOptions::default() }]
+}
+
+#[test]
+#[cfg(all(feature = "json", feature = "yaml"))]
+fn formats_generate_the_same() {
+    let (toml, json, yaml) = (fixture("formats.toml"), fixture("formats.json"), fixture("formats.yaml"));
+    for options in &options() {
+        let generated = generate(&toml, options).unwrap().to_string();
+        assert_eq!(generate(&json, options).unwrap().to_string(), generated, "JSON generated different code to TOML");
+        assert_eq!(generate(&yaml, options).unwrap().to_string(), generated, "YAML generated different code to TOML");
+    }
+}
+
+#[test]
+fn bom_and_crlf_are_ignored() {
+    let spec = include_bytes!("fixtures/crlf.toml");
+    assert!(spec.starts_with("\u{feff}".as_bytes()) && spec.windows(2).any(|w| w == b"\r\n"), "the fixture lost its BOM or CRLF");
+    let (crlf, lf) = (fixture("crlf.toml"), fixture("formats.toml"));
+    assert_eq!(crlf.description, lf.description);
+    for options in &options() {
+        let generated = generate(&crlf, options).unwrap().to_string();
+        assert!(!generated.contains("\\r"), "a carriage return made it into the docs");
+        assert_eq!(generated, generate(&lf, options).unwrap().to_string());
+    }
+}
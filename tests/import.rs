@@ -0,0 +1,30 @@
+//! Checks that importing a Wayland XML specification keeps its meaning, so that the imported TOML generates the same
+//! code as the XML it came from.
+
+use yutani_codegen::{generate, CodegenOptions, Protocol};
+
+const XDG_SHELL: &str = include_str!("fixtures/xdg-shell.xml");
+
+#[test]
+fn xdg_shell_round_trip() {
+    let xml = Protocol::from_xml(XDG_SHELL).unwrap();
+    let toml = xml.to_toml();
+    let imported = Protocol::from_str(&toml).unwrap();
+    assert_eq!(imported.to_toml(), toml, "importing is not idempotent");
+    let options = [CodegenOptions::default(), CodegenOptions { rust_enums: true, fixed_as_f64: true, ..CodegenOptions::default() }];
+    for options in &options {
+        assert_eq!(generate(&xml, options).unwrap().to_string(), generate(&imported, options).unwrap().to_string());
+    }
+}
+
+#[test]
+fn multibyte_indentation() {
+    // Both lines are indented by nine characters, but a no-break space is two bytes
+    let protocol = Protocol::from_xml("<protocol name=\"acme\">
+        <description summary=\"indented\">
+        \u{a0}Ünïcödé, indented the same
+         As indented
+        </description>
+    </protocol>").unwrap();
+    assert_eq!(protocol.description.as_deref(), Some("Ünïcödé, indented the same\nAs indented"));
+}
@@ -0,0 +1,54 @@
+//! Checks that names which become keywords that cannot be raw identifiers, such as an argument named `self`, are
+//! renamed with a trailing underscore and warned about in every position, rather than panicking while generating.
+
+mod common;
+
+use common::Spec;
+use yutani_codegen::{generate, validate, CodegenOptions, Protocol, Severity};
+
+const KEYWORDS: &[&str] = &["self", "Self", "super", "crate", "_"];
+
+/// A specification with `name` in one position, and how the name is converted in that position.
+fn spec(position: &str, name: &str) -> (String, fn(&str) -> String) {
+    let spec = Spec::named(position, name).toml();
+    // Entries are in PascalCase as variants of Rust enums, and enums are always in PascalCase
+    let converted: fn(&str) -> String = match position {
+        "enum" | "entry" => |name| name[..1].to_uppercase() + &name[1..].to_lowercase(),
+        _ => |name| name.to_lowercase()
+    };
+    (spec, converted)
+}
+
+#[test]
+fn keywords_are_renamed_in_every_position() {
+    let options = CodegenOptions { rust_enums: true, ..CodegenOptions::default() };
+    for position in ["interface", "request", "event", "enum", "entry", "arg"] {
+        for &keyword in KEYWORDS {
+            let (spec, converted) = spec(position, keyword);
+            let protocol = Protocol::from_str(&spec).unwrap();
+            let diagnostics = validate(&protocol, &options);
+            let generated = generate(&protocol, &options);
+            let context = format!("`{}` as the name of the {}: {:?}", keyword, position, diagnostics);
+            // `_` has nothing left once converted, as underscores only separate words
+            if keyword == "_" {
+                assert!(diagnostics.iter().any(|d| d.severity == Severity::Error && d.message.contains("has no characters")), "{}", context);
+                assert!(generated.is_err(), "{}", context);
+                continue
+            }
+            assert!(diagnostics.iter().all(|d| d.severity == Severity::Warning), "{}", context);
+            let generated = generated.unwrap().to_string();
+            let converted = converted(keyword);
+            if matches!(converted.as_str(), "self" | "Self" | "super" | "crate") {
+                let fallback = format!("{}_", converted);
+                let message = format!(
+                    "the name `{}` becomes the keyword `{}`, which cannot be an identifier, so `{}` is generated instead",
+                    keyword, converted, fallback
+                );
+                assert!(diagnostics.iter().any(|d| d.message == message), "{}", context);
+                assert!(generated.contains(&format!(" {} ", fallback)), "{}", context);
+            } else {
+                assert!(diagnostics.is_empty(), "{}", context);
+            }
+        }
+    }
+}
@@ -0,0 +1,89 @@
+//! Broadcasting an event sends the same messages as sending it from each object alone.
+
+use yutani::{lease::Resident, server::Client, Fixed, Id};
+
+#[macro_use]
+mod common;
+use common::Connection;
+
+mod surface {
+    surface!(runtime, ::yutani::Fixed);
+}
+mod surface_f64 {
+    surface!(runtime_f64, f64);
+}
+
+/// Fixed point numbers with these raw values, read from a request as they cannot be made otherwise.
+fn fixed(connection: &mut Connection, raw: &[u32]) -> Vec<Fixed> {
+    connection.request(1, 0, raw);
+    raw.iter().map(|_| connection.client.stream().fixed().unwrap()).collect()
+}
+
+#[test]
+fn same_as_alone() {
+    use compile::runtime::RtSurface;
+    use surface::Surface;
+
+    let mut alone = Connection::new();
+    let mut broadcast = Connection::new();
+    let mut first: Resident<Surface, (), Client<()>> = compile::runtime::RtSurfaceDispatch::into_object(Surface::default(), Id::new(3));
+    let mut second: Resident<Surface, (), Client<()>> = compile::runtime::RtSurfaceDispatch::into_object(Surface::default(), Id::new(4));
+    let mut first = first.lease().unwrap();
+    let mut second = second.lease().unwrap();
+    let values = fixed(&mut alone, &[256, 512, 768]);
+
+    for surface in [&mut first, &mut second] {
+        Surface::enter(surface, &mut alone.client, "title", &[1, 2, 3], 7).unwrap();
+        Surface::enter(surface, &mut alone.client, "", &[], 0).unwrap();
+        Surface::moved(surface, &mut alone.client, values[0], values[1..].to_vec()).unwrap();
+        Surface::scrolled(surface, &mut alone.client, 1, values[2], Some("wheel"), &[-1, 2]).unwrap();
+        Surface::scrolled(surface, &mut alone.client, 2, values[0], None, &[]).unwrap();
+        Surface::child(surface, &mut alone.client, Id::new(9)).unwrap();
+    }
+    let objects = [&first, &second];
+    Surface::enter_broadcast(objects, &mut broadcast.client, "title", &[1, 2, 3], 7).unwrap();
+    Surface::enter_broadcast(objects, &mut broadcast.client, "", &[], 0).unwrap();
+    Surface::moved_broadcast(objects, &mut broadcast.client, values[0], values[1..].to_vec()).unwrap();
+    Surface::scrolled_broadcast(objects, &mut broadcast.client, 1, values[2], Some("wheel"), &[-1, 2]).unwrap();
+    Surface::scrolled_broadcast(objects, &mut broadcast.client, 2, values[0], None, &[]).unwrap();
+    Surface::child_broadcast(objects, &mut broadcast.client, Id::new(9)).unwrap();
+
+    let sent = broadcast.events();
+    let mut expected = alone.events();
+    // Messages are grouped by event when broadcast, rather than by object
+    let messages = split(&expected);
+    expected = (0..6).flat_map(|event| [messages[event].clone(), messages[6 + event].clone()]).flatten().collect();
+    assert_eq!(sent, expected);
+    assert_eq!(&sent[..10], [3, 32 << 16 | 1, 8, u32::from_ne_bytes(*b"titl"), u32::from_ne_bytes(*b"e\0\0\0"), 4, u32::from_ne_bytes([1, 2, 3, 0]), 7, 4, 32 << 16 | 1]);
+}
+
+#[test]
+fn same_as_alone_f64() {
+    use compile::runtime_f64::RtSurface;
+    use surface_f64::Surface;
+
+    let mut alone = Connection::new();
+    let mut broadcast = Connection::new();
+    let mut first: Resident<Surface, (), Client<()>> = compile::runtime_f64::RtSurfaceDispatch::into_object(Surface::default(), Id::new(3));
+    let mut second: Resident<Surface, (), Client<()>> = compile::runtime_f64::RtSurfaceDispatch::into_object(Surface::default(), Id::new(4));
+    let mut first = first.lease().unwrap();
+    let mut second = second.lease().unwrap();
+
+    Surface::moved(&mut first, &mut alone.client, 1.5, &[2.0, -0.25]).unwrap();
+    Surface::moved(&mut second, &mut alone.client, 1.5, &[2.0, -0.25]).unwrap();
+    Surface::moved_broadcast([&first, &second], &mut broadcast.client, 1.5, &[2.0, -0.25]).unwrap();
+    let sent = broadcast.events();
+    assert_eq!(sent, alone.events());
+    assert_eq!(&sent[..6], [3, 24 << 16, 384, 8, 512, -64i32 as u32]);
+}
+
+/// Split words into messages by the size in their headers.
+fn split(mut words: &[u32]) -> Vec<Vec<u32>> {
+    let mut messages = Vec::new();
+    while !words.is_empty() {
+        let (message, rest) = words.split_at((words[1] >> 16) as usize / 4);
+        messages.push(message.to_vec());
+        words = rest;
+    }
+    messages
+}
@@ -0,0 +1,125 @@
+//! A client connected over a socket to a peer, to dispatch requests to generated code and read the events it sends.
+#![allow(dead_code)]
+
+use std::{
+    io::{ErrorKind, Read, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering}
+};
+
+use yutani::{server::Client, wire::{EventLoop, Message, Stream}};
+
+static CONNECTIONS: AtomicUsize = AtomicUsize::new(0);
+
+pub struct Connection {
+    pub client: Client<()>,
+    pub event_loop: EventLoop<()>,
+    peer: UnixStream,
+    path: PathBuf
+}
+impl Connection {
+    pub fn new() -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "yutani-codegen-runtime-{}-{}",
+            std::process::id(),
+            CONNECTIONS.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let client = Client::new(Stream::connect(&path).unwrap());
+        let (peer, _) = listener.accept().unwrap();
+        peer.set_nonblocking(true).unwrap();
+        Self { client, event_loop: EventLoop::new(()).unwrap(), peer, path }
+    }
+    /// Send a request from the peer, and read the header of the message from the client.
+    pub fn request(&mut self, object: u32, opcode: u16, args: &[u32]) -> Message {
+        let size = (args.len() as u32 + 2) * 4;
+        let words = [object, size << 16 | opcode as u32].into_iter().chain(args.iter().copied());
+        for word in words {
+            self.peer.write_all(&word.to_ne_bytes()).unwrap();
+        }
+        self.client.stream().recvmsg().unwrap();
+        self.client.stream().message().unwrap().unwrap()
+    }
+    /// The words of the events sent by the client since they were last read.
+    pub fn events(&mut self) -> Vec<u32> {
+        self.client.stream().sendmsg().unwrap();
+        let mut bytes = Vec::new();
+        let mut buf = [0; 4096];
+        loop {
+            match self.peer.read(&mut buf) {
+                Ok(0) => break,
+                Ok(read) => bytes.extend_from_slice(&buf[..read]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => panic!("failed to read events: {e}")
+            }
+        }
+        bytes.chunks(4).map(|word| u32::from_ne_bytes(word.try_into().unwrap())).collect()
+    }
+}
+impl Drop for Connection {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// The words of a string argument, with its length, terminating nul byte and padding.
+pub fn string(string: &str) -> Vec<u32> {
+    let mut bytes = string.as_bytes().to_vec();
+    bytes.push(0);
+    let len = bytes.len() as u32;
+    bytes.resize(bytes.len().next_multiple_of(4), 0);
+    std::iter::once(len).chain(bytes.chunks(4).map(|word| u32::from_ne_bytes(word.try_into().unwrap()))).collect()
+}
+
+/// Define `Surface`, implementing `RtSurface` of a module of the generated code by recording the requests it handles,
+/// along with any other items of the implementation, such as an event sender overriding the provided one.
+macro_rules! surface {
+    ($module:ident, $fixed:ty $(, $item:item)*) => {
+        #[derive(Default)]
+        pub struct Surface {
+            pub requests: Vec<String>
+        }
+        impl ::compile::$module::RtSurface<()> for Surface {
+            fn destroy(
+                mut this: ::yutani::lease::Lease<Self>,
+                _: &mut ::yutani::wire::EventLoop<()>,
+                _: &mut ::yutani::server::Client<()>
+            ) -> Result<(), ::yutani::wire::WlError<'static>> {
+                this.requests.push("destroy()".into());
+                Ok(())
+            }
+            fn damage(
+                mut this: ::yutani::lease::Lease<Self>,
+                _: &mut ::yutani::wire::EventLoop<()>,
+                _: &mut ::yutani::server::Client<()>,
+                x: i32,
+                y: i32
+            ) -> Result<(), ::yutani::wire::WlError<'static>> {
+                this.requests.push(format!("damage({x}, {y})"));
+                Ok(())
+            }
+            fn set_title(
+                mut this: ::yutani::lease::Lease<Self>,
+                _: &mut ::yutani::wire::EventLoop<()>,
+                _: &mut ::yutani::server::Client<()>,
+                title: String
+            ) -> Result<(), ::yutani::wire::WlError<'static>> {
+                this.requests.push(format!("set_title({title:?})"));
+                Ok(())
+            }
+            fn r#move(
+                mut this: ::yutani::lease::Lease<Self>,
+                _: &mut ::yutani::wire::EventLoop<()>,
+                _: &mut ::yutani::server::Client<()>,
+                x: $fixed,
+                points: Vec<$fixed>
+            ) -> Result<(), ::yutani::wire::WlError<'static>> {
+                this.requests.push(format!("move({x:?}, {points:?})"));
+                Ok(())
+            }
+            $($item)*
+        }
+    };
+}
@@ -0,0 +1,74 @@
+//! Events creating an object add it to the client with a new id, or fail without taking one or, if the event cannot be
+//! sent, without the client hearing of it.
+
+use compile::runtime::{RtChild, RtSurface, RtSurfaceDispatch};
+use yutani::{lease::Resident, server::Client, wire::WlError, Id};
+
+#[macro_use]
+mod common;
+use common::Connection;
+
+mod surface {
+    surface!(runtime, ::yutani::Fixed);
+}
+use surface::Surface;
+
+mod failing {
+    // The event cannot be sent, such as if the client had gone
+    surface!(runtime, ::yutani::Fixed, fn child(
+        _: &mut ::yutani::lease::Lease<Self>,
+        _: &mut ::yutani::server::Client<()>,
+        _: ::yutani::Id
+    ) -> Result<(), ::yutani::wire::WlError<'static>> {
+        Err(::yutani::wire::WlError::INTERNAL)
+    });
+}
+
+struct Child(u32);
+impl RtChild<()> for Child {}
+
+const FIRST_ID: u32 = 0xFF00_0000;
+
+#[test]
+fn created() {
+    let mut connection = Connection::new();
+    let mut surface: Resident<Surface, (), Client<()>> = Surface::default().into_versioned_object(Id::new(3), 1).unwrap();
+    let mut surface = surface.lease().unwrap();
+    let child = Surface::child_create(&mut surface, &mut connection.client, Child(7)).unwrap();
+    assert_eq!(child.0, 7);
+    assert_eq!(child.id(), Id::new(FIRST_ID));
+    assert_eq!(child.version(), 1);
+    assert!(connection.client.get_mut(Id::new(FIRST_ID)).is_some());
+    assert_eq!(connection.events(), [3, 12 << 16 | 2, FIRST_ID]);
+}
+
+#[test]
+fn unsupported_version() {
+    let mut connection = Connection::new();
+    // `rt_child` only has version 1, so cannot be created by an `rt_surface` of version 2
+    let mut surface: Resident<Surface, (), Client<()>> = Surface::default().into_versioned_object(Id::new(3), 2).unwrap();
+    let mut surface = surface.lease().unwrap();
+    let Err(error) = Surface::child_create(&mut surface, &mut connection.client, Child(7)) else {
+        panic!("an object was created with a version it does not support")
+    };
+    assert_eq!(error.error, WlError::UNSUPPORTED_VERSION.error);
+    assert_eq!(error.description, "rt_child: version is higher than the highest version of the interface");
+    assert!(connection.events().is_empty());
+    // No id was taken, so the next object gets the first id
+    assert_eq!(connection.client.new_id(), FIRST_ID);
+    assert!(connection.client.get_mut(Id::new(FIRST_ID)).is_none());
+}
+
+#[test]
+fn unsent() {
+    let mut connection = Connection::new();
+    let mut surface: Resident<failing::Surface, (), Client<()>> = failing::Surface::default().into_versioned_object(Id::new(3), 1).unwrap();
+    let mut surface = surface.lease().unwrap();
+    let Err(error) = failing::Surface::child_create(&mut surface, &mut connection.client, Child(7)) else {
+        panic!("an object was created without the event being sent")
+    };
+    assert_eq!(error.error, WlError::INTERNAL.error);
+    // The client was never told of the object, so is not told that it was deleted either
+    assert!(connection.client.get_mut(Id::new(FIRST_ID)).is_none());
+    assert!(connection.events().is_empty());
+}
@@ -0,0 +1,63 @@
+//! The dispatch strategies handle requests alike, including requests they reject.
+
+use yutani::{lease::Resident, server::Client, Id};
+
+#[macro_use]
+mod common;
+use common::{string, Connection};
+
+mod matched {
+    surface!(runtime, ::yutani::Fixed);
+}
+mod table {
+    surface!(runtime_table, ::yutani::Fixed);
+}
+
+/// Requests by opcode with their arguments, each sent on a connection of its own as a rejected request is left
+/// partly read.
+fn requests() -> Vec<(u16, Vec<u32>)> {
+    vec![
+        (1, vec![1, -2i32 as u32]),
+        (2, string("title")),
+        (3, vec![256, 8, 512, 768]),
+        // An array of fixed point numbers with a partial element
+        (3, vec![256, 3, 0]),
+        // An unknown opcode
+        (9, vec![1]),
+        (0, vec![])
+    ]
+}
+
+macro_rules! dispatch {
+    ($module:ident, $generated:ident) => {{
+        use compile::$generated::RtSurfaceDispatch as _;
+        let mut object: Resident<$module::Surface, (), Client<()>> = $module::Surface::default().into_object(Id::new(3));
+        let results: Vec<_> = requests().into_iter().map(|(opcode, args)| {
+            let mut connection = Connection::new();
+            let message = connection.request(3, opcode, &args);
+            $module::Surface::dispatch_typed(object.lease().unwrap(), &mut connection.event_loop, &mut connection.client, message)
+                .map_err(|e| (e.error, e.description.into_owned()))
+        }).collect();
+        (results, object.get().unwrap().requests.clone())
+    }};
+}
+
+#[test]
+fn strategies_agree() {
+    let (matched, matched_requests) = dispatch!(matched, runtime);
+    let (table, table_requests) = dispatch!(table, runtime_table);
+    assert_eq!(matched, table);
+    assert_eq!(matched_requests, table_requests);
+
+    assert_eq!(matched_requests, [
+        "damage(1, -2)",
+        "set_title(\"title\")",
+        "move(Fixed(256), [Fixed(512), Fixed(768)])",
+        "destroy()"
+    ]);
+    let errors: Vec<_> = matched.into_iter().filter_map(Result::err).collect();
+    assert_eq!(errors, [
+        (yutani::wire::WlError::CORRUPT.error, "rt_surface.move: argument `points` has a length that is not a multiple of 4".into()),
+        (yutani::wire::WlError::INVALID_OPCODE.error, "rt_surface: request opcode is not known".into())
+    ]);
+}
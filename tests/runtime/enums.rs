@@ -0,0 +1,77 @@
+//! Enums convert to and from the names of their entries.
+
+use compile::{runtime::rt_surface::{Capability, Rotation, Transform}, runtime_enums};
+
+#[test]
+fn names_round_trip() {
+    for &transform in Transform::ENTRIES {
+        let name = transform.as_str().unwrap();
+        assert_eq!(name.parse::<Transform>().unwrap(), transform);
+        assert_eq!(name.to_lowercase().parse::<Transform>().unwrap(), transform);
+        assert_eq!(transform.to_string(), name);
+    }
+    for &(name, alias) in Transform::ALIASES {
+        assert_eq!(name.parse::<Transform>().unwrap(), alias);
+    }
+    for &rotation in Rotation::ENTRIES {
+        assert_eq!(rotation.as_str().unwrap().parse::<Rotation>().unwrap(), rotation);
+    }
+    for rotation in runtime_enums::rt_surface::Rotation::iter() {
+        assert_eq!(rotation.as_str().unwrap().parse::<runtime_enums::rt_surface::Rotation>().unwrap(), rotation);
+    }
+}
+
+#[test]
+fn entries_starting_with_digits() {
+    // Constants cannot start with a digit, so are prefixed with the name of the enum, which is not needed to parse them
+    assert_eq!(Transform::TRANSFORM_90.as_str(), Some("TRANSFORM_90"));
+    assert_eq!("90".parse::<Transform>().unwrap(), Transform::TRANSFORM_90);
+    assert_eq!("TRANSFORM_90".parse::<Transform>().unwrap(), Transform::TRANSFORM_90);
+    assert_eq!("flipped_90".parse::<Transform>().unwrap(), Transform::FLIPPED_90);
+    assert_eq!("180".parse::<Rotation>().unwrap(), Rotation::ROTATION_180);
+
+    let rotation: runtime_enums::rt_surface::Rotation = "90".parse().unwrap();
+    assert_eq!(rotation, runtime_enums::rt_surface::Rotation::Rotation90);
+    assert_eq!(rotation.as_str(), Some("ROTATION_90"));
+}
+
+#[test]
+fn case_insensitive() {
+    for name in ["flipped_90", "FLIPPED_90", "Flipped_90", " flipped_90 "] {
+        assert_eq!(name.parse::<Transform>().unwrap(), Transform::FLIPPED_90, "{name:?}");
+    }
+    for name in ["transform_90", "Transform_90", "rotated", "ROTATED"] {
+        assert_eq!(name.parse::<Transform>().unwrap(), Transform::TRANSFORM_90, "{name:?}");
+    }
+    assert_eq!("rotation_180".parse::<runtime_enums::rt_surface::Rotation>().unwrap(), runtime_enums::rt_surface::Rotation::Rotation180);
+}
+
+#[test]
+fn unknown_names() {
+    for name in ["", "270", "transform_270", "flipped-90", "normal | flipped"] {
+        let error = name.parse::<Transform>().unwrap_err();
+        assert_eq!(error.value, name);
+        assert_eq!(error.enumeration, "transform");
+    }
+    assert!("270".parse::<runtime_enums::rt_surface::Rotation>().is_err());
+}
+
+#[test]
+fn bitfield_flags() {
+    let cases = [
+        (Capability::POINTER, "POINTER"),
+        (Capability::from(5), "POINTER | TOUCH"),
+        (Capability::from(7), "POINTER | KEYBOARD | TOUCH"),
+        // A combination with a name of its own is written as it
+        (Capability::from(3), "POINTER_AND_KEYBOARD"),
+        (Capability::from(0), "(empty)"),
+        (Capability::from(0x41), "POINTER | 0x40"),
+        (Capability::from(0x40), "0x40")
+    ];
+    for (capability, written) in cases {
+        assert_eq!(format!("{capability:?}"), written);
+        assert_eq!(capability.to_string(), written);
+    }
+    assert_eq!("pointer | touch".parse::<Capability>().unwrap(), Capability::from(5));
+    assert_eq!("POINTER_AND_KEYBOARD".parse::<Capability>().unwrap(), Capability::from(3));
+}
@@ -0,0 +1,68 @@
+//! Errors of the generated code are described as `interface.message: detail`, or `interface: detail`.
+
+use compile::runtime::{rt_surface, RtChild, RtChildDispatch, RtSurfaceDispatch};
+use yutani::{lease::Resident, server::Client, wire::WlError, Id};
+
+#[macro_use]
+mod common;
+use common::Connection;
+
+mod surface {
+    surface!(runtime, ::yutani::Fixed);
+}
+use surface::Surface;
+
+struct Child;
+impl RtChild<()> for Child {}
+
+fn error(error: WlError<'static>) -> (u32, String) {
+    (error.error, error.description.into_owned())
+}
+
+#[test]
+fn dispatched() {
+    let mut connection = Connection::new();
+    let mut surface: Resident<Surface, (), Client<()>> = Surface::default().into_object(Id::new(3));
+
+    let message = connection.request(3, 9, &[]);
+    let Err(e) = Surface::dispatch_typed(surface.lease().unwrap(), &mut connection.event_loop, &mut connection.client, message) else {
+        panic!("dispatched an unknown opcode")
+    };
+    assert_eq!(error(e), (WlError::INVALID_OPCODE.error, "rt_surface: request opcode is not known".into()));
+
+    let mut connection = Connection::new();
+    let message = connection.request(3, 3, &[256, 3, 0]);
+    let Err(e) = Surface::dispatch_typed(surface.lease().unwrap(), &mut connection.event_loop, &mut connection.client, message) else {
+        panic!("dispatched a misaligned array")
+    };
+    assert_eq!(error(e), (WlError::CORRUPT.error, "rt_surface.move: argument `points` has a length that is not a multiple of 4".into()));
+
+    // An object of another implementation is not dispatched to
+    let mut connection = Connection::new();
+    let mut child = Child.into_object(Id::new(4)).into_any();
+    let message = connection.request(4, 0, &[1, 2]);
+    let Err(e) = Surface::dispatch(child.lease().unwrap(), &mut connection.event_loop, &mut connection.client, message) else {
+        panic!("dispatched to another implementation")
+    };
+    assert_eq!(error(e), (WlError::INTERNAL.error, "rt_surface: object is not of the type whose implementation it was dispatched to".into()));
+}
+
+#[test]
+fn versioned() {
+    let Err(e) = Surface::default().into_versioned_object(Id::new(3), 3) else { panic!("created an object of an unsupported version") };
+    assert_eq!(error(e), (WlError::UNSUPPORTED_VERSION.error, "rt_surface: version is higher than the highest version of the interface".into()));
+    assert!(Surface::default().into_versioned_object(Id::new(3), 2).is_ok());
+}
+
+#[test]
+fn printed() {
+    let mut connection = Connection::new();
+    let message = connection.request(3, 9, &[]);
+    let e = rt_surface::debug_request(&message, connection.client.stream()).unwrap_err();
+    assert_eq!(error(e), (WlError::INVALID_OPCODE.error, "rt_surface: request opcode is not known".into()));
+
+    let mut connection = Connection::new();
+    let message = connection.request(3, 3, &[256, 3, 0]);
+    let e = rt_surface::debug_request(&message, connection.client.stream()).unwrap_err();
+    assert_eq!(error(e), (WlError::CORRUPT.error, "rt_surface.move: argument `points` has a length that is not a multiple of 4".into()));
+}
@@ -0,0 +1,43 @@
+//! Fixed point numbers exposed as `f64` are rounded to the nearest 1/256 and saturate when sent.
+
+use compile::runtime_f64::{RtSurface, RtSurfaceDispatch};
+use yutani::{lease::Resident, server::Client, Id};
+
+#[macro_use]
+mod common;
+use common::Connection;
+
+mod surface {
+    surface!(runtime_f64, f64);
+}
+use surface::Surface;
+
+/// Send `moved` with `x` and `points`, then receive its arguments back as a `move` request, which has the same
+/// signature.
+fn round_trip(x: f64, points: &[f64]) -> String {
+    let mut connection = Connection::new();
+    let mut surface: Resident<Surface, (), Client<()>> = Surface::default().into_object(Id::new(3));
+    Surface::moved(&mut surface.lease().unwrap(), &mut connection.client, x, points).unwrap();
+    let words = connection.events();
+    let message = connection.request(3, 3, &words[2..]);
+    Surface::dispatch_typed(surface.lease().unwrap(), &mut connection.event_loop, &mut connection.client, message).unwrap();
+    surface.get().unwrap().requests.concat()
+}
+
+#[test]
+fn round_trips() {
+    assert_eq!(round_trip(1.5, &[0.25, -2.0]), "move(1.5, [0.25, -2.0])");
+    assert_eq!(round_trip(-8388608.0, &[8388607.99609375]), "move(-8388608.0, [8388607.99609375])");
+}
+
+#[test]
+fn rounded() {
+    assert_eq!(round_trip(0.001, &[3.0 / 512.0, -3.0 / 512.0]), "move(0.0, [0.0078125, -0.0078125])");
+    assert_eq!(round_trip(1.0 / 1024.0 * 3.0, &[]), "move(0.00390625, [])");
+}
+
+#[test]
+fn saturated() {
+    assert_eq!(round_trip(1e10, &[-1e10, f64::INFINITY]), "move(8388607.99609375, [-8388608.0, 8388607.99609375])");
+    assert_eq!(round_trip(f64::NAN, &[f64::NEG_INFINITY]), "move(0.0, [-8388608.0])");
+}
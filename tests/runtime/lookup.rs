@@ -0,0 +1,54 @@
+//! Objects are leased from a client by id as an implementation of their interface.
+
+use compile::runtime::{rt_child, rt_surface, RtChild, RtChildDispatch, RtSurfaceDispatch};
+use yutani::{wire::WlError, Id};
+
+#[macro_use]
+mod common;
+use common::Connection;
+
+mod surface {
+    surface!(runtime, ::yutani::Fixed);
+}
+mod other {
+    surface!(runtime, ::yutani::Fixed);
+}
+use surface::Surface;
+
+struct Child;
+impl RtChild<()> for Child {}
+
+fn error(error: WlError<'static>) -> (u32, String) {
+    (error.error, error.description.into_owned())
+}
+
+#[test]
+fn leased_by_id() {
+    let mut connection = Connection::new();
+    let client = &mut connection.client;
+    client.insert(Surface { requests: vec!["damage(1, 2)".into()] }.into_object(Id::new(3)).into_any()).unwrap();
+    client.insert(Child.into_object(Id::new(4)).into_any()).unwrap();
+
+    let lease = rt_surface::lease::<(), Surface>(client, Id::new(3)).unwrap();
+    assert_eq!(lease.requests, ["damage(1, 2)"]);
+    let Err(e) = rt_surface::lease::<(), Surface>(client, Id::new(3)) else { panic!("leased an object twice") };
+    assert_eq!(error(e), (WlError::INTERNAL.error, "rt_surface: the object is already leased, such as by the request being dispatched".into()));
+    drop(lease);
+    assert!(rt_surface::lease::<(), Surface>(client, Id::new(3)).is_ok());
+    assert!(rt_child::lease::<(), Child>(client, Id::new(4)).is_ok());
+}
+
+#[test]
+fn rejected() {
+    let mut connection = Connection::new();
+    let client = &mut connection.client;
+    client.insert(Surface::default().into_object(Id::new(3)).into_any()).unwrap();
+    client.insert(Child.into_object(Id::new(4)).into_any()).unwrap();
+
+    let Err(e) = rt_surface::lease::<(), other::Surface>(client, Id::new(3)) else { panic!("leased another implementation") };
+    assert_eq!(error(e), (WlError::INTERNAL.error, "rt_surface: object is not of the type whose implementation it was leased as".into()));
+    let Err(e) = rt_surface::lease::<(), Surface>(client, Id::new(4)) else { panic!("leased an object of another interface") };
+    assert_eq!(error(e), (WlError::NO_OBJECT.error, "rt_surface: the object is of another interface".into()));
+    let Err(e) = rt_surface::lease::<(), Surface>(client, Id::new(9)) else { panic!("leased a missing object") };
+    assert_eq!(error(e), (WlError::NO_OBJECT.error, "rt_surface: no object has the id".into()));
+}
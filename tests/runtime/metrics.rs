@@ -0,0 +1,51 @@
+//! Requests dispatched to an interface are counted by opcode.
+//!
+//! The counts are shared by every test in the binary, so only this test dispatches requests here.
+
+use std::sync::atomic::Ordering;
+
+use compile::{runtime, runtime_table};
+use yutani::{lease::Resident, server::Client, Id};
+
+#[macro_use]
+mod common;
+use common::{string, Connection};
+
+mod matched {
+    surface!(runtime, ::yutani::Fixed);
+}
+mod table {
+    surface!(runtime_table, ::yutani::Fixed);
+}
+
+fn counts(counts: &[std::sync::atomic::AtomicU64]) -> Vec<u64> {
+    counts.iter().map(|count| count.load(Ordering::Relaxed)).collect()
+}
+
+#[test]
+fn request_counts() {
+    use runtime::RtSurfaceDispatch as _;
+    use runtime_table::RtSurfaceDispatch as _;
+
+    assert_eq!(runtime::rt_surface::request_counts().len(), runtime::rt_surface::REQUEST_NAMES.len());
+    assert_eq!(counts(runtime::rt_surface::request_counts()), [0, 0, 0, 0]);
+    assert!(runtime::rt_child::request_counts().is_empty());
+
+    let mut connection = Connection::new();
+    let mut matched: Resident<matched::Surface, (), Client<()>> = matched::Surface::default().into_object(Id::new(3));
+    let mut table: Resident<table::Surface, (), Client<()>> = table::Surface::default().into_object(Id::new(4));
+    let requests: [(u16, Vec<u32>); 4] = [(1, vec![1, 2]), (1, vec![3, 4]), (2, string("title")), (7, vec![])];
+    for (opcode, args) in &requests {
+        let message = connection.request(3, *opcode, args);
+        let result = matched::Surface::dispatch_typed(matched.lease().unwrap(), &mut connection.event_loop, &mut connection.client, message);
+        assert_eq!(result.is_ok(), *opcode != 7);
+    }
+    for (opcode, args) in &requests[..3] {
+        let message = connection.request(4, *opcode, args);
+        table::Surface::dispatch_typed(table.lease().unwrap(), &mut connection.event_loop, &mut connection.client, message).unwrap();
+    }
+
+    // Unknown opcodes have no count, as they have no name
+    assert_eq!(counts(runtime::rt_surface::request_counts()), [0, 2, 1, 0]);
+    assert_eq!(counts(runtime_table::rt_surface::request_counts()), [0, 2, 1, 0]);
+}
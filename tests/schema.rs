@@ -0,0 +1,196 @@
+//! Checks that the JSON Schema of the specification format describes the keys that the parser accepts.
+//!
+//! Every item of the format is built with every field set, so that adding a field fails to compile here until the
+//! schema is checked for it. The keys the items serialize to must be exactly the properties of the schema, apart from
+//! the plural aliases of arrays of tables, which must parse as the same keys.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde_json::Value;
+use yutani_codegen::*;
+
+/// The plural aliases of arrays of tables, which are not serialized.
+const ALIASES: &[(&str, &str)] = &[
+    ("constants", "const"),
+    ("interfaces", "interface"),
+    ("enums", "enum"),
+    ("entries", "entry"),
+    ("requests", "request"),
+    ("events", "event"),
+    ("args", "arg")
+];
+
+fn arg() -> Arg {
+    Arg {
+        name: "x".into(),
+        nullable: true,
+        type_name: "object".into(),
+        ty: DataType::Object,
+        interface: Some("wl_thing".into()),
+        enumeration: Some("mode".into()),
+        array_type_name: Some("uint".into()),
+        array_type: Some(ArrayType::Uint),
+        summary: Some("summary".into()),
+        extensions: BTreeMap::new()
+    }
+}
+
+fn protocol() -> Protocol {
+    let entry = Entry {
+        name: "a".into(),
+        since: Some(1),
+        summary: Some("summary".into()),
+        description: Some("description".into()),
+        literal: Some(EntryValue::Integer(1)),
+        value: 1,
+        alias_of: Some("b".into()),
+        combination: true,
+        extensions: BTreeMap::new()
+    };
+    let enumeration = Enum {
+        name: "mode".into(),
+        summary: Some("summary".into()),
+        description: Some("description".into()),
+        since: Some(1),
+        bitfield: true,
+        default_entry: Some("a".into()),
+        inherited: false,
+        line: None,
+        entries: vec![entry],
+        extensions: BTreeMap::new()
+    };
+    let request = Request {
+        name: "set".into(),
+        since: Some(1),
+        destructor: true,
+        unimplemented: true,
+        unimplemented_error: Some("a".into()),
+        capability: Some("capability".into()),
+        summary: Some("summary".into()),
+        description: Some("description".into()),
+        args: vec![arg()],
+        inherited: false,
+        line: None,
+        extensions: BTreeMap::new()
+    };
+    let event = Event {
+        name: "done".into(),
+        since: Some(1),
+        destructor: true,
+        summary: Some("summary".into()),
+        description: Some("description".into()),
+        args: vec![arg()],
+        inherited: false,
+        line: None,
+        extensions: BTreeMap::new()
+    };
+    let interface = Interface {
+        name: "wl_thing".into(),
+        summary: Some("summary".into()),
+        description: Some("description".into()),
+        version: 1,
+        extends: Some("wl_other".into()),
+        stability_name: Some("staging".into()),
+        stability: Some(Stability::Staging),
+        deprecated: true,
+        replaced_by: Some("wl_other".into()),
+        deprecation_note: Some("note".into()),
+        capability: Some("capability".into()),
+        enums: vec![enumeration],
+        requests: vec![request],
+        events: vec![event],
+        line: None,
+        extensions: BTreeMap::new()
+    };
+    let constant = Constant {
+        name: "max".into(),
+        summary: Some("summary".into()),
+        description: Some("description".into()),
+        type_name: "uint".into(),
+        ty: ConstType::Uint,
+        value: ConstValue::Integer(1),
+        extensions: BTreeMap::new()
+    };
+    Protocol {
+        name: "test".into(),
+        rust_module: Some("test".into()),
+        namespace: Some("acme".into()),
+        summary: Some("summary".into()),
+        description: Some("description".into()),
+        copyright: Some("copyright".into()),
+        stability_name: Some("unstable".into()),
+        stability: Stability::Unstable,
+        externals: vec!["wl_surface".into()],
+        constants: vec![constant],
+        interfaces: vec![interface],
+        source: None,
+        extensions: BTreeMap::new()
+    }
+}
+
+/// Record the keys of each table of `value`, by the definition of the schema describing it.
+fn collect_keys(definition: &str, value: &toml::Value, keys: &mut BTreeMap<String, BTreeSet<String>>) {
+    let toml::Value::Table(table) = value else {
+        return
+    };
+    for (key, value) in table {
+        keys.entry(definition.to_owned()).or_default().insert(key.clone());
+        let child = match (definition, key.as_str()) {
+            ("protocol", "const") => "constant",
+            ("protocol", "interface") => "interface",
+            ("interface", "enum") => "enum",
+            ("interface", "request") => "request",
+            ("interface", "event") => "event",
+            ("enum", "entry") => "entry",
+            ("request" | "event", "arg") => "arg_table",
+            _ => continue
+        };
+        for item in value.as_array().into_iter().flatten() {
+            collect_keys(child, item, keys);
+        }
+    }
+}
+
+#[test]
+fn schema_matches_parser() {
+    let schema: Value = serde_json::from_str(&schema::json()).unwrap();
+    let definitions = schema["definitions"].as_object().unwrap();
+
+    let mut keys = BTreeMap::new();
+    collect_keys("protocol", &toml::Value::try_from(protocol()).unwrap(), &mut keys);
+    for (definition, keys) in &keys {
+        let properties: BTreeSet<_> = definitions[definition]["properties"].as_object().unwrap().keys()
+            .filter(|property| !ALIASES.iter().any(|(alias, _)| alias == property))
+            .cloned()
+            .collect();
+        assert_eq!(&properties, keys, "the properties of `{}` in the schema differ from its fields", definition);
+    }
+    assert_eq!(keys.len(), definitions.len() - 1, "every definition but the argument shorthand has fields");
+
+    // Each alias parses as the key it is an alias of, rather than as an unknown key
+    let spec = r#"
+        name = "aliases"
+        constants = [{ name = "max", type = "uint", value = 1 }]
+        [[interfaces]]
+        name = "wl_thing"
+        version = 1
+        [[interfaces.enums]]
+        name = "mode"
+        entries = [{ name = "a", value = 1 }]
+        [[interfaces.requests]]
+        name = "set"
+        args = ["x:uint"]
+        [[interfaces.events]]
+        name = "done"
+        args = [{ name = "x", type = "uint" }]
+    "#;
+    let protocol = Protocol::from_str(spec).unwrap();
+    let interface = &protocol.interfaces[0];
+    assert_eq!(protocol.constants.len(), 1);
+    assert_eq!(interface.enums[0].entries.len(), 1);
+    assert_eq!((interface.requests[0].args.len(), interface.events[0].args.len()), (1, 1));
+    for (alias, key) in ALIASES {
+        let definition = definitions.values().find(|d| d["properties"].get(*alias).is_some()).unwrap();
+        assert_eq!(definition["properties"][*alias], definition["properties"][*key], "`{}` differs from `{}`", alias, key);
+    }
+}
@@ -0,0 +1,128 @@
+//! Checks rules of `validate` with a specification that breaks each rule and one that keeps to it.
+
+use yutani_codegen::{validate, CodegenOptions, Protocol, Severity};
+
+/// The errors of an interface with the TOML of its messages and enums, such as `[[interface.request]]` tables.
+fn errors(items: &str) -> Vec<String> {
+    let protocol = Protocol::from_str(&format!("name = \"acme\"\n\n[[interface]]\nname = \"acme_thing\"\nversion = 1\n\n{}", items)).unwrap();
+    validate(&protocol, &CodegenOptions::default())
+        .into_iter()
+        .filter(|d| d.severity == Severity::Error)
+        .map(|d| d.to_string())
+        .collect()
+}
+
+#[test]
+fn argument_names_differ_once_converted() {
+    assert_eq!(errors(r#"
+        [[interface.request]]
+        name = "set"
+        arg = ["surfaceX:int", "surface_x:int"]
+    "#), ["error: acme_thing.set.surface_x: argument is named `surface_x` in Rust, the same as `surfaceX`"]);
+    assert_eq!(errors(r#"
+        [[interface.request]]
+        name = "set"
+        arg = ["x:int", "x:int"]
+    "#), ["error: acme_thing.set.x: argument is defined more than once"]);
+    assert!(errors(r#"
+        [[interface.request]]
+        name = "set"
+        arg = ["surface_x:int", "surface_y:int"]
+    "#).is_empty());
+}
+
+#[test]
+fn bitfield_entries_are_single_bits() {
+    assert_eq!(errors(r#"
+        [[interface.enum]]
+        name = "caps"
+        bitfield = true
+        entry = [{ name = "a", value = 1 }, { name = "b", value = 6 }, { name = "c", value = 12 }]
+    "#), ["error: acme_thing.caps: bitfield entries must be a single bit unless marked as a combination: `b` (6), `c` (12)"]);
+    // Combinations, aliases of single bits and the empty set are exempt
+    assert!(errors(r#"
+        [[interface.enum]]
+        name = "caps"
+        bitfield = true
+        entry = [
+            { name = "none", value = 0 },
+            { name = "a", value = 1 },
+            { name = "b", value = 2 },
+            { name = "both", value = 3, combination = true },
+            { name = "first", alias_of = "a" },
+            { name = "all", alias_of = "both" }
+        ]
+    "#).is_empty());
+}
+
+#[test]
+fn only_strings_and_objects_are_nullable() {
+    assert_eq!(errors(r#"
+        [[interface.request]]
+        name = "set"
+        arg = [
+            { name = "data", type = "array", allow-null = true },
+            { name = "file", type = "fd", allow-null = true }
+        ]
+    "#), [
+        "error: acme_thing.set.data: `array` arguments have no null representation on the wire, only `string` and `object` arguments can allow null",
+        "error: acme_thing.set.file: `fd` arguments have no null representation on the wire, only `string` and `object` arguments can allow null"
+    ]);
+    assert!(errors(r#"
+        [[interface.request]]
+        name = "set"
+        arg = [
+            { name = "title", type = "string", allow-null = true },
+            { name = "parent", type = "object", interface = "acme_thing", allow-null = true }
+        ]
+    "#).is_empty());
+}
+
+#[test]
+fn entries_are_in_the_range_of_the_backing_type() {
+    assert_eq!(errors(r#"
+        [[interface.enum]]
+        name = "mode"
+        entry = [{ name = "big", value = 4294967296 }, { name = "negative", value = -1 }]
+    "#), [
+        "error: acme_thing.mode.big: value 4294967296 is out of range for the backing type `u32`, which is 0 to 4294967295",
+        "error: acme_thing.mode.negative: value -1 is out of range for the backing type `u32`, which is 0 to 4294967295, negative values require the enum to be used by `int` arguments"
+    ]);
+    // Enums used by `int` arguments are signed
+    assert_eq!(errors(r#"
+        [[interface.request]]
+        name = "set"
+        arg = [{ name = "mode", type = "int", enum = "mode" }]
+
+        [[interface.enum]]
+        name = "mode"
+        entry = [{ name = "negative", value = -1 }, { name = "big", value = 4294967295 }]
+    "#), ["error: acme_thing.mode.big: value 4294967295 is out of range for the backing type `i32`, which is -2147483648 to 2147483647"]);
+    assert!(errors(r#"
+        [[interface.request]]
+        name = "set"
+        arg = [{ name = "mode", type = "int", enum = "mode" }]
+
+        [[interface.enum]]
+        name = "mode"
+        entry = [{ name = "min", value = -2147483648 }, { name = "max", value = 2147483647 }]
+
+        [[interface.enum]]
+        name = "flags"
+        entry = [{ name = "max", value = 4294967295 }]
+    "#).is_empty());
+}
+
+#[test]
+fn messages_create_one_object() {
+    assert_eq!(errors(r#"
+        [[interface.request]]
+        name = "create"
+        arg = ["a:new_id<acme_thing>", "b:new_id<acme_thing>", "c:new_id<acme_thing>"]
+    "#), ["error: acme_thing.create: a message can create at most one object, but has the `new_id` arguments `a`, `b` and `c`"]);
+    assert!(errors(r#"
+        [[interface.request]]
+        name = "create"
+        arg = ["a:new_id<acme_thing>", "b:object<acme_thing>"]
+    "#).is_empty());
+}
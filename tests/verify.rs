@@ -0,0 +1,97 @@
+//! Checks that `build::verify` finds checked in code stale only when the code generated now differs, and diffs it
+//! readably when it does.
+
+use std::{env, fs, path::PathBuf};
+
+use yutani_codegen::{
+    build::{verify, VerifyOptions}, generated_with, render, verify_generated, CodegenOptions, Error, LintLevel, Lockfile, Mismatch,
+    Protocol
+};
+
+const SPEC: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/protocol/runtime.toml");
+
+/// Check in `code` at a path of its own, returning the result of verifying it against the code generated now.
+fn verify_code(name: &str, code: &str, verify_options: &VerifyOptions) -> Result<(), Error> {
+    let path = env::temp_dir().join(format!("yutani-codegen-verify-{}-{name}.rs", std::process::id()));
+    fs::write(&path, code).unwrap();
+    let result = verify(SPEC, &path, &CodegenOptions::default(), verify_options);
+    fs::remove_file(&path).unwrap();
+    result
+}
+
+/// The code generated now.
+fn generated() -> String {
+    render(&Protocol::load(PathBuf::from(SPEC)).unwrap(), &CodegenOptions::default()).unwrap()
+}
+
+/// The diff of stale code.
+fn diff(result: Result<(), Error>) -> String {
+    match result {
+        Err(Error::Stale(mismatches)) => match &mismatches[..] {
+            [Mismatch::Contents { diff }] => diff.clone(),
+            mismatches => panic!("expected the contents to differ, not {:?}", mismatches)
+        },
+        result => panic!("expected the code to be stale, not {:?}", result)
+    }
+}
+
+#[test]
+fn fresh() {
+    verify_code("fresh", &generated(), &VerifyOptions::default()).unwrap();
+}
+
+#[test]
+fn stale() {
+    let code = generated().replace("fn r#damage", "fn r#damaged");
+    let diff = diff(verify_code("stale", &code, &VerifyOptions::default()));
+    // The generated code is mostly on one line, so is diffed a statement or item per line
+    let removed: Vec<_> = diff.lines().filter(|line| line.starts_with('-') && !line.starts_with("---")).collect();
+    let added: Vec<_> = diff.lines().filter(|line| line.starts_with('+') && !line.starts_with("+++")).collect();
+    assert_eq!(removed.len(), 1, "{}", diff);
+    assert_eq!(added.len(), 1, "{}", diff);
+    assert!(removed[0].trim_start_matches('-').trim_start().starts_with("fn r#damaged (this"), "{}", diff);
+    assert!(added[0].trim_start_matches('+').trim_start().starts_with("fn r#damage (this"), "{}", diff);
+    assert!(added[0].len() < 400, "{}", diff);
+}
+
+#[test]
+fn markers_are_ignored() {
+    let options = CodegenOptions::default();
+    let code = generated().replace(&generated_with(&options), "yutani-codegen 9.9.9, options-hash 0123456789abcdef");
+    assert_ne!(code, generated());
+    verify_code("markers", &code, &VerifyOptions::default()).unwrap();
+}
+
+#[test]
+fn ignore_formatting() {
+    let code = generated().replace(" ; ", ";\n").replace(" { ", " {\n    ");
+    assert_ne!(code, generated());
+    let ignore_formatting = VerifyOptions { ignore_formatting: true, ..VerifyOptions::default() };
+    verify_code("ignore-formatting", &code, &ignore_formatting).unwrap();
+    // Code only differing in its layout is the same once normalized, so is diffed as it is
+    let layout = diff(verify_code("formatting", &code, &VerifyOptions::default()));
+    assert!(layout.lines().any(|line| line.starts_with('+') && line.contains(" ; ")), "{}", layout);
+    let code = code.replace("fn r#damage", "fn r#damaged");
+    assert!(diff(verify_code("ignore-formatting-stale", &code, &ignore_formatting)).contains("fn r#damaged"));
+}
+
+#[test]
+fn validation_options_keep_the_marker() {
+    let protocol = Protocol::load(PathBuf::from(SPEC)).unwrap();
+    let options = CodegenOptions::default();
+    let validation = CodegenOptions {
+        lockfile: Some(Lockfile::new([&protocol])),
+        deny_warnings: true,
+        strict: true,
+        embed_spec_limit: Some(1),
+        ..CodegenOptions::default().lint(&["naming"], LintLevel::Warn)
+    };
+    assert_eq!(generated_with(&validation), generated_with(&options));
+    let path = env::temp_dir().join(format!("yutani-codegen-verify-{}-marker.rs", std::process::id()));
+    fs::write(&path, generated()).unwrap();
+    let result = verify_generated(&path, &validation);
+    fs::remove_file(&path).unwrap();
+    result.unwrap();
+    // Options that change the generated code change the marker
+    assert_ne!(generated_with(&CodegenOptions { rust_enums: true, ..CodegenOptions::default() }), generated_with(&options));
+}